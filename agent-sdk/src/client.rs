@@ -2,14 +2,26 @@
 
 use async_stream::stream;
 use futures::Stream;
+use std::collections::HashMap;
 use std::pin::Pin;
-use tokio::sync::mpsc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 use tracing::info;
 
 use crate::internal::transport::{
-    ProcessHandle, PromptInput as TransportPromptInput, SubprocessCLITransport,
+    CliInfo, ProcessHandle, StderrTail, DEFAULT_STDERR_BUFFER_LINES, PromptInput as TransportPromptInput, SubprocessCLITransport, Transport,
 };
-use crate::types::{ClaudeAgentOptions, Error, InputMessage, Message, Result};
+use crate::internal::session::{ControlResponder, EventDelivery, EventHub, EventReceiver};
+use crate::types::{
+    ClaudeAgentOptions, Error, InputMessage, McpServerConfig, McpServersConfig, Message,
+    ProcessExit, Result, SdkMcpServer, SystemInit,
+};
+
+/// Default time to wait for the CLI to answer a control request.
+const DEFAULT_CONTROL_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long `disconnect()` waits after SIGTERM before sending SIGKILL.
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
 
 /// Prompt input for client operations.
 pub enum ClientPromptInput {
@@ -21,6 +33,24 @@ pub enum ClientPromptInput {
     None,
 }
 
+impl From<String> for ClientPromptInput {
+    fn from(prompt: String) -> Self {
+        ClientPromptInput::String(prompt)
+    }
+}
+
+impl From<&str> for ClientPromptInput {
+    fn from(prompt: &str) -> Self {
+        ClientPromptInput::String(prompt.to_string())
+    }
+}
+
+impl From<mpsc::Receiver<serde_json::Value>> for ClientPromptInput {
+    fn from(messages: mpsc::Receiver<serde_json::Value>) -> Self {
+        ClientPromptInput::Stream(messages)
+    }
+}
+
 /// Client for bidirectional, interactive conversations with Claude Code.
 ///
 /// This client provides full control over the conversation flow with support
@@ -77,10 +107,24 @@ pub struct ClaudeClient {
     options: ClaudeAgentOptions,
     // Channel to send commands to the session actor
     command_tx: Option<mpsc::Sender<crate::internal::ClientCommand>>,
-    // Channel to receive protocol events (shared with subscribers)
-    event_rx: Option<tokio::sync::broadcast::Receiver<crate::types::ProtocolMessage>>,
+    // Subscribes message streams to the session's protocol events
+    events: Option<EventHub>,
+    // Receiver created with the session; only kept for one-shot queries so an
+    // unread subscriber never holds back lossless delivery
+    event_rx: Option<EventReceiver>,
     stderr_rx: Option<mpsc::Receiver<String>>,
+    // Most recent stderr lines, filled even if `stderr_rx` is never read
+    stderr_tail: Option<StderrTail>,
     process_handle: Option<ProcessHandle>,
+    // Kept for teardown even after the caller takes `process_handle`
+    process: Option<ProcessHandle>,
+    session_task: Option<tokio::task::JoinHandle<()>>,
+    // Custom transport supplied via `with_transport`, consumed by `connect`
+    transport: Option<Box<dyn Transport>>,
+    // Set by the session once the CLI has exited or closed its output
+    exit: Arc<OnceLock<ProcessExit>>,
+    // Detected by the subprocess transport when it connects
+    cli_info: Option<CliInfo>,
 }
 
 impl ClaudeClient {
@@ -92,25 +136,87 @@ impl ClaudeClient {
         Self {
             options,
             command_tx: None,
+            events: None,
             event_rx: None,
             stderr_rx: None,
+            stderr_tail: None,
             process_handle: None,
+            process: None,
+            session_task: None,
+            transport: None,
+            exit: Arc::new(OnceLock::new()),
+            cli_info: None,
         }
     }
 
+    /// Create a client that talks to Claude Code over a custom transport.
+    ///
+    /// The transport is connected by [`connect`](Self::connect). Custom
+    /// transports always use the streaming protocol; a string prompt passed to
+    /// `connect` is sent as the first user message. `stderr_receiver()` and
+    /// `process_handle()` return `None` for custom transports.
+    ///
+    /// # Arguments
+    /// * `options` - Configuration options for the client
+    /// * `transport` - Transport carrying the control protocol
+    pub fn with_transport(options: ClaudeAgentOptions, transport: impl Transport + 'static) -> Self {
+        let mut client = Self::new(options);
+        client.transport = Some(Box::new(transport));
+        client
+    }
+
     /// Connect to Claude with an optional prompt or message stream.
     ///
+    /// A `Stream` prompt is forwarded to the CLI message by message, alongside
+    /// control traffic, until the sender is dropped. The connection stays open
+    /// afterwards for further messages.
+    ///
     /// # Arguments
     /// * `prompt` - Optional initial prompt (string, stream, or None for interactive mode)
     ///
     /// # Errors
     /// Returns an error if connection fails or configuration is invalid
     pub async fn connect(&mut self, prompt: Option<ClientPromptInput>) -> Result<()> {
+        self.connect_with_mode(prompt, false).await
+    }
+
+    /// Connect, optionally in one-shot mode.
+    ///
+    /// In one-shot mode stdin is closed once the prompt has been delivered, so
+    /// the CLI exits after answering. With callbacks registered (hooks,
+    /// `can_use_tool`, SDK MCP servers) stdin stays open until the first
+    /// result, since the CLI answers those over stdin.
+    pub(crate) async fn connect_with_mode(
+        &mut self,
+        prompt: Option<ClientPromptInput>,
+        one_shot: bool,
+    ) -> Result<()> {
+        // Custom transports always speak the streaming protocol
+        let is_streaming = self.transport.is_some()
+            || !matches!(prompt, Some(ClientPromptInput::String(_)));
+
         // Validate configuration
+        let sdk_mcp_servers = self.sdk_mcp_servers();
+        if !sdk_mcp_servers.is_empty() && !is_streaming {
+            return Err(Error::InvalidConfig(
+                "SDK MCP servers require streaming mode. \
+                Please provide prompt as a Stream instead of a String."
+                    .to_string(),
+            ));
+        }
+
+        if self.options.hooks.is_some() && !is_streaming {
+            return Err(Error::InvalidConfig(
+                "hooks require streaming mode. \
+                Please provide prompt as a Stream instead of a String."
+                    .to_string(),
+            ));
+        }
+
         if self.options.can_use_tool.is_some() {
             info!("🔐 can_use_tool callback is set, configuring permission prompt tool");
             // canUseTool callback requires streaming mode
-            if matches!(prompt, Some(ClientPromptInput::String(_))) {
+            if !is_streaming {
                 return Err(Error::InvalidConfig(
                     "can_use_tool callback requires streaming mode. \
                     Please provide prompt as a Stream instead of a String."
@@ -134,50 +240,118 @@ impl ClaudeClient {
             info!("⚠️ can_use_tool callback is NOT set");
         }
 
-        // Create empty stream for interactive mode if no prompt provided
-        let (empty_tx, empty_rx) = mpsc::channel(1);
-        drop(empty_tx); // Close immediately to create empty stream
-
-        let actual_prompt = match prompt {
-            Some(ClientPromptInput::String(s)) => TransportPromptInput::String(s),
-            Some(ClientPromptInput::Stream(rx)) => TransportPromptInput::Stream(rx),
-            Some(ClientPromptInput::None) | None => TransportPromptInput::Stream(empty_rx),
-        };
-
         // Extract callbacks before creating transport
         let can_use_tool = self.options.can_use_tool.take();
-        // Hooks support will be added to AgentSession later if needed, currently unused in refactor
-        let _hooks = self.options.hooks.take();
-
-        // Create and connect transport
-        let mut transport = SubprocessCLITransport::new(actual_prompt, self.options.clone())?;
-        transport.connect().await?;
-
-        // Split transport into independent halves
-        let (read_half, write_half, stderr_half, process_handle) = transport.split()?;
+        let hooks = self.options.hooks.take();
+
+        // Stream prompts are forwarded by the session, not the transport
+        let mut prompt_stream = None;
+        let (mut transport, stderr_rx, stderr_tail, process_handle): (Box<dyn Transport>, _, _, _) =
+            match self.transport.take() {
+                Some(mut transport) => {
+                    prompt_stream = match prompt {
+                        Some(ClientPromptInput::String(s)) => Some(single_message_stream(s)?),
+                        Some(ClientPromptInput::Stream(rx)) => Some(rx),
+                        Some(ClientPromptInput::None) | None => None,
+                    };
+                    transport.connect().await?;
+                    (transport, None, None, None)
+                }
+                None => {
+                    // Create empty stream for interactive mode if no prompt provided
+                    let (empty_tx, empty_rx) = mpsc::channel(1);
+                    drop(empty_tx); // Close immediately to create empty stream
+
+                    let actual_prompt = match prompt {
+                        Some(ClientPromptInput::String(s)) => TransportPromptInput::String(s),
+                        Some(ClientPromptInput::Stream(rx)) => {
+                            prompt_stream = Some(rx);
+                            TransportPromptInput::Stream(empty_rx)
+                        }
+                        Some(ClientPromptInput::None) | None => TransportPromptInput::Stream(empty_rx),
+                    };
+
+                    // Create and connect transport
+                    let mut transport = SubprocessCLITransport::new(actual_prompt, self.options.clone())?;
+                    transport.connect().await?;
+                    self.cli_info = transport.cli_info().cloned();
+
+                    // Stderr and process lifecycle stay with the client; the session
+                    // watches clones of them to report unexpected exits
+                    let (stderr_rx, stderr_tail) = match transport.take_stderr() {
+                        Some(stderr) => {
+                            let (rx, tail) = stderr.read_lines_with_tail(
+                                self.options
+                                    .stderr_buffer_lines
+                                    .unwrap_or(DEFAULT_STDERR_BUFFER_LINES),
+                                self.options.stderr.clone(),
+                            );
+                            (Some(rx), Some(tail))
+                        }
+                        None => (None, None),
+                    };
+                    let process_handle = transport.take_process_handle();
+                    (Box::new(transport), stderr_rx, stderr_tail, process_handle)
+                }
+            };
 
         // Start reading messages
-        let read_rx = read_half.read_messages();
-        let stderr_rx = stderr_half.read_lines();
+        let read_rx = transport.read_messages()?;
 
         // Create channels for Actor communication
         let (command_tx, command_rx) = mpsc::channel(100);
 
         // Create and spawn AgentSession actor
+        let delivery = EventDelivery {
+            capacity: self
+                .options
+                .event_buffer_capacity
+                .unwrap_or(crate::internal::session::DEFAULT_EVENT_BUFFER_CAPACITY),
+            lossless: self.options.lossless_events,
+        };
         let (session, event_rx) = crate::internal::AgentSession::new(
             command_rx,
             read_rx,
-            write_half,
+            transport,
             can_use_tool,
+            hooks,
+            sdk_mcp_servers,
+            delivery,
         );
+        let session = session.with_process(process_handle.clone(), stderr_tail.clone());
+        self.exit = session.exit_state();
+        self.events = Some(session.event_hub());
 
-        tokio::spawn(session.run());
-
+        self.session_task = Some(tokio::spawn(session.run()));
 
         self.command_tx = Some(command_tx);
-        self.event_rx = Some(event_rx);
-        self.stderr_rx = Some(stderr_rx);
-        self.process_handle = Some(process_handle);
+        self.event_rx = one_shot.then_some(event_rx);
+        self.stderr_rx = stderr_rx;
+        self.stderr_tail = stderr_tail;
+        self.process = process_handle.clone();
+        self.process_handle = process_handle;
+
+        // Register hook callbacks with the CLI before any user message is sent
+        if is_streaming {
+            if let Err(e) = self
+                .send_control_command("initialize", crate::internal::ClientCommand::Initialize)
+                .await
+            {
+                self.disconnect().await?;
+                return Err(e);
+            }
+        }
+
+        if let Some(messages) = prompt_stream {
+            self.send_command(crate::internal::ClientCommand::StreamInput {
+                messages,
+                end_input: one_shot,
+            })
+            .await?;
+        } else if one_shot {
+            // String prompts travel on the command line; nothing else will be written
+            self.send_command(crate::internal::ClientCommand::EndInput).await?;
+        }
 
         Ok(())
     }
@@ -218,15 +392,22 @@ impl ClaudeClient {
         Ok(())
     }
 
-    /// Send interrupt signal and return the request_id for tracking the response.
-    pub async fn interrupt(&self) -> Result<String> {
-        let request_id = format!("interrupt_{}", uuid::Uuid::new_v4());
-        self.send_command(crate::internal::ClientCommand::Interrupt(request_id.clone())).await?;
-        Ok(request_id)
+    /// Send interrupt signal and wait until the CLI acknowledges it.
+    pub async fn interrupt(&self) -> Result<()> {
+        self.send_control_command("interrupt", crate::internal::ClientCommand::Interrupt)
+            .await
+            .map(|_| ())
+    }
+
+    /// Take the receiver created with the session, which has buffered every
+    /// message since `connect()`. Subscribers made later may miss early messages.
+    pub(crate) fn take_event_receiver(&mut self) -> Option<EventReceiver> {
+        self.event_rx.take()
     }
 
-    /// Get initialization data from the Claude connection.
-    pub async fn get_server_info(&self) -> Result<Option<serde_json::Value>> {
+    /// Get the session details from the CLI's `init` message, once it has
+    /// arrived.
+    pub async fn get_server_info(&self) -> Result<Option<SystemInit>> {
         let (tx, rx) = tokio::sync::oneshot::channel();
         if let Some(cmd_tx) = &self.command_tx {
             cmd_tx.send(crate::internal::ClientCommand::GetInitData(tx)).await
@@ -238,15 +419,33 @@ impl ClaudeClient {
     }
 
     /// Receive all messages from Claude (as broadcast stream).
+    ///
+    /// If the stream falls more than `event_buffer_capacity` messages behind,
+    /// it yields `Error::Lagged(n)` and continues with newer messages; enable
+    /// `lossless_events` to apply backpressure instead. A stdout line longer
+    /// than `max_buffer_size` is skipped and reported as `Error::MessageParse`.
     pub async fn receive_messages_from_cc_stdout(
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
-        let mut rx = self.event_rx.as_ref()
-            .ok_or_else(|| Error::CLIConnection("Not connected".to_string()))?
-            .resubscribe();
+        let mut rx = self.subscribe()?;
+
+        let exit = self.exit.clone();
 
         let message_stream = stream! {
-            while let Ok(protocol_msg) = rx.recv().await {
+            loop {
+                let protocol_msg = match rx.recv().await {
+                    Some(Ok(msg)) => msg,
+                    Some(Err(e)) => {
+                        yield Err(e);
+                        continue;
+                    }
+                    None => {
+                        if let Some(exit) = exit.get().filter(|exit| !exit.success()) {
+                            yield Err(exit.to_error());
+                        }
+                        break;
+                    }
+                };
                 match crate::internal::protocol_message_to_message(protocol_msg) {
                     Ok(message) => yield Ok(message),
                     Err(e) => {
@@ -268,13 +467,21 @@ impl ClaudeClient {
     pub async fn receive_protocol_messages(
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<crate::types::ProtocolMessage>> + Send>>> {
-        let mut rx = self.event_rx.as_ref()
-            .ok_or_else(|| Error::CLIConnection("Not connected".to_string()))?
-            .resubscribe();
+        let mut rx = self.subscribe()?;
+
+        let exit = self.exit.clone();
 
         let protocol_stream = stream! {
-            while let Ok(protocol_msg) = rx.recv().await {
-                yield Ok(protocol_msg);
+            loop {
+                match rx.recv().await {
+                    Some(item) => yield item,
+                    None => {
+                        if let Some(exit) = exit.get().filter(|exit| !exit.success()) {
+                            yield Err(exit.to_error());
+                        }
+                        break;
+                    }
+                }
             }
         };
 
@@ -282,75 +489,249 @@ impl ClaudeClient {
     }
     
     // Legacy support method for receive_response (kept similar to before)
+    //
+    // Ends after the `result` message. If the CLI goes away first, the last
+    // item is the `Error::Process` describing its exit.
     pub async fn receive_response(
         &self,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
         let mut messages = self.receive_messages_from_cc_stdout().await?;
+        let exit = self.exit.clone();
 
         let response_stream = stream! {
             use futures::StreamExt;
-            while let Some(result) = messages.next().await {
-                match result {
-                    Ok(message) => {
+            loop {
+                match messages.next().await {
+                    Some(Ok(message)) => {
                         let is_result = matches!(message, Message::Result(_));
                         yield Ok(message);
                         if is_result { break; }
                     }
-                    Err(e) => {
+                    // Later messages may still include the result
+                    Some(Err(e @ (Error::Lagged(_) | Error::MessageParse(_)))) => yield Err(e),
+                    Some(Err(e)) => {
                         yield Err(e);
                         break;
                     }
+                    None => {
+                        // A clean exit still ended the turn without a result
+                        if let Some(exit) = exit.get().filter(|exit| exit.success()) {
+                            yield Err(exit.to_error());
+                        }
+                        break;
+                    }
                 }
             }
         };
         Ok(Box::pin(response_stream))
     }
 
+    /// Change the permission mode and wait until the CLI confirms it.
     pub async fn set_permission_mode(&self, mode: &str) -> Result<()> {
-        self.send_command(crate::internal::ClientCommand::SetPermissionMode(mode.to_string())).await
+        let mode = mode.to_string();
+        self.send_control_command("set_permission_mode", |tx| {
+            crate::internal::ClientCommand::SetPermissionMode(mode, tx)
+        })
+        .await
+        .map(|_| ())
     }
 
+    /// Switch the model (`None` restores the default) and wait until the CLI confirms it.
     pub async fn set_model(&self, model: Option<&str>) -> Result<()> {
-        self.send_command(crate::internal::ClientCommand::SetModel(model.map(String::from))).await
+        let model = model.map(String::from);
+        self.send_control_command("set_model", |tx| {
+            crate::internal::ClientCommand::SetModel(model, tx)
+        })
+        .await
+        .map(|_| ())
     }
 
+    /// Rewind tracked files to their state at the given user message.
     pub async fn rewind_files(&self, user_message_id: &str) -> Result<()> {
-        self.send_command(crate::internal::ClientCommand::RewindFiles(user_message_id.to_string())).await
+        let user_message_id = user_message_id.to_string();
+        self.send_control_command("rewind_files", |tx| {
+            crate::internal::ClientCommand::RewindFiles(user_message_id, tx)
+        })
+        .await
+        .map(|_| ())
     }
 
+    /// Stop the session and the CLI, allowing it 5 seconds to exit after SIGTERM.
+    ///
+    /// See [`shutdown`](Self::shutdown).
     pub async fn disconnect(&mut self) -> Result<()> {
-        if let Some(tx) = self.command_tx.take() {
-            let _ = tx.send(crate::internal::ClientCommand::Disconnect).await;
-        }
-        Ok(())
+        self.shutdown(DEFAULT_SHUTDOWN_GRACE).await
+    }
+
+    /// Stop the session and tear down the CLI along with everything it spawned.
+    ///
+    /// Closes stdin, sends SIGTERM to the CLI's process group, then sends
+    /// SIGKILL if it has not exited once `grace` has passed since the call.
+    /// Custom transports are only closed.
+    pub async fn shutdown(&mut self, grace: Duration) -> Result<()> {
+        teardown(
+            self.command_tx.take(),
+            self.session_task.take(),
+            self.process.take(),
+            grace,
+        )
+        .await
     }
 
+    /// Take a channel of stderr lines.
+    ///
+    /// Lines are dropped while the channel is full rather than blocking the
+    /// CLI; use the `stderr` option or [`stderr_lines`](Self::stderr_lines)
+    /// to see every line.
     pub fn stderr_receiver(&mut self) -> Option<mpsc::Receiver<String>> {
         self.stderr_rx.take()
     }
 
+    /// The most recent stderr lines (up to `stderr_buffer_lines`), oldest
+    /// first. Empty for custom transports.
+    pub fn stderr_lines(&self) -> Vec<String> {
+        self.stderr_tail
+            .as_ref()
+            .map(StderrTail::lines)
+            .unwrap_or_default()
+    }
+
     pub fn process_handle(&mut self) -> Option<ProcessHandle> {
         self.process_handle.take()
     }
 
+    /// Version and capabilities of the Claude CLI, detected on connect.
+    ///
+    /// `None` for custom transports, before connecting, or when the version
+    /// check was skipped with `CLAUDE_AGENT_SDK_SKIP_VERSION_CHECK`.
+    pub fn cli_info(&self) -> Option<&CliInfo> {
+        self.cli_info.as_ref()
+    }
+
+    /// How the CLI ended, once it has exited or closed its output stream.
+    ///
+    /// After this is set, pending and new requests fail with the
+    /// corresponding `Error::Process`.
+    pub fn process_exit(&self) -> Option<ProcessExit> {
+        self.exit.get().cloned()
+    }
+
+    // Collect in-process MCP servers, keyed by the name the CLI will use
+    fn sdk_mcp_servers(&self) -> HashMap<String, Arc<SdkMcpServer>> {
+        let mut servers = HashMap::new();
+        if let McpServersConfig::Map(ref configs) = self.options.mcp_servers {
+            for (name, config) in configs {
+                if let McpServerConfig::Sdk { instance: Some(instance), .. } = config {
+                    servers.insert(name.clone(), instance.clone());
+                }
+            }
+        }
+        servers
+    }
+
+    // Helper to subscribe a new message stream
+    fn subscribe(&self) -> Result<EventReceiver> {
+        let events = self
+            .events
+            .as_ref()
+            .ok_or_else(|| Error::CLIConnection("Not connected".to_string()))?;
+        events.subscribe().ok_or_else(|| match self.exit.get() {
+            Some(exit) => exit.to_error(),
+            None => Error::CLIConnection("Session closed".to_string()),
+        })
+    }
+
     // Helper to send commands
     async fn send_command(&self, cmd: crate::internal::ClientCommand) -> Result<()> {
         if let Some(tx) = &self.command_tx {
-            tx.send(cmd).await.map_err(|_| Error::CLIConnection("Actor closed".to_string()))
+            tx.send(cmd).await.map_err(|_| match self.exit.get() {
+                Some(exit) => exit.to_error(),
+                None => Error::CLIConnection("Actor closed".to_string()),
+            })
         } else {
             Err(Error::CLIConnection("Not connected".to_string()))
         }
     }
+
+    // Helper to send a control request and await the correlated response
+    async fn send_control_command(
+        &self,
+        subtype: &str,
+        make_cmd: impl FnOnce(ControlResponder) -> crate::internal::ClientCommand,
+    ) -> Result<Option<serde_json::Value>> {
+        let (tx, rx) = oneshot::channel();
+        self.send_command(make_cmd(tx)).await?;
+
+        let timeout = self
+            .options
+            .control_request_timeout
+            .unwrap_or(DEFAULT_CONTROL_REQUEST_TIMEOUT);
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(match self.exit.get() {
+                Some(exit) => exit.to_error(),
+                None => Error::CLIConnection(format!(
+                    "Session closed before {} was answered",
+                    subtype
+                )),
+            }),
+            Err(_) => Err(Error::Timeout(format!(
+                "No response to {} control request after {:?}",
+                subtype, timeout
+            ))),
+        }
+    }
 }
 
 
+// Wrap a string prompt as a one-message stream for the streaming protocol
+fn single_message_stream(prompt: String) -> Result<mpsc::Receiver<serde_json::Value>> {
+    let (tx, rx) = mpsc::channel(1);
+    let message = serde_json::to_value(InputMessage::user(prompt, "default".to_string()))?;
+    let _ = tx.try_send(message);
+    Ok(rx)
+}
+
+// Close the session (which closes stdin), then stop the process group
+async fn teardown(
+    command_tx: Option<mpsc::Sender<crate::internal::ClientCommand>>,
+    session_task: Option<tokio::task::JoinHandle<()>>,
+    process: Option<ProcessHandle>,
+    grace: Duration,
+) -> Result<()> {
+    let started = tokio::time::Instant::now();
+    if let Some(tx) = command_tx {
+        let _ = tx.send(crate::internal::ClientCommand::Disconnect).await;
+    }
+    if let Some(task) = session_task {
+        let _ = tokio::time::timeout(grace, task).await;
+    }
+    if let Some(mut process) = process {
+        // `grace` covers the whole teardown, not each step
+        process.shutdown(grace.saturating_sub(started.elapsed())).await?;
+    }
+    Ok(())
+}
+
 // Implement Drop to ensure cleanup
 impl Drop for ClaudeClient {
     fn drop(&mut self) {
-        // Note: We can't call async methods in Drop
-        // Users should call disconnect() explicitly
-        if self.command_tx.is_some() {
-            eprintln!("Warning: ClaudeClient dropped without calling disconnect()");
+        // Best-effort teardown; call disconnect() to wait for it
+        if self.command_tx.is_none() && self.process.is_none() {
+            return;
+        }
+        let command_tx = self.command_tx.take();
+        let session_task = self.session_task.take();
+        let process = self.process.take();
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(teardown(command_tx, session_task, process, DEFAULT_SHUTDOWN_GRACE));
+            }
+            Err(_) => {
+                if let Some(process) = process {
+                    process.start_kill();
+                }
+            }
         }
     }
 }
@@ -368,12 +749,16 @@ mod tests {
 
     #[tokio::test]
     async fn test_connect_without_prompt() {
-        let options = ClaudeAgentOptions::new();
-        let mut client = ClaudeClient::new(options);
-
-        // This will fail without a real CLI, but tests the structure
-        let result = client.connect(None).await;
-        // Just verify it compiles and returns a Result
-        let _ = result;
+        let (transport, handle) = crate::testing::MockCli::new()
+            .expect(serde_json::json!({
+                "type": "control_request",
+                "request": {"subtype": "initialize"}
+            }))
+            .build();
+        let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+
+        client.connect(None).await.unwrap();
+        handle.finish().await.unwrap();
+        client.disconnect().await.unwrap();
     }
 }
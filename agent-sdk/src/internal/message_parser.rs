@@ -1,11 +1,6 @@
 //! Message parser for Claude Code SDK responses.
 
-use crate::types::{
-    AssistantMessage, ContentBlock, Error, Message, MessageContent, ProtocolMessage, Result,
-    ResultMessage, StreamEvent, SystemMessage, UserMessage,
-};
-
-
+use crate::types::{AssistantMessage, Error, Message, ProtocolMessage, Result, UserMessage};
 
 /// Convert ProtocolMessage to Message type.
 ///
@@ -22,11 +17,12 @@ use crate::types::{
 /// Returns `Error::MessageParse` if the protocol message cannot be converted
 pub fn protocol_message_to_message(protocol_msg: ProtocolMessage) -> Result<Message> {
     match protocol_msg {
-        ProtocolMessage::User { message, parent_tool_use_id, uuid, .. } => {
+        ProtocolMessage::User { message, parent_tool_use_id, uuid, tool_use_result, .. } => {
             Ok(Message::User(UserMessage {
                 content: message.content,
                 uuid,
                 parent_tool_use_id,
+                tool_use_result,
             }))
         }
         ProtocolMessage::Assistant { message, parent_tool_use_id, .. } => {
@@ -46,5 +42,8 @@ pub fn protocol_message_to_message(protocol_msg: ProtocolMessage) -> Result<Mess
         ProtocolMessage::ControlResponse { .. } => Err(Error::MessageParse(
             "ControlResponse should not be converted to Message".to_string(),
         )),
+        ProtocolMessage::ControlCancelRequest { .. } => Err(Error::MessageParse(
+            "ControlCancelRequest should not be converted to Message".to_string(),
+        )),
     }
 }
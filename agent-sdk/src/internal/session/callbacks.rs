@@ -0,0 +1,196 @@
+//! Answering the CLI's control requests with the user's callbacks.
+//!
+//! Each request runs on its own task so a slow callback (a permission prompt
+//! waiting on a human, say) never holds up the session actor. The answer is
+//! written back through the shared transport.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+use super::mcp;
+use crate::internal::transport::Transport;
+use crate::types::{
+    CanUseTool, CancellationSignal, Error, HookCallback, HookContext, HookInput, PermissionResult,
+    Result, SDKControlRequestType, SDKControlResponse, SdkMcpServer, ToolPermissionContext,
+};
+
+/// Cancellation tokens of requests still being answered, by request ID.
+pub(crate) type InFlight = Arc<StdMutex<HashMap<String, CancellationToken>>>;
+
+/// The callbacks that can answer control requests.
+pub(crate) struct Callbacks {
+    pub(crate) can_use_tool: Option<Box<dyn CanUseTool>>,
+    pub(crate) hooks: HashMap<String, Box<dyn HookCallback>>,
+    pub(crate) sdk_mcp_servers: HashMap<String, Arc<SdkMcpServer>>,
+}
+
+impl Callbacks {
+    /// Whether any callback may need stdin to stay open for its answer.
+    pub(crate) fn any(&self) -> bool {
+        self.can_use_tool.is_some() || !self.hooks.is_empty() || !self.sdk_mcp_servers.is_empty()
+    }
+
+    async fn answer(
+        &self,
+        request: SDKControlRequestType,
+        signal: CancellationSignal,
+    ) -> Result<serde_json::Value> {
+        match request {
+            SDKControlRequestType::CanUseTool {
+                tool_name,
+                input,
+                tool_use_id,
+                permission_suggestions,
+                blocked_path,
+                agent_id,
+            } => {
+                let context = ToolPermissionContext {
+                    signal: Some(signal),
+                    suggestions: permission_suggestions.unwrap_or_default(),
+                    tool_use_id,
+                    blocked_path,
+                    agent_id,
+                };
+                self.handle_permission_request(&tool_name, &input, &context).await
+            }
+            SDKControlRequestType::HookCallback { callback_id, input, tool_use_id } => {
+                self.handle_hook_callback(&callback_id, &input, tool_use_id, signal).await
+            }
+            SDKControlRequestType::McpMessage { server_name, message } => {
+                Ok(self.handle_mcp_message(&server_name, &message).await)
+            }
+            _ => {
+                warn!("Unsupported request type: {:?}", std::mem::discriminant(&request));
+                Err(Error::ControlProtocol("Unsupported control request".to_string()))
+            }
+        }
+    }
+
+    async fn handle_permission_request(
+        &self,
+        tool_name: &str,
+        input: &serde_json::Value,
+        context: &ToolPermissionContext,
+    ) -> Result<serde_json::Value> {
+        let can_use_tool = self.can_use_tool.as_ref().ok_or_else(|| {
+            Error::ControlProtocol("canUseTool callback not provided".to_string())
+        })?;
+
+        let result = can_use_tool.can_use(tool_name, input, context).await
+            .map_err(|e| Error::ControlProtocol(e.to_string()))?;
+
+        let response = match result {
+            PermissionResult::Allow(allow) => {
+                let mut json = serde_json::json!({
+                    "behavior": "allow",
+                    "updatedInput": allow.updated_input.unwrap_or_else(|| input.clone()),
+                });
+
+                if let Some(permissions) = allow.updated_permissions {
+                    json["updatedPermissions"] = serde_json::to_value(permissions).unwrap();
+                }
+                json
+            }
+            PermissionResult::Deny(deny) => {
+                serde_json::json!({
+                    "behavior": "deny",
+                    "message": deny.message,
+                    "interrupt": deny.interrupt
+                })
+            }
+        };
+
+        Ok(response)
+    }
+
+    async fn handle_hook_callback(
+        &self,
+        callback_id: &str,
+        input: &serde_json::Value,
+        tool_use_id: Option<String>,
+        signal: CancellationSignal,
+    ) -> Result<serde_json::Value> {
+        let callback = self.hooks.get(callback_id).ok_or_else(|| {
+            Error::ControlProtocol(format!("Hook callback not found: {}", callback_id))
+        })?;
+
+        let hook_input: HookInput = serde_json::from_value(input.clone())?;
+        let context = HookContext { signal: Some(signal) };
+        let output = callback.call(hook_input, tool_use_id, context).await?;
+
+        Ok(serde_json::to_value(&output)?)
+    }
+
+    /// Route an `mcp_message` to the named SDK MCP server.
+    async fn handle_mcp_message(
+        &self,
+        server_name: &str,
+        message: &serde_json::Value,
+    ) -> serde_json::Value {
+        let response = match self.sdk_mcp_servers.get(server_name) {
+            Some(server) => mcp::handle_mcp_message(server, message).await,
+            None => mcp::server_not_found(server_name, message),
+        };
+        serde_json::json!({ "mcp_response": response })
+    }
+}
+
+/// Answer `request` on a new task.
+///
+/// The request is tracked in `in_flight` until answered so the CLI can cancel
+/// it. When `cancel` fires first the callback is dropped and the CLI gets an
+/// error response instead, so it never waits on a request we gave up on.
+pub(crate) fn spawn_answer(
+    callbacks: Arc<Callbacks>,
+    transport: Arc<Mutex<Box<dyn Transport>>>,
+    in_flight: InFlight,
+    request_id: String,
+    request: SDKControlRequestType,
+    cancel: CancellationToken,
+    session_id: Option<String>,
+) {
+    let (tool_use_id, session_id) = match &request {
+        SDKControlRequestType::CanUseTool { tool_use_id, .. } => (tool_use_id.clone(), session_id),
+        // Hook input names its own session, which may be a subagent's
+        SDKControlRequestType::HookCallback { tool_use_id, input, .. } => (
+            tool_use_id.clone(),
+            input["session_id"].as_str().map(str::to_string).or(session_id),
+        ),
+        _ => (None, session_id),
+    };
+    let signal = CancellationSignal::for_request(cancel.clone(), tool_use_id, session_id);
+    in_flight.lock().unwrap().insert(request_id.clone(), cancel.clone());
+
+    tokio::spawn(async move {
+        let result = tokio::select! {
+            // Poll the callback first so it always sees the request, even one
+            // that was cancelled before this task got to run
+            biased;
+            result = callbacks.answer(request, signal) => result,
+            _ = cancel.cancelled() => Err(Error::ControlProtocol("Request cancelled".to_string())),
+        };
+        in_flight.lock().unwrap().remove(&request_id);
+
+        // Always answer, otherwise the CLI waits on the request forever
+        let response = match result {
+            Ok(response_data) => SDKControlResponse::success(request_id, response_data),
+            Err(e) => {
+                error!("Control request {} failed: {}", request_id, e);
+                SDKControlResponse::error(request_id, e.to_string())
+            }
+        };
+        let line = match serde_json::to_string(&response) {
+            Ok(json) => json + "\n",
+            Err(e) => {
+                error!("Failed to serialize control response: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = transport.lock().await.write(&line).await {
+            warn!("Failed to send control response: {}", e);
+        }
+    });
+}
@@ -0,0 +1,235 @@
+//! Fan-out of protocol messages from the session actor to subscribers.
+//!
+//! Messages go through a `broadcast` channel. In the default (lossy) mode a
+//! subscriber that falls more than `capacity` messages behind skips ahead and
+//! sees `Error::Lagged`. In lossless mode the session stops reading stdout
+//! while the slowest subscriber's buffer is full, so backpressure reaches the
+//! CLI and nothing is dropped. It keeps handling commands meanwhile.
+
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Notify;
+
+use crate::types::{Error, ProtocolMessage, Result};
+
+// A message, or a description of stdout the transport could not deliver
+type Event = std::result::Result<ProtocolMessage, String>;
+
+/// Default number of messages buffered per subscriber.
+pub const DEFAULT_EVENT_BUFFER_CAPACITY: usize = 100;
+
+/// How the session delivers protocol messages to subscribers.
+#[derive(Debug, Clone, Copy)]
+pub struct EventDelivery {
+    /// Messages buffered per subscriber.
+    pub capacity: usize,
+    /// Wait for slow subscribers instead of letting them lag.
+    pub lossless: bool,
+}
+
+impl Default for EventDelivery {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_EVENT_BUFFER_CAPACITY,
+            lossless: false,
+        }
+    }
+}
+
+/// Sending side, owned by the session actor.
+pub(crate) struct EventPublisher {
+    tx: broadcast::Sender<Event>,
+    space: Arc<Notify>,
+    delivery: EventDelivery,
+}
+
+impl EventPublisher {
+    pub(crate) fn new(delivery: EventDelivery) -> (Self, EventReceiver) {
+        let capacity = delivery.capacity.max(1);
+        let (tx, rx) = broadcast::channel(capacity);
+        let space = Arc::new(Notify::new());
+        let receiver = EventReceiver {
+            rx,
+            space: space.clone(),
+        };
+        let publisher = Self {
+            tx,
+            space,
+            delivery: EventDelivery { capacity, ..delivery },
+        };
+        (publisher, receiver)
+    }
+
+    /// Handle for subscribing later. It does not keep the channel open, so
+    /// subscribers still see it close when the session stops.
+    pub(crate) fn hub(&self) -> EventHub {
+        EventHub {
+            tx: self.tx.downgrade(),
+            space: self.space.clone(),
+        }
+    }
+
+    /// Whether one more message can be published without any subscriber
+    /// lagging. Always true in lossy mode.
+    pub(crate) fn has_space(&self) -> bool {
+        // `len` counts messages not yet seen by every live subscriber
+        !self.delivery.lossless
+            || self.tx.receiver_count() == 0
+            || self.tx.len() < self.delivery.capacity
+    }
+
+    /// Wait until [`has_space`](Self::has_space) holds.
+    pub(crate) async fn wait_for_space(&self) {
+        while !self.has_space() {
+            self.space.notified().await;
+        }
+    }
+
+    pub(crate) fn publish(&self, msg: ProtocolMessage) {
+        self.send(Ok(msg));
+    }
+
+    /// Tell subscribers a message was lost, e.g. a stdout line was too long.
+    pub(crate) fn publish_read_error(&self, description: String) {
+        self.send(Err(description));
+    }
+
+    // Callers in lossless mode check `has_space` first
+    fn send(&self, event: Event) {
+        let _ = self.tx.send(event);
+    }
+}
+
+/// Creates subscriptions to a running session's messages.
+#[derive(Clone)]
+pub struct EventHub {
+    tx: broadcast::WeakSender<Event>,
+    space: Arc<Notify>,
+}
+
+impl EventHub {
+    /// Subscribe to messages published from now on, or `None` once the
+    /// session has stopped.
+    pub fn subscribe(&self) -> Option<EventReceiver> {
+        self.tx.upgrade().map(|tx| EventReceiver {
+            rx: tx.subscribe(),
+            space: self.space.clone(),
+        })
+    }
+}
+
+/// A subscription to the session's protocol messages.
+///
+/// Consuming a message (or dropping the receiver) wakes a session waiting for
+/// buffer space in lossless mode.
+pub struct EventReceiver {
+    rx: broadcast::Receiver<Event>,
+    space: Arc<Notify>,
+}
+
+impl EventReceiver {
+    /// Receive the next message, or `None` once the session stopped.
+    ///
+    /// Yields `Error::Lagged(n)` when `n` messages were dropped for this
+    /// subscriber, and `Error::MessageParse` for output the transport could
+    /// not deliver. Receiving can continue after either.
+    pub async fn recv(&mut self) -> Option<Result<ProtocolMessage>> {
+        let result = self.rx.recv().await;
+        self.space.notify_one();
+        match result {
+            Ok(Ok(msg)) => Some(Ok(msg)),
+            Ok(Err(description)) => Some(Err(Error::MessageParse(description))),
+            Err(RecvError::Lagged(skipped)) => Some(Err(Error::Lagged(skipped))),
+            Err(RecvError::Closed) => None,
+        }
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.space.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn message(n: usize) -> ProtocolMessage {
+        serde_json::from_value(serde_json::json!({
+            "type": "system",
+            "subtype": "status",
+            "n": n
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lossy_subscriber_sees_lagged() {
+        let (publisher, mut rx) = EventPublisher::new(EventDelivery {
+            capacity: 2,
+            lossless: false,
+        });
+        for n in 0..5 {
+            publisher.publish(message(n));
+        }
+        assert!(matches!(rx.recv().await, Some(Err(Error::Lagged(3)))));
+        assert!(matches!(rx.recv().await, Some(Ok(_))));
+    }
+
+    #[tokio::test]
+    async fn test_lossless_publisher_waits_for_subscriber() {
+        let (publisher, mut rx) = EventPublisher::new(EventDelivery {
+            capacity: 2,
+            lossless: true,
+        });
+        let producer = tokio::spawn(async move {
+            for n in 0..10 {
+                publisher.wait_for_space().await;
+                publisher.publish(message(n));
+            }
+        });
+
+        // The producer cannot run ahead of the subscriber
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!producer.is_finished());
+
+        for _ in 0..10 {
+            rx.recv().await.unwrap().unwrap();
+        }
+        producer.await.unwrap();
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lossless_space_ignores_dropped_subscribers() {
+        let (publisher, rx) = EventPublisher::new(EventDelivery {
+            capacity: 1,
+            lossless: true,
+        });
+        publisher.publish(message(0));
+        assert!(!publisher.has_space());
+        drop(rx);
+        assert!(publisher.has_space());
+        publisher.wait_for_space().await;
+    }
+
+    #[tokio::test]
+    async fn test_read_error_is_delivered_in_order() {
+        let (publisher, mut rx) = EventPublisher::new(EventDelivery::default());
+        publisher.publish_read_error("line too long".to_string());
+        publisher.publish(message(0));
+        assert!(matches!(rx.recv().await, Some(Err(Error::MessageParse(msg))) if msg == "line too long"));
+        assert!(matches!(rx.recv().await, Some(Ok(_))));
+    }
+
+    #[tokio::test]
+    async fn test_hub_does_not_keep_channel_open() {
+        let (publisher, _rx) = EventPublisher::new(EventDelivery::default());
+        let hub = publisher.hub();
+        assert!(hub.subscribe().is_some());
+        drop(publisher);
+        assert!(hub.subscribe().is_none());
+    }
+}
@@ -0,0 +1,176 @@
+//! JSON-RPC handling for in-process SDK MCP servers.
+//!
+//! The CLI forwards MCP traffic for `type: "sdk"` servers as `mcp_message`
+//! control requests. Each message is a JSON-RPC request or notification that
+//! we answer on behalf of the matching [`SdkMcpServer`].
+
+use serde_json::{json, Value};
+
+use crate::types::SdkMcpServer;
+
+/// MCP protocol version advertised in the `initialize` response.
+const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Answer one JSON-RPC message addressed to an SDK MCP server.
+pub(crate) async fn handle_mcp_message(server: &SdkMcpServer, message: &Value) -> Value {
+    let id = message.get("id").cloned().unwrap_or(Value::Null);
+    let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+    let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+    match method {
+        "initialize" => success(
+            id,
+            json!({
+                "protocolVersion": MCP_PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": {
+                    "name": server.name,
+                    "version": server.version,
+                },
+            }),
+        ),
+        "notifications/initialized" => success(id, json!({})),
+        "tools/list" => {
+            let tools: Vec<Value> = server
+                .tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "inputSchema": tool.input_schema,
+                    })
+                })
+                .collect();
+            success(id, json!({ "tools": tools }))
+        }
+        "tools/call" => {
+            let Some(name) = params.get("name").and_then(Value::as_str) else {
+                return error(id, INVALID_PARAMS, "Missing tool name".to_string());
+            };
+            let Some(tool) = server.get_tool(name) else {
+                return error(id, INVALID_PARAMS, format!("Tool '{}' not found", name));
+            };
+
+            let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+            match tool.handler.call(arguments).await {
+                Ok(result) => match serde_json::to_value(result) {
+                    Ok(result) => success(id, result),
+                    Err(e) => error(id, INTERNAL_ERROR, e.to_string()),
+                },
+                Err(e) => error(id, INTERNAL_ERROR, e.to_string()),
+            }
+        }
+        _ => error(id, METHOD_NOT_FOUND, format!("Method '{}' not found", method)),
+    }
+}
+
+/// JSON-RPC error for a message addressed to an unregistered server.
+pub(crate) fn server_not_found(server_name: &str, message: &Value) -> Value {
+    let id = message.get("id").cloned().unwrap_or(Value::Null);
+    error(id, METHOD_NOT_FOUND, format!("Server '{}' not found", server_name))
+}
+
+fn success(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn error(id: Value, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Error, McpToolResult};
+
+    fn ticket_server() -> SdkMcpServer {
+        SdkMcpServer::new("tickets")
+            .with_version("2.0.0")
+            .tool(
+                "lookup",
+                "Look up a ticket",
+                json!({"type": "object", "properties": {"id": {"type": "string"}}}),
+                |args: Value| async move {
+                    Ok(McpToolResult::text(format!("ticket {} is open", args["id"].as_str().unwrap())))
+                },
+            )
+            .tool("fail", "Always fails", json!({"type": "object"}), |_args: Value| async {
+                Err(Error::ToolFailed("backend unavailable".to_string()))
+            })
+    }
+
+    #[tokio::test]
+    async fn test_initialize() {
+        let response = handle_mcp_message(
+            &ticket_server(),
+            &json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}),
+        )
+        .await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["protocolVersion"], MCP_PROTOCOL_VERSION);
+        assert_eq!(response["result"]["serverInfo"]["name"], "tickets");
+        assert_eq!(response["result"]["serverInfo"]["version"], "2.0.0");
+    }
+
+    #[tokio::test]
+    async fn test_tools_list() {
+        let response = handle_mcp_message(
+            &ticket_server(),
+            &json!({"jsonrpc": "2.0", "id": 2, "method": "tools/list"}),
+        )
+        .await;
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["name"], "lookup");
+        assert_eq!(tools[0]["inputSchema"]["properties"]["id"]["type"], "string");
+    }
+
+    #[tokio::test]
+    async fn test_tools_call() {
+        let server = ticket_server();
+        let response = handle_mcp_message(
+            &server,
+            &json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "tools/call",
+                "params": {"name": "lookup", "arguments": {"id": "T-42"}}
+            }),
+        )
+        .await;
+        assert_eq!(response["result"]["content"][0]["text"], "ticket T-42 is open");
+
+        let response = handle_mcp_message(
+            &server,
+            &json!({"jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": {"name": "fail"}}),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], INTERNAL_ERROR);
+
+        let response = handle_mcp_message(
+            &server,
+            &json!({"jsonrpc": "2.0", "id": 5, "method": "tools/call", "params": {"name": "missing"}}),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_method() {
+        let response = handle_mcp_message(
+            &ticket_server(),
+            &json!({"jsonrpc": "2.0", "id": 6, "method": "resources/list"}),
+        )
+        .await;
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -4,17 +4,37 @@
 //! It handles the state and protocol logic in a dedicated task, communicating
 //! via channels.
 
+mod callbacks;
+mod events;
+mod mcp;
+
+use callbacks::{Callbacks, InFlight};
+pub use events::{EventDelivery, EventHub, EventReceiver, DEFAULT_EVENT_BUFFER_CAPACITY};
+
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use std::process::ExitStatus;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch, Mutex};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
-use crate::internal::transport::WriteHalf;
+use crate::internal::transport::{ProcessHandle, StderrTail, Transport};
 use crate::types::{
-    CanUseTool, Error, HookCallback, HookContext, HookInput, PermissionResult,
-    ProtocolMessage, Result, SDKControlRequestType, SDKControlResponse, ToolPermissionContext,
+    CanUseTool, Error, HookCallback, HookConfig, HookEvent, ProcessExit, ProtocolMessage, Result,
+    SDKControlRequestType, SDKControlResponseType, SdkMcpServer, SystemEvent, SystemInit,
 };
-use tokio::process::ChildStdin;
+
+/// How long to wait for the process to exit after it closed stdout.
+const EXIT_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Number of stderr lines attached to process exit errors.
+const EXIT_STDERR_LINES: usize = 20;
+/// How long to keep reading stdout/stderr after the process exited.
+const EXIT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Resolves with the `response` payload of the matching `control_response`,
+/// or with `Error::ControlProtocol` when the CLI answers with an error.
+pub type ControlResponder = oneshot::Sender<Result<Option<serde_json::Value>>>;
 
 /// Commands sent from Client to Session Actor
 #[derive(Debug)]
@@ -24,56 +44,125 @@ pub enum ClientCommand {
         session_id: String,
     },
     SendInputMessage(crate::types::InputMessage), // New variant
-    SetModel(Option<String>),
-    SetPermissionMode(String),
-    Interrupt(String),  // request_id generated by caller
-    RewindFiles(String),
+    SetModel(Option<String>, ControlResponder),
+    SetPermissionMode(String, ControlResponder),
+    Interrupt(ControlResponder),
+    RewindFiles(String, ControlResponder),
+    /// Send the `initialize` control request carrying the registered hooks
+    Initialize(ControlResponder),
+    /// Close the transport's input side (stdin) so the CLI sees end of input
+    EndInput,
+    /// Forward a stream of input messages to the CLI as they arrive.
+    ///
+    /// With `end_input`, input is closed once the stream ends (or after the
+    /// first result, when callbacks still need stdin to answer the CLI).
+    StreamInput {
+        messages: mpsc::Receiver<serde_json::Value>,
+        end_input: bool,
+    },
     Disconnect,
-    GetInitData(tokio::sync::oneshot::Sender<Option<serde_json::Value>>),
+    GetInitData(tokio::sync::oneshot::Sender<Option<SystemInit>>),
 }
 
 /// The Agent Session Actor
 pub struct AgentSession {
     // Communication channels
     command_rx: mpsc::Receiver<ClientCommand>,
-    protocol_rx: mpsc::Receiver<ProtocolMessage>,
-    event_tx: broadcast::Sender<ProtocolMessage>,
+    protocol_rx: mpsc::Receiver<Result<ProtocolMessage>>,
+    events: events::EventPublisher,
+    // Number of `result` messages seen so far
+    results_tx: watch::Sender<usize>,
     
     // Transport output
-    write_half: Arc<Mutex<WriteHalf<ChildStdin>>>,
+    transport: Arc<Mutex<Box<dyn Transport>>>,
     
     // State
     request_counter: usize,
-    init_data: Option<serde_json::Value>,
+    init_data: Option<SystemInit>,
+    // Control requests we sent, awaiting the CLI's control_response
+    pending_requests: HashMap<String, ControlResponder>,
     
     // Hooks and Callbacks
-    hook_callbacks: HashMap<String, Box<dyn HookCallback>>,
-    can_use_tool: Option<Box<dyn CanUseTool>>,
+    hooks_config: Option<HashMap<HookEvent, serde_json::Value>>,
+    callbacks: Arc<Callbacks>,
+    // Cancelled on disconnect; parent of `turn`
+    shutdown: CancellationToken,
+    // Cancelled on interrupt; parent of every outstanding callback
+    turn: CancellationToken,
+    // Control requests from the CLI still being answered
+    in_flight: InFlight,
+    // Latest session ID reported by the CLI
+    session_id: Option<String>,
+
+    // Process watching
+    process: Option<ProcessHandle>,
+    stderr_tail: Option<StderrTail>,
+    exit: Arc<OnceLock<ProcessExit>>,
 }
 
 impl AgentSession {
     pub fn new(
         command_rx: mpsc::Receiver<ClientCommand>,
-        protocol_rx: mpsc::Receiver<ProtocolMessage>,
-        write_half: WriteHalf<ChildStdin>,
+        protocol_rx: mpsc::Receiver<Result<ProtocolMessage>>,
+        transport: Box<dyn Transport>,
         can_use_tool: Option<Box<dyn CanUseTool>>,
-    ) -> (Self, broadcast::Receiver<ProtocolMessage>) {
-        let (event_tx, event_rx) = broadcast::channel(100);
+        hooks: Option<HookConfig>,
+        sdk_mcp_servers: HashMap<String, Arc<SdkMcpServer>>,
+        delivery: EventDelivery,
+    ) -> (Self, EventReceiver) {
+        let (events, event_rx) = events::EventPublisher::new(delivery);
         
+        let mut hook_callbacks = HashMap::new();
+        let hooks_config = hooks.map(|hooks| register_hooks(hooks, &mut hook_callbacks));
+
+        let shutdown = CancellationToken::new();
         let session = Self {
             command_rx,
             protocol_rx,
-            event_tx,
-            write_half: Arc::new(Mutex::new(write_half)),
+            events,
+            results_tx: watch::Sender::new(0),
+            transport: Arc::new(Mutex::new(transport)),
             request_counter: 0,
             init_data: None,
-            hook_callbacks: HashMap::new(),
-            can_use_tool,
+            pending_requests: HashMap::new(),
+            hooks_config,
+            callbacks: Arc::new(Callbacks {
+                can_use_tool,
+                hooks: hook_callbacks,
+                sdk_mcp_servers,
+            }),
+            turn: shutdown.child_token(),
+            shutdown,
+            in_flight: InFlight::default(),
+            session_id: None,
+            process: None,
+            stderr_tail: None,
+            exit: Arc::new(OnceLock::new()),
         };
         
         (session, event_rx)
     }
 
+    /// Watch the CLI process so an unexpected exit ends the session with its
+    /// exit status and stderr tail.
+    pub fn with_process(mut self, process: Option<ProcessHandle>, stderr_tail: Option<StderrTail>) -> Self {
+        self.process = process;
+        self.stderr_tail = stderr_tail;
+        self
+    }
+
+    /// Handle for subscribing to the messages this session publishes.
+    pub fn event_hub(&self) -> EventHub {
+        self.events.hub()
+    }
+
+    /// Slot filled with the [`ProcessExit`] once the CLI has gone away.
+    ///
+    /// Subscribers see the event channel close afterwards; this tells them why.
+    pub fn exit_state(&self) -> Arc<OnceLock<ProcessExit>> {
+        self.exit.clone()
+    }
+
     /// Run the session actor loop
     pub async fn run(mut self) {
         info!("🚀 AgentSession actor started");
@@ -91,43 +180,110 @@ impl AgentSession {
                     }
                 }
 
+                // In lossless mode, stop reading stdout while a subscriber's
+                // buffer is full; commands are still served meanwhile
+                _ = self.events.wait_for_space(), if !self.events.has_space() => {}
+
                 // Handle protocol messages from Transport
-                Some(msg) = self.protocol_rx.recv() => {
-                    if let Err(e) = self.handle_protocol_message(msg).await {
-                        error!("Error handling protocol message: {}", e);
+                msg = self.protocol_rx.recv(), if self.events.has_space() => match msg {
+                    Some(msg) => self.handle_transport_item(msg).await,
+                    None => {
+                        info!("🛑 Claude Code closed its output stream");
+                        let status = match self.process.as_mut() {
+                            Some(process) => tokio::time::timeout(EXIT_WAIT_TIMEOUT, process.wait())
+                                .await
+                                .ok()
+                                .and_then(|status| status.ok()),
+                            None => None,
+                        };
+                        self.record_exit(status).await;
+                        break;
                     }
-                }
+                },
 
-                // All channels closed - exit gracefully
-                else => {
-                    info!("🛑 All channels closed, exiting session loop");
+                // The process exited; drain what it wrote before going away
+                status = wait_for_exit(&mut self.process) => {
+                    info!("🛑 Claude Code exited: {:?}", status);
+                    let deadline = tokio::time::Instant::now() + EXIT_DRAIN_TIMEOUT;
+                    loop {
+                        let (events, protocol_rx) = (&self.events, &mut self.protocol_rx);
+                        let next = tokio::time::timeout_at(deadline, async move {
+                            events.wait_for_space().await;
+                            protocol_rx.recv().await
+                        });
+                        match next.await {
+                            Ok(Some(msg)) => self.handle_transport_item(msg).await,
+                            _ => break,
+                        }
+                    }
+                    self.record_exit(status).await;
                     break;
                 }
             }
         }
 
+        // Stop waiting on callbacks nobody will read the answer to
+        self.shutdown.cancel();
+        if let Err(e) = self.transport.lock().await.close().await {
+            warn!("Failed to close transport: {}", e);
+        }
+
         info!("🛑 AgentSession actor stopped");
     }
 
+    async fn handle_transport_item(&mut self, item: Result<ProtocolMessage>) {
+        match item {
+            Ok(msg) => {
+                if let Err(e) = self.handle_protocol_message(msg).await {
+                    error!("Error handling protocol message: {}", e);
+                }
+            }
+            // The transport has already resynchronised; let subscribers know
+            // a message was lost
+            Err(e) => {
+                warn!("Transport could not deliver a message: {}", e);
+                let description = match e {
+                    Error::MessageParse(description) => description,
+                    other => other.to_string(),
+                };
+                self.events.publish_read_error(description);
+            }
+        }
+    }
+
     async fn handle_protocol_message(&mut self, msg: ProtocolMessage) -> Result<()> {
+        self.observe_session_id(&msg);
         match msg {
             ProtocolMessage::ControlRequest { request_id, request } => {
-                self.handle_control_request(request_id, request).await?;
+                self.handle_control_request(request_id, request);
+            }
+            ProtocolMessage::ControlCancelRequest { request_id } => {
+                debug!("🚫 CLI cancelled control request {}", request_id);
+                if let Some(cancel) = self.in_flight.lock().unwrap().get(&request_id) {
+                    cancel.cancel();
+                }
             }
             ProtocolMessage::System(ref system) if system.subtype == "init" => {
                 // Capture init data
-                self.init_data = Some(serde_json::Value::Object(system.extra.clone()));
+                if let SystemEvent::Init(init) = system.event() {
+                    self.init_data = Some(*init);
+                }
                 // Forward to subscribers
-                let _ = self.event_tx.send(msg);
+                self.events.publish(msg);
             }
-            // Forward control responses to subscribers for request-response tracking
+            // Complete the pending request, then forward to subscribers as well
             ProtocolMessage::ControlResponse { ref response } => {
                 debug!("📨 Received control_response: {:?}", response);
-                let _ = self.event_tx.send(msg);
+                complete_pending_request(&mut self.pending_requests, response.clone());
+                self.events.publish(msg);
+            }
+            ProtocolMessage::Result(_) => {
+                self.results_tx.send_modify(|count| *count += 1);
+                self.events.publish(msg);
             }
             _ => {
                 // Forward other messages to subscribers
-                let _ = self.event_tx.send(msg);
+                self.events.publish(msg);
             }
         }
         Ok(())
@@ -142,30 +298,45 @@ impl AgentSession {
             ClientCommand::SendInputMessage(msg) => {
                 self.send_json(&msg).await?;
             }
-            ClientCommand::SetModel(model) => {
+            ClientCommand::SetModel(model, responder) => {
                 let req = serde_json::json!({
                     "subtype": "set_model",
                     "model": model
                 });
-                let _ = self.send_control_request(req).await?;
+                self.send_control_request(req, responder).await;
             }
-            ClientCommand::SetPermissionMode(mode) => {
+            ClientCommand::SetPermissionMode(mode, responder) => {
                 let req = serde_json::json!({
                     "subtype": "set_permission_mode",
                     "mode": mode
                 });
-                let _ = self.send_control_request(req).await?;
+                self.send_control_request(req, responder).await;
             }
-            ClientCommand::Interrupt(request_id) => {
+            ClientCommand::Interrupt(responder) => {
+                // Callbacks for the interrupted turn are moot
+                self.turn.cancel();
+                self.turn = self.shutdown.child_token();
                 let req = serde_json::json!({ "subtype": "interrupt" });
-                self.send_control_request_with_id(&request_id, req).await?;
+                self.send_control_request(req, responder).await;
             }
-            ClientCommand::RewindFiles(id) => {
+            ClientCommand::RewindFiles(id, responder) => {
                 let req = serde_json::json!({
                     "subtype": "rewind_files",
                     "user_message_id": id
                 });
-                let _ = self.send_control_request(req).await?;
+                self.send_control_request(req, responder).await;
+            }
+            ClientCommand::Initialize(responder) => {
+                let req = SDKControlRequestType::Initialize {
+                    hooks: self.hooks_config.take(),
+                };
+                self.send_control_request(serde_json::to_value(&req)?, responder).await;
+            }
+            ClientCommand::EndInput => {
+                self.transport.lock().await.end_input().await?;
+            }
+            ClientCommand::StreamInput { messages, end_input } => {
+                self.spawn_input_forwarder(messages, end_input);
             }
             ClientCommand::GetInitData(tx) => {
                 let _ = tx.send(self.init_data.clone());
@@ -177,106 +348,311 @@ impl AgentSession {
         Ok(())
     }
 
-    async fn handle_control_request(&mut self, request_id: String, request: SDKControlRequestType) -> Result<()> {
+    /// Answer a control request from the CLI on its own task, so the actor
+    /// keeps handling commands and messages while callbacks run.
+    fn handle_control_request(&self, request_id: String, request: SDKControlRequestType) {
         info!("🔧 Processing control_request: {:?}", std::mem::discriminant(&request));
+        callbacks::spawn_answer(
+            self.callbacks.clone(),
+            self.transport.clone(),
+            self.in_flight.clone(),
+            request_id,
+            request,
+            self.turn.child_token(),
+            self.session_id.clone(),
+        );
+    }
 
-        let response_data = match request {
-            SDKControlRequestType::CanUseTool { tool_name, input, .. } => {
-                self.handle_permission_request(&tool_name, &input).await?
-            }
-            SDKControlRequestType::HookCallback { callback_id, input, tool_use_id } => {
-                self.handle_hook_callback(&callback_id, &input, tool_use_id).await?
-            }
-            _ => {
-                warn!("Unsupported request type: {:?}", std::mem::discriminant(&request));
-                return Ok(()); // Ignore unsupported requests
+    // Remember the session ID so callbacks' signals can report it
+    fn observe_session_id(&mut self, msg: &ProtocolMessage) {
+        let session_id = match msg {
+            ProtocolMessage::System(system) => system.extra.get("session_id").and_then(|id| id.as_str()),
+            ProtocolMessage::Assistant { session_id, .. } | ProtocolMessage::User { session_id, .. } => {
+                session_id.as_deref()
             }
+            ProtocolMessage::Result(result) => Some(result.session_id.as_str()),
+            _ => None,
         };
-
-        let response = SDKControlResponse::success(request_id, response_data);
-        self.send_json(&response).await?;
-        
-        Ok(())
+        if let Some(session_id) = session_id {
+            if self.session_id.as_deref() != Some(session_id) {
+                self.session_id = Some(session_id.to_string());
+            }
+        }
     }
 
-    async fn handle_permission_request(
-        &self,
-        tool_name: &str,
-        input: &serde_json::Value,
-    ) -> Result<serde_json::Value> {
-        let can_use_tool = self.can_use_tool.as_ref().ok_or_else(|| {
-            Error::ControlProtocol("canUseTool callback not provided".to_string())
-        })?;
-
-        let context = ToolPermissionContext {
-            signal: None,
-            suggestions: vec![],
-        };
-
-        let result = can_use_tool.can_use(tool_name, input, &context).await
-            .map_err(|e| Error::ControlProtocol(e.to_string()))?;
+    /// Forward input messages on a separate task so control traffic keeps
+    /// flowing while the caller's stream is still producing.
+    fn spawn_input_forwarder(&self, mut messages: mpsc::Receiver<serde_json::Value>, end_input: bool) {
+        let transport = self.transport.clone();
+        // Callbacks are answered over stdin, so keep it open until the turn ends.
+        // Take the result count now so an early result is not missed.
+        let has_callbacks = self.callbacks.any();
+        let mut results = (end_input && has_callbacks).then(|| self.results_tx.subscribe());
 
-        let response = match result {
-            PermissionResult::Allow(allow) => {
-                let mut json = serde_json::json!({
-                    "behavior": "allow",
-                    "updatedInput": allow.updated_input.unwrap_or_else(|| input.clone()),
-                });
-
-                if let Some(permissions) = allow.updated_permissions {
-                    json["updatedPermissions"] = serde_json::to_value(permissions).unwrap();
+        tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                let line = format!("{}\n", message);
+                if let Err(e) = transport.lock().await.write(&line).await {
+                    warn!("Failed to forward input message: {}", e);
+                    return;
                 }
-                json
             }
-            PermissionResult::Deny(deny) => {
-                serde_json::json!({
-                    "behavior": "deny",
-                    "message": deny.message,
-                    "interrupt": deny.interrupt
-                })
+            if !end_input {
+                return;
             }
-        };
 
-        Ok(response)
+            // Any result counted after subscribing ends the turn
+            if let Some(results) = results.as_mut() {
+                if results.changed().await.is_err() {
+                    return;
+                }
+            }
+            debug!("Input stream finished, closing input");
+            if let Err(e) = transport.lock().await.end_input().await {
+                warn!("Failed to close input: {}", e);
+            }
+        });
     }
 
-    async fn handle_hook_callback(
-        &mut self,
-        callback_id: &str,
-        input: &serde_json::Value,
-        tool_use_id: Option<String>,
-    ) -> Result<serde_json::Value> {
-        let callback = self.hook_callbacks.get(callback_id).ok_or_else(|| {
-            Error::ControlProtocol(format!("Hook callback not found: {}", callback_id))
-        })?;
-
-        let hook_input: HookInput = serde_json::from_value(input.clone())?;
-        let context = HookContext { signal: None };
-        let output = callback.call(hook_input, tool_use_id, context).await?;
-        
-        Ok(serde_json::to_value(&output)?)
+    /// Record why the CLI went away and fail everything still waiting on it.
+    async fn record_exit(&mut self, status: Option<ExitStatus>) {
+        let stderr_tail = match &self.stderr_tail {
+            Some(tail) => {
+                tail.wait_closed(EXIT_DRAIN_TIMEOUT).await;
+                tail.last(EXIT_STDERR_LINES)
+            }
+            None => Vec::new(),
+        };
+        let exit = ProcessExit { status, stderr_tail };
+        if !exit.success() {
+            warn!("{}", exit);
+        }
+
+        for (_, responder) in self.pending_requests.drain() {
+            let _ = responder.send(Err(exit.to_error()));
+        }
+        let _ = self.exit.set(exit);
     }
 
-    async fn send_control_request(&mut self, request_data: serde_json::Value) -> Result<String> {
+    /// Send a control request and park its responder until the CLI answers.
+    ///
+    /// Write failures are reported through the responder rather than returned,
+    /// so the caller awaiting the response always gets an outcome. A broken
+    /// pipe is left to the exit handling, which reports why the CLI went away.
+    async fn send_control_request(&mut self, request_data: serde_json::Value, responder: ControlResponder) {
         self.request_counter += 1;
         let request_id = format!("req_{}_{}", self.request_counter, uuid::Uuid::new_v4());
-        self.send_control_request_with_id(&request_id, request_data).await?;
-        Ok(request_id)
-    }
-
-    async fn send_control_request_with_id(&mut self, request_id: &str, request_data: serde_json::Value) -> Result<()> {
         let control_request = serde_json::json!({
             "type": "control_request",
             "request_id": request_id,
             "request": request_data
         });
-        self.send_json(&control_request).await
+
+        // Callers that timed out dropped their receiver; forget those requests
+        self.pending_requests.retain(|_, tx| !tx.is_closed());
+
+        match self.send_json(&control_request).await {
+            Ok(()) => {
+                self.pending_requests.insert(request_id, responder);
+            }
+            // The CLI is going away; its exit fails the request with a better diagnostic
+            Err(Error::Io(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                debug!("Input closed while sending {}: {}", request_id, e);
+                self.pending_requests.insert(request_id, responder);
+            }
+            Err(e) => {
+                let _ = responder.send(Err(e));
+            }
+        }
     }
 
     async fn send_json<T: serde::Serialize>(&self, data: &T) -> Result<()> {
         let json_str = serde_json::to_string(data)? + "\n";
-        let mut writer = self.write_half.lock().await;
-        writer.write(&json_str).await?;
+        let mut transport = self.transport.lock().await;
+        transport.write(&json_str).await?;
         Ok(())
     }
 }
+
+/// Resolve when the watched process exits; pending forever when there is none.
+async fn wait_for_exit(process: &mut Option<ProcessHandle>) -> Option<ExitStatus> {
+    match process {
+        Some(process) => process.wait().await.ok(),
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolve the pending request a `control_response` answers, if any.
+fn complete_pending_request(
+    pending_requests: &mut HashMap<String, ControlResponder>,
+    response: SDKControlResponseType,
+) {
+    let (request_id, result) = match response {
+        SDKControlResponseType::Success { request_id, response } => (request_id, Ok(response)),
+        SDKControlResponseType::Error { request_id, error } => {
+            (request_id, Err(Error::ControlProtocol(error)))
+        }
+    };
+
+    match pending_requests.remove(&request_id) {
+        Some(responder) => {
+            let _ = responder.send(result);
+        }
+        None => debug!("No pending request for control_response {}", request_id),
+    }
+}
+
+/// Register hook callbacks under generated callback IDs.
+///
+/// Returns the hooks section of the `initialize` request, where each matcher
+/// references its callbacks by ID. The CLI later sends `hook_callback` control
+/// requests carrying one of these IDs.
+fn register_hooks(
+    hooks: HookConfig,
+    hook_callbacks: &mut HashMap<String, Box<dyn HookCallback>>,
+) -> HashMap<HookEvent, serde_json::Value> {
+    let mut config = HashMap::new();
+
+    for (event, matchers) in hooks {
+        let mut matcher_configs = Vec::with_capacity(matchers.len());
+
+        for matcher in matchers {
+            let mut callback_ids = Vec::with_capacity(matcher.hooks.len());
+            for callback in matcher.hooks {
+                let callback_id = format!("hook_{}", hook_callbacks.len());
+                hook_callbacks.insert(callback_id.clone(), callback);
+                callback_ids.push(callback_id);
+            }
+
+            let mut matcher_config = serde_json::json!({
+                "matcher": matcher.matcher,
+                "hookCallbackIds": callback_ids,
+            });
+            if let Some(timeout) = matcher.timeout {
+                matcher_config["timeout"] = serde_json::json!(timeout);
+            }
+            matcher_configs.push(matcher_config);
+        }
+
+        config.insert(event, serde_json::Value::Array(matcher_configs));
+    }
+
+    info!("🪝 Registered {} hook callbacks", hook_callbacks.len());
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{HookContext, HookInput, HookJSONOutput, HookMatcher, SyncHookJSONOutput};
+    use async_trait::async_trait;
+
+    struct NoopHook;
+
+    #[async_trait]
+    impl HookCallback for NoopHook {
+        async fn call(
+            &self,
+            _input: HookInput,
+            _tool_use_id: Option<String>,
+            _context: HookContext,
+        ) -> Result<HookJSONOutput> {
+            Ok(HookJSONOutput::Sync(SyncHookJSONOutput {
+                continue_: Some(true),
+                suppress_output: None,
+                stop_reason: None,
+                decision: None,
+                system_message: None,
+                reason: None,
+                hook_specific_output: None,
+            }))
+        }
+    }
+
+    #[test]
+    fn test_register_hooks_assigns_callback_ids() {
+        let mut hooks: HookConfig = HashMap::new();
+        hooks.insert(
+            HookEvent::PreToolUse,
+            vec![HookMatcher {
+                matcher: Some("Bash".to_string()),
+                hooks: vec![Box::new(NoopHook), Box::new(NoopHook)],
+                timeout: Some(30.0),
+            }],
+        );
+
+        let mut callbacks = HashMap::new();
+        let config = register_hooks(hooks, &mut callbacks);
+
+        assert_eq!(callbacks.len(), 2);
+        assert!(callbacks.contains_key("hook_0"));
+        assert!(callbacks.contains_key("hook_1"));
+
+        let matcher = &config[&HookEvent::PreToolUse][0];
+        assert_eq!(matcher["matcher"], "Bash");
+        assert_eq!(matcher["hookCallbackIds"], serde_json::json!(["hook_0", "hook_1"]));
+        assert_eq!(matcher["timeout"], 30.0);
+    }
+
+    #[test]
+    fn test_initialize_request_serialization() {
+        let mut callbacks = HashMap::new();
+        let mut hooks: HookConfig = HashMap::new();
+        hooks.insert(
+            HookEvent::PostToolUse,
+            vec![HookMatcher {
+                matcher: None,
+                hooks: vec![Box::new(NoopHook)],
+                timeout: None,
+            }],
+        );
+
+        let req = SDKControlRequestType::Initialize {
+            hooks: Some(register_hooks(hooks, &mut callbacks)),
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["subtype"], "initialize");
+        assert_eq!(json["hooks"]["PostToolUse"][0]["hookCallbackIds"][0], "hook_0");
+        assert!(json["hooks"]["PostToolUse"][0].get("timeout").is_none());
+    }
+
+    #[test]
+    fn test_complete_pending_request() {
+        let mut pending = HashMap::new();
+        let (ok_tx, mut ok_rx) = oneshot::channel();
+        let (err_tx, mut err_rx) = oneshot::channel();
+        pending.insert("req_1".to_string(), ok_tx);
+        pending.insert("req_2".to_string(), err_tx);
+
+        complete_pending_request(
+            &mut pending,
+            SDKControlResponseType::Success {
+                request_id: "req_1".to_string(),
+                response: Some(serde_json::json!({"model": "sonnet"})),
+            },
+        );
+        complete_pending_request(
+            &mut pending,
+            SDKControlResponseType::Error {
+                request_id: "req_2".to_string(),
+                error: "unknown model".to_string(),
+            },
+        );
+        // Responses nobody is waiting for are ignored
+        complete_pending_request(
+            &mut pending,
+            SDKControlResponseType::Success {
+                request_id: "req_3".to_string(),
+                response: None,
+            },
+        );
+
+        assert!(pending.is_empty());
+        let ok = ok_rx.try_recv().unwrap().unwrap();
+        assert_eq!(ok.unwrap()["model"], "sonnet");
+        match err_rx.try_recv().unwrap() {
+            Err(Error::ControlProtocol(msg)) => assert_eq!(msg, "unknown model"),
+            other => panic!("expected control protocol error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Transport abstraction for the Claude Code control protocol.
+//!
+//! A transport carries newline-delimited JSON between the SDK and a Claude Code
+//! process. [`SubprocessCLITransport`](super::SubprocessCLITransport) spawns the
+//! CLI locally; [`StreamTransport`](super::StreamTransport) runs the protocol
+//! over any async byte stream (Unix socket, TCP relay, in-memory duplex pipe).
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use crate::types::{ProtocolMessage, Result};
+
+/// Bidirectional channel to a Claude Code process.
+///
+/// The session actor calls [`connect`](Transport::connect) once, takes the
+/// incoming message stream with [`read_messages`](Transport::read_messages),
+/// and then writes JSON lines until it calls [`close`](Transport::close).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use claude_agent_sdk::internal::transport::StreamTransport;
+/// use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let socket = tokio::net::UnixStream::connect("/tmp/claude.sock").await?;
+///     let (reader, writer) = socket.into_split();
+///
+///     let transport = StreamTransport::new(reader, writer);
+///     let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+///     client.connect(None).await?;
+///     Ok(())
+/// }
+/// ```
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Establish the connection (spawn the process, open the socket, ...).
+    async fn connect(&mut self) -> Result<()>;
+
+    /// Take the stream of protocol messages coming from Claude Code.
+    ///
+    /// Called once after `connect()`. The channel closes when the other side
+    /// reaches end of stream. An `Err` item reports output that could not be
+    /// turned into a message (for example a line over the size limit); it is
+    /// passed on to message streams as `Error::MessageParse` and reading
+    /// continues.
+    fn read_messages(&mut self) -> Result<mpsc::Receiver<Result<ProtocolMessage>>>;
+
+    /// Write raw data, typically one JSON message terminated by a newline.
+    async fn write(&mut self, data: &str) -> Result<()>;
+
+    /// Close the input side so Claude Code sees end of stream.
+    async fn end_input(&mut self) -> Result<()>;
+
+    /// Close the transport and release its resources.
+    async fn close(&mut self) -> Result<()>;
+
+    /// Check if the transport is ready for communication.
+    fn is_ready(&self) -> bool;
+}
@@ -1,13 +1,19 @@
 //! Transport implementations for Claude SDK.
 
+mod base;
 mod process_handle;
 mod read_half;
 mod stderr_half;
+mod stream;
 pub mod subprocess;
+mod version;
 mod write_half;
 
+pub use base::Transport;
 pub use process_handle::ProcessHandle;
-pub use read_half::ReadHalf;
-pub use stderr_half::StderrHalf;
-pub use subprocess::{PromptInput, SubprocessCLITransport};
+pub use read_half::{ReadHalf, DEFAULT_MAX_BUFFER_SIZE};
+pub use stderr_half::{StderrHalf, StderrTail, DEFAULT_STDERR_BUFFER_LINES};
+pub use stream::StreamTransport;
+pub use subprocess::{PromptInput, SubprocessCLITransport, MINIMUM_CLAUDE_CODE_VERSION};
+pub use version::{CliCapabilities, CliInfo, CliVersion, SKIP_VERSION_CHECK_ENV};
 pub use write_half::WriteHalf;
@@ -3,8 +3,15 @@
 //! This module provides a safe wrapper around the subprocess that allows
 //! controlling its lifecycle without direct access to the underlying process.
 
+use std::process::ExitStatus;
+use std::time::Duration;
+
 use crate::types::{Error, Result};
 use tokio::process::Child;
+use tokio::sync::{mpsc, watch};
+
+/// Exit status published by the monitor task once the process has been reaped.
+type ExitState = Option<std::result::Result<ExitStatus, String>>;
 
 /// Handle for managing a subprocess.
 ///
@@ -12,6 +19,10 @@ use tokio::process::Child;
 /// without exposing the underlying Child object. This allows safe concurrent
 /// access to process management operations while other tasks handle I/O.
 ///
+/// The child is owned by a background task that reaps it, so the handle is
+/// cheap to clone: the session actor keeps a clone to detect unexpected exits
+/// while the caller keeps another for kill/wait.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -40,24 +51,71 @@ use tokio::process::Child;
 ///     Ok(())
 /// }
 /// ```
+#[derive(Debug, Clone)]
 pub struct ProcessHandle {
-    child: Child,
+    pid: Option<u32>,
+    // Set when the child leads its own process group, so signals also reach
+    // everything it spawned (Bash tools, MCP stdio servers, ...)
+    process_group: Option<u32>,
+    kill_tx: mpsc::Sender<()>,
+    exit_rx: watch::Receiver<ExitState>,
 }
 
 impl ProcessHandle {
     /// Create a new process handle from a Child process.
     ///
+    /// Spawns the task that waits on the child, so this must be called from
+    /// within a Tokio runtime.
+    ///
     /// # Arguments
     ///
     /// * `child` - The tokio Child process to wrap
     pub fn new(child: Child) -> Self {
-        Self { child }
+        Self::spawn_monitor(child, false)
+    }
+
+    /// Create a handle for a child spawned as the leader of its own process
+    /// group (`Command::process_group(0)`).
+    ///
+    /// `kill()` and `shutdown()` then signal the whole group.
+    pub fn new_group_leader(child: Child) -> Self {
+        Self::spawn_monitor(child, true)
+    }
+
+    fn spawn_monitor(mut child: Child, group_leader: bool) -> Self {
+        let pid = child.id();
+        let (kill_tx, mut kill_rx) = mpsc::channel(1);
+        let (exit_tx, exit_rx) = watch::channel(None);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    status = child.wait() => {
+                        let _ = exit_tx.send(Some(status.map_err(|e| e.to_string())));
+                        break;
+                    }
+                    Some(()) = kill_rx.recv() => {
+                        if let Err(e) = child.start_kill() {
+                            tracing::warn!("Failed to kill process: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+
+        Self {
+            pid,
+            process_group: if group_leader { pid } else { None },
+            kill_tx,
+            exit_rx,
+        }
     }
 
     /// Terminate the process forcefully.
     ///
-    /// Sends a SIGKILL signal to the process. This is a forceful termination
-    /// and the process will not have a chance to clean up.
+    /// Sends a SIGKILL signal to the process (and its process group, for a
+    /// group leader). This is a forceful termination and the process will not
+    /// have a chance to clean up.
     ///
     /// # Errors
     ///
@@ -74,10 +132,79 @@ impl ProcessHandle {
     /// # }
     /// ```
     pub async fn kill(&mut self) -> Result<()> {
-        self.child
-            .kill()
-            .await
-            .map_err(|e| Error::Process(format!("Failed to kill process: {}", e)))
+        self.start_kill();
+        self.wait().await.map(|_| ())
+    }
+
+    /// Send SIGKILL without waiting for the process to exit.
+    pub fn start_kill(&self) {
+        self.signal(Signal::Kill);
+    }
+
+    /// Stop the process gracefully, then forcefully.
+    ///
+    /// Sends SIGTERM, waits up to `grace` for the process (and the rest of its
+    /// process group) to exit, then sends SIGKILL to whatever is left.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use claude_agent_sdk::internal::transport::ProcessHandle;
+    /// # use std::time::Duration;
+    /// # async fn example(mut handle: ProcessHandle) -> Result<(), Box<dyn std::error::Error>> {
+    /// let status = handle.shutdown(Duration::from_secs(5)).await?;
+    /// println!("Process exited with: {:?}", status);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(&mut self, grace: Duration) -> Result<ExitStatus> {
+        self.signal(Signal::Terminate);
+
+        let deadline = tokio::time::Instant::now() + grace;
+        let _ = tokio::time::timeout_at(deadline, self.wait()).await;
+        while self.group_alive() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        if self.exit_rx.borrow().is_none() || self.group_alive() {
+            tracing::debug!("Process did not stop within {:?}, sending SIGKILL", grace);
+            self.signal(Signal::Kill);
+        }
+        self.wait().await
+    }
+
+    fn signal(&self, signal: Signal) {
+        #[cfg(unix)]
+        {
+            let signo = match signal {
+                Signal::Terminate => libc::SIGTERM,
+                Signal::Kill => libc::SIGKILL,
+            };
+            if let Some(pgid) = self.process_group {
+                // SAFETY: killpg has no memory-safety preconditions
+                unsafe { libc::killpg(pgid as libc::pid_t, signo) };
+                return;
+            }
+            if let (Signal::Terminate, Some(pid)) = (signal, self.id()) {
+                // SAFETY: kill has no memory-safety preconditions
+                unsafe { libc::kill(pid as libc::pid_t, signo) };
+                return;
+            }
+        }
+        if matches!(signal, Signal::Kill) && self.exit_rx.borrow().is_none() {
+            // The monitor only goes away once the process has been reaped
+            let _ = self.kill_tx.try_send(());
+        }
+    }
+
+    // Whether any process in the group is still running
+    fn group_alive(&self) -> bool {
+        #[cfg(unix)]
+        if let Some(pgid) = self.process_group {
+            // SAFETY: signal 0 only checks for existence
+            return unsafe { libc::killpg(pgid as libc::pid_t, 0) } == 0;
+        }
+        self.exit_rx.borrow().is_none()
     }
 
     /// Wait for the process to exit and return its status.
@@ -107,11 +234,13 @@ impl ProcessHandle {
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn wait(&mut self) -> Result<std::process::ExitStatus> {
-        self.child
-            .wait()
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        let state = self
+            .exit_rx
+            .wait_for(Option::is_some)
             .await
-            .map_err(|e| Error::Process(format!("Failed to wait for process: {}", e)))
+            .map_err(|_| Error::Process("Process monitor stopped".to_string()))?;
+        exit_state_to_result(&state).expect("wait_for returned an exited state")
     }
 
     /// Check if the process has exited without blocking.
@@ -140,10 +269,8 @@ impl ProcessHandle {
     /// # Ok(())
     /// # }
     /// ```
-    pub fn try_wait(&mut self) -> Result<Option<std::process::ExitStatus>> {
-        self.child
-            .try_wait()
-            .map_err(|e| Error::Process(format!("Failed to check process status: {}", e)))
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        exit_state_to_result(&self.exit_rx.borrow()).transpose()
     }
 
     /// Get the process ID.
@@ -166,6 +293,81 @@ impl ProcessHandle {
     /// # }
     /// ```
     pub fn id(&self) -> Option<u32> {
-        self.child.id()
+        if self.exit_rx.borrow().is_some() {
+            return None;
+        }
+        self.pid
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Signal {
+    Terminate,
+    Kill,
+}
+
+fn exit_state_to_result(state: &ExitState) -> Option<Result<ExitStatus>> {
+    state.as_ref().map(|result| {
+        result
+            .clone()
+            .map_err(|e| Error::Process(format!("Failed to wait for process: {}", e)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_clones_observe_exit() {
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let mut handle = ProcessHandle::new(child);
+        let mut watcher = handle.clone();
+
+        assert_eq!(watcher.wait().await.unwrap().code(), Some(3));
+        assert_eq!(handle.try_wait().unwrap().unwrap().code(), Some(3));
+        assert!(handle.id().is_none());
+    }
+
+    #[cfg(unix)]
+    fn spawn_group(script: &str) -> Child {
+        tokio::process::Command::new("sh")
+            .args(["-c", script])
+            .stdout(std::process::Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_escalates_to_sigkill() {
+        use std::os::unix::process::ExitStatusExt;
+
+        use tokio::io::AsyncBufReadExt;
+
+        let mut child = spawn_group("trap '' TERM; echo ready; sleep 30");
+        let stdout = child.stdout.take().unwrap();
+        let mut lines = tokio::io::BufReader::new(stdout).lines();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("ready"));
+
+        let mut handle = ProcessHandle::new_group_leader(child);
+        let status = handle.shutdown(Duration::from_millis(100)).await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    #[tokio::test]
+    async fn test_kill() {
+        let child = tokio::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let mut handle = ProcessHandle::new(child);
+        assert!(handle.try_wait().unwrap().is_none());
+
+        handle.kill().await.unwrap();
+        assert!(!handle.wait().await.unwrap().success());
+        // Killing an exited process is a no-op
+        handle.kill().await.unwrap();
     }
 }
@@ -2,11 +2,20 @@
 //!
 //! This module provides a wrapper for reading JSON messages from the subprocess stdout.
 //! It spawns a background task that continuously reads and parses JSON lines.
+//!
+//! Lines are read with a size limit: an oversized line is discarded up to the
+//! next newline and reported as `Error::MessageParse`, so a single huge tool
+//! result cannot exhaust memory or end the stream. Invalid UTF-8 is replaced
+//! rather than treated as fatal.
 
 use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::sync::mpsc;
 
-use crate::types::ProtocolMessage;
+use crate::types::{Error, ProtocolMessage, Result};
+
+/// Default limit for a single stdout line (1MB), used when
+/// `ClaudeAgentOptions::max_buffer_size` is not set.
+pub const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024;
 
 /// Read half for subprocess stdout.
 ///
@@ -43,6 +52,7 @@ use crate::types::ProtocolMessage;
 /// ```
 pub struct ReadHalf<R: AsyncRead + Unpin + Send> {
     reader: BufReader<R>,
+    max_line_size: usize,
 }
 
 impl<R: AsyncRead + Unpin + Send + 'static> ReadHalf<R> {
@@ -52,8 +62,14 @@ impl<R: AsyncRead + Unpin + Send + 'static> ReadHalf<R> {
     ///
     /// * `reader` - An async reader (typically stdout from a subprocess)
     pub fn new(reader: R) -> Self {
+        Self::with_max_line_size(reader, DEFAULT_MAX_BUFFER_SIZE)
+    }
+
+    /// Create a read half that rejects lines longer than `max_line_size` bytes.
+    pub fn with_max_line_size(reader: R, max_line_size: usize) -> Self {
         Self {
             reader: BufReader::new(reader),
+            max_line_size,
         }
     }
 
@@ -61,11 +77,13 @@ impl<R: AsyncRead + Unpin + Send + 'static> ReadHalf<R> {
     ///
     /// This method spawns a background task that continuously reads lines
     /// from stdout, parses them as JSON, and sends them through the channel.
-    /// The task runs until EOF is reached or the receiver is dropped.
+    /// The task runs until EOF, a read error, or the receiver is dropped.
+    /// Lines that are not valid protocol messages are logged and skipped.
     ///
     /// # Returns
     ///
-    /// A receiver that yields `ProtocolMessage` messages. The channel has
+    /// A receiver that yields parsed `ProtocolMessage`s, or
+    /// `Error::MessageParse` for a line over the size limit. The channel has
     /// a buffer size of 100 messages.
     ///
     /// # Example
@@ -83,23 +101,45 @@ impl<R: AsyncRead + Unpin + Send + 'static> ReadHalf<R> {
     /// }
     /// # }
     /// ```
-    pub fn read_messages(self) -> mpsc::Receiver<ProtocolMessage> {
+    pub fn read_messages(self) -> mpsc::Receiver<Result<ProtocolMessage>> {
         let (tx, rx) = mpsc::channel(100);
-        let reader = self.reader;
+        let mut reader = self.reader;
+        let max_line_size = self.max_line_size;
 
         tokio::spawn(async move {
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                tracing::info!("📥 [STDOUT] Received from Claude Code:\n{}", line);
-                match serde_json::from_str::<ProtocolMessage>(&line) {
-                    Ok(msg) => {
-                        if tx.send(msg).await.is_err() {
-                            break;
+            let mut buf = Vec::new();
+            loop {
+                let item = match read_line(&mut reader, &mut buf, max_line_size).await {
+                    Ok(Line::Complete) => {
+                        let line = String::from_utf8_lossy(&buf);
+                        let line = line.trim_end_matches('\r');
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        tracing::info!("📥 [STDOUT] Received from Claude Code:\n{}", line);
+                        match serde_json::from_str::<ProtocolMessage>(line) {
+                            Ok(msg) => Ok(msg),
+                            Err(e) => {
+                                tracing::warn!("⚠️  [STDOUT] Failed to parse as ProtocolMessage: {} - line: {}", e, line);
+                                continue;
+                            }
                         }
                     }
+                    Ok(Line::Overflow(length)) => {
+                        tracing::warn!("⚠️  [STDOUT] Skipped a {} byte line (limit {})", length, max_line_size);
+                        Err(Error::MessageParse(format!(
+                            "stdout line of {} bytes exceeds max_buffer_size ({} bytes) and was skipped",
+                            length, max_line_size
+                        )))
+                    }
+                    Ok(Line::Eof) => break,
                     Err(e) => {
-                        tracing::warn!("⚠️  [STDOUT] Failed to parse as ProtocolMessage: {} - line: {}", e, line);
+                        tracing::warn!("⚠️  [STDOUT] Read failed: {}", e);
+                        break;
                     }
+                };
+                if tx.send(item).await.is_err() {
+                    break;
                 }
             }
             tracing::info!("📥 [STDOUT] Stream ended");
@@ -109,3 +149,124 @@ impl<R: AsyncRead + Unpin + Send + 'static> ReadHalf<R> {
     }
 }
 
+/// Outcome of reading one line.
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    /// `buf` holds the line, without its newline.
+    Complete,
+    /// The line had this many bytes and was discarded.
+    Overflow(usize),
+    /// End of stream with no pending data.
+    Eof,
+}
+
+/// Read up to the next newline into `buf`, keeping at most `max` bytes.
+///
+/// Past the limit the rest of the line is consumed without being stored, so
+/// the next call starts cleanly at the following line. A final line without a
+/// newline is returned as complete.
+async fn read_line<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+    buf: &mut Vec<u8>,
+    max: usize,
+) -> std::io::Result<Line> {
+    buf.clear();
+    let mut length = 0;
+    let mut overflowed = false;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(match (overflowed, length) {
+                (true, _) => Line::Overflow(length),
+                (false, 0) => Line::Eof,
+                (false, _) => Line::Complete,
+            });
+        }
+
+        let newline = available.iter().position(|&b| b == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        length += chunk.len();
+        if !overflowed {
+            if length > max {
+                overflowed = true;
+                buf.clear();
+            } else {
+                buf.extend_from_slice(chunk);
+            }
+        }
+
+        let used = chunk.len() + usize::from(newline.is_some());
+        reader.consume(used);
+        if newline.is_some() {
+            return Ok(if overflowed { Line::Overflow(length) } else { Line::Complete });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    fn status(n: usize) -> String {
+        format!("{{\"type\":\"system\",\"subtype\":\"status\",\"n\":{}}}\n", n)
+    }
+
+    async fn read_all(input: Vec<u8>, max: usize) -> Vec<Result<ProtocolMessage>> {
+        let (mut writer, reader) = tokio::io::duplex(64);
+        tokio::spawn(async move {
+            writer.write_all(&input).await.unwrap();
+        });
+        let mut rx = ReadHalf::with_max_line_size(reader, max).read_messages();
+        let mut items = Vec::new();
+        while let Some(item) = rx.recv().await {
+            items.push(item);
+        }
+        items
+    }
+
+    #[tokio::test]
+    async fn test_oversized_line_is_reported_and_skipped() {
+        let mut input = status(0).into_bytes();
+        input.extend(format!("{{\"type\":\"assistant\",\"text\":\"{}\"}}\n", "x".repeat(500)).bytes());
+        input.extend(status(1).bytes());
+
+        let items = read_all(input, 200).await;
+        assert_eq!(items.len(), 3);
+        assert!(items[0].is_ok());
+        match &items[1] {
+            Err(Error::MessageParse(msg)) => assert!(msg.contains("max_buffer_size"), "{}", msg),
+            other => panic!("expected overflow error, got {:?}", other),
+        }
+        assert!(matches!(&items[2], Ok(ProtocolMessage::System(system)) if system.extra["n"] == 1));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_does_not_stop_reading() {
+        let mut input = b"{\"type\":\"system\",\"subtype\":\"status\",\"note\":\"\xff\xfe\"}\n".to_vec();
+        input.extend(b"\xc3\x28 not json\n");
+        input.extend(status(2).bytes());
+
+        let items = read_all(input, DEFAULT_MAX_BUFFER_SIZE).await;
+        assert_eq!(items.len(), 2);
+        assert!(matches!(&items[0], Ok(ProtocolMessage::System(system)) if system.extra["note"] == "\u{fffd}\u{fffd}"));
+        assert!(matches!(&items[1], Ok(ProtocolMessage::System(system)) if system.extra["n"] == 2));
+    }
+
+    #[tokio::test]
+    async fn test_read_line_handles_final_line_and_crlf() {
+        let (mut writer, reader) = tokio::io::duplex(8);
+        tokio::spawn(async move {
+            writer.write_all(b"abcdef\r\n0123456789\nlast").await.unwrap();
+        });
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        assert_eq!(read_line(&mut reader, &mut buf, 8).await.unwrap(), Line::Complete);
+        assert_eq!(buf, b"abcdef\r");
+        assert_eq!(read_line(&mut reader, &mut buf, 8).await.unwrap(), Line::Overflow(10));
+        assert_eq!(read_line(&mut reader, &mut buf, 8).await.unwrap(), Line::Complete);
+        assert_eq!(buf, b"last");
+        assert_eq!(read_line(&mut reader, &mut buf, 8).await.unwrap(), Line::Eof);
+    }
+}
+
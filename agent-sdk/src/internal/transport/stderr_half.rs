@@ -3,8 +3,16 @@
 //! This module provides a wrapper for reading diagnostic logs from the subprocess stderr.
 //! It spawns a background task that continuously reads lines from stderr.
 
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
+
+use crate::types::StderrCallback;
+
+/// Default number of stderr lines kept by the client.
+pub const DEFAULT_STDERR_BUFFER_LINES: usize = 100;
 
 /// Stderr half for subprocess stderr.
 ///
@@ -83,11 +91,10 @@ impl<R: AsyncRead + Unpin + Send + 'static> StderrHalf<R> {
     /// ```
     pub fn read_lines(self) -> mpsc::Receiver<String> {
         let (tx, rx) = mpsc::channel(100);
-        let reader = self.reader;
+        let mut reader = self.reader;
 
         tokio::spawn(async move {
-            let mut lines = reader.lines();
-            while let Ok(Some(line)) = lines.next_line().await {
+            while let Some(line) = next_line(&mut reader).await {
                 if tx.send(line).await.is_err() {
                     break;
                 }
@@ -96,4 +103,127 @@ impl<R: AsyncRead + Unpin + Send + 'static> StderrHalf<R> {
 
         rx
     }
+
+    /// Like [`read_lines`](Self::read_lines), but also keep the last
+    /// `capacity` lines in a [`StderrTail`] and pass each line to `callback`.
+    ///
+    /// Stderr is drained until EOF whether or not anyone reads the channel:
+    /// lines are only forwarded while it has room, so an unread receiver
+    /// never stalls the process. The callback runs on the reader task and
+    /// should return quickly.
+    pub fn read_lines_with_tail(
+        self,
+        capacity: usize,
+        callback: Option<StderrCallback>,
+    ) -> (mpsc::Receiver<String>, StderrTail) {
+        let (tx, rx) = mpsc::channel(100);
+        let (closed_tx, closed_rx) = watch::channel(false);
+        let tail = StderrTail {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+            closed_rx,
+        };
+        let mut reader = self.reader;
+        let task_tail = tail.clone();
+
+        tokio::spawn(async move {
+            while let Some(line) = next_line(&mut reader).await {
+                if let Some(callback) = &callback {
+                    callback(line.clone());
+                }
+                task_tail.push(line.clone());
+                let _ = tx.try_send(line);
+            }
+            let _ = closed_tx.send(true);
+        });
+
+        (rx, tail)
+    }
+}
+
+// Read one line, replacing invalid UTF-8; `None` at EOF or on a read error
+async fn next_line<R: AsyncRead + Unpin>(reader: &mut BufReader<R>) -> Option<String> {
+    let mut buf = Vec::new();
+    match reader.read_until(b'\n', &mut buf).await {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            let line = String::from_utf8_lossy(&buf);
+            Some(line.trim_end_matches(['\n', '\r']).to_string())
+        }
+    }
+}
+
+/// The most recent stderr lines of a process, shared with the reader task.
+#[derive(Debug, Clone)]
+pub struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+    closed_rx: watch::Receiver<bool>,
+}
+
+impl StderrTail {
+    /// The retained lines, oldest first.
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// The last `n` retained lines, oldest first.
+    pub fn last(&self, n: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines.iter().skip(lines.len().saturating_sub(n)).cloned().collect()
+    }
+
+    /// Wait up to `timeout` for stderr to reach end of file, so lines written
+    /// just before the process exited are included.
+    pub async fn wait_closed(&self, timeout: Duration) {
+        let mut closed_rx = self.closed_rx.clone();
+        let _ = tokio::time::timeout(timeout, closed_rx.wait_for(|closed| *closed)).await;
+    }
+
+    fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tail_keeps_last_lines() {
+        let stderr: &[u8] = b"one\ntwo\nthree\n";
+        let (mut rx, tail) = StderrHalf::new(stderr).read_lines_with_tail(2, None);
+
+        tail.wait_closed(Duration::from_secs(1)).await;
+        assert_eq!(tail.lines(), vec!["two".to_string(), "three".to_string()]);
+        assert_eq!(tail.last(1), vec!["three".to_string()]);
+        assert_eq!(rx.recv().await.as_deref(), Some("one"));
+    }
+
+    #[tokio::test]
+    async fn test_callback_sees_every_line_without_a_reader() {
+        let stderr: Vec<u8> = (0..500)
+            .flat_map(|n| format!("line {}\n", n).into_bytes())
+            .chain(b"bad \xff byte\r\n".iter().copied())
+            .collect();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let callback_seen = seen.clone();
+        let callback: StderrCallback = Arc::new(move |line| callback_seen.lock().unwrap().push(line));
+
+        let (_rx, tail) = StderrHalf::new(std::io::Cursor::new(stderr)).read_lines_with_tail(3, Some(callback));
+        tail.wait_closed(Duration::from_secs(1)).await;
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 501);
+        assert_eq!(seen[499], "line 499");
+        assert_eq!(seen[500], "bad \u{fffd} byte");
+        assert_eq!(tail.lines().len(), 3);
+    }
 }
@@ -0,0 +1,126 @@
+//! Transport over arbitrary async byte streams.
+
+use async_trait::async_trait;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+
+use super::{ReadHalf, Transport, WriteHalf};
+use crate::types::{Error, ProtocolMessage, Result};
+
+/// Transport speaking the control protocol over a reader/writer pair.
+///
+/// Use it to reach a Claude Code process that is not a local child: a Unix
+/// socket, a TCP relay to another machine, or `tokio::io::duplex` pipes in tests.
+/// The peer is assumed to be running already, so `connect()` does nothing.
+///
+/// # Example
+///
+/// ```rust
+/// use claude_agent_sdk::internal::transport::{StreamTransport, Transport};
+///
+/// let (sdk_side, _cli_side) = tokio::io::duplex(4096);
+/// let (reader, writer) = tokio::io::split(sdk_side);
+/// let transport = StreamTransport::new(reader, writer);
+/// assert!(transport.is_ready());
+/// ```
+pub struct StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + Sync + 'static,
+    W: AsyncWrite + Unpin + Send + Sync,
+{
+    reader: Option<R>,
+    writer: Option<WriteHalf<W>>,
+}
+
+impl<R, W> StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + Sync + 'static,
+    W: AsyncWrite + Unpin + Send + Sync,
+{
+    /// Create a transport reading messages from `reader` and writing to `writer`.
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader: Some(reader),
+            writer: Some(WriteHalf::new(writer)),
+        }
+    }
+}
+
+#[async_trait]
+impl<R, W> Transport for StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + Sync + 'static,
+    W: AsyncWrite + Unpin + Send + Sync,
+{
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_messages(&mut self) -> Result<mpsc::Receiver<Result<ProtocolMessage>>> {
+        let reader = self
+            .reader
+            .take()
+            .ok_or_else(|| Error::CLIConnection("Messages already being read".to_string()))?;
+        Ok(ReadHalf::new(reader).read_messages())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.write(data).await,
+            None => Err(Error::CLIConnection("Transport input closed".to_string())),
+        }
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.end_input().await?;
+        self.reader = None;
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.writer.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[tokio::test]
+    async fn test_stream_transport_round_trip() {
+        let (sdk_side, cli_side) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(sdk_side);
+        let (cli_reader, mut cli_writer) = tokio::io::split(cli_side);
+
+        let mut transport = StreamTransport::new(reader, writer);
+        transport.connect().await.unwrap();
+        let mut messages = transport.read_messages().unwrap();
+        assert!(transport.read_messages().is_err());
+
+        transport.write("{\"type\":\"ping\"}\n").await.unwrap();
+        let mut lines = BufReader::new(cli_reader).lines();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "{\"type\":\"ping\"}");
+
+        cli_writer
+            .write_all(b"{\"type\":\"system\",\"subtype\":\"init\"}\n")
+            .await
+            .unwrap();
+        match messages.recv().await.unwrap().unwrap() {
+            ProtocolMessage::System(system) => assert_eq!(system.subtype, "init"),
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        transport.end_input().await.unwrap();
+        assert!(!transport.is_ready());
+        assert!(transport.write("{}\n").await.is_err());
+        assert!(lines.next_line().await.unwrap().is_none());
+    }
+}
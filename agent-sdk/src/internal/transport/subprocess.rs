@@ -35,17 +35,21 @@
 //! }
 //! ```
 
-use std::fmt::format;
+use async_trait::async_trait;
 use std::path::PathBuf;
 use std::process::Stdio;
 use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
-use tracing::info;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
 
-use super::{ProcessHandle, ReadHalf, StderrHalf, WriteHalf};
-use crate::types::{ClaudeAgentOptions, Error, Result};
+use super::version::SKIP_VERSION_CHECK_ENV;
+use super::read_half::DEFAULT_MAX_BUFFER_SIZE;
+use super::{CliCapabilities, CliInfo, CliVersion, ProcessHandle, ReadHalf, StderrHalf, Transport, WriteHalf};
+use crate::types::{ClaudeAgentOptions, Error, ProtocolMessage, Result};
 
-const _DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024; // 1MB
-const _MINIMUM_CLAUDE_CODE_VERSION: &str = "2.0.0";
+
+/// Oldest Claude Code release that speaks the control protocol this SDK uses.
+pub const MINIMUM_CLAUDE_CODE_VERSION: CliVersion = CliVersion::new(2, 0, 0);
 
 /// Type alias for the split subprocess components
 type SplitSubprocess = (
@@ -64,7 +68,7 @@ type SplitSubprocess = (
 /// # Lifecycle
 ///
 /// 1. Create with `new()` - validates configuration and locates CLI binary
-/// 2. Connect with `connect()` - spawns the CLI process
+/// 2. Connect with `connect()` - checks the CLI version and spawns the process
 /// 3. Split with `split()` - separates into independent I/O components
 ///
 /// # Example
@@ -91,6 +95,12 @@ pub struct SubprocessCLITransport {
     options: ClaudeAgentOptions,
     cli_path: PathBuf,
     process: Option<Child>,
+    stdin: Option<WriteHalf<ChildStdin>>,
+    stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
+    cli_info: Option<CliInfo>,
+    // `--settings` value, with `options.sandbox` merged in
+    settings: Option<String>,
     ready: bool,
 }
 
@@ -100,6 +110,9 @@ pub struct SubprocessCLITransport {
 /// - `String`: A single string prompt sent immediately
 /// - `Stream`: A stream of JSON messages for interactive/streaming mode
 ///
+/// The transport only uses the variant to pick CLI flags; the messages of a
+/// `Stream` are forwarded by the session actor (see `ClaudeClient::connect`).
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -134,7 +147,9 @@ impl SubprocessCLITransport {
     ///
     /// # Errors
     ///
-    /// Returns an error if the Claude CLI binary cannot be found.
+    /// Returns an error if the Claude CLI binary cannot be found, or
+    /// `Error::InvalidConfig` if `options.sandbox` cannot be merged into
+    /// `options.settings`.
     ///
     /// # Example
     ///
@@ -153,12 +168,18 @@ impl SubprocessCLITransport {
         } else {
             Self::find_cli()?
         };
+        let settings = settings_value(&options)?;
 
         Ok(Self {
             prompt,
             options,
             cli_path,
             process: None,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            cli_info: None,
+            settings,
             ready: false,
         })
     }
@@ -315,7 +336,7 @@ impl SubprocessCLITransport {
         }
 
         // Settings
-        if let Some(ref settings) = self.options.settings {
+        if let Some(ref settings) = self.settings {
             cmd.push("--settings".to_string());
             cmd.push(settings.clone());
         }
@@ -483,38 +504,54 @@ impl SubprocessCLITransport {
     /// ```
     pub fn split(mut self) -> Result<SplitSubprocess> {
         // Ensure process is started
-        let mut child = self.process.take().ok_or_else(|| {
+        let child = self.process.take().ok_or_else(|| {
             Error::Process("Process not started. Call connect() first.".to_string())
         })?;
 
         // Take stdin, stdout, stderr
-        let stdin = child
+        let write_half = self
             .stdin
             .take()
             .ok_or_else(|| Error::Process("stdin not available".to_string()))?;
 
-        let stdout = child
+        let stdout = self
             .stdout
             .take()
             .ok_or_else(|| Error::Process("stdout not available".to_string()))?;
 
-        let stderr = child
+        let stderr = self
             .stderr
             .take()
             .ok_or_else(|| Error::Process("stderr not available".to_string()))?;
 
         // Create halves
-        let read_half = ReadHalf::new(stdout);
-        let write_half = WriteHalf::new(stdin);
+        let read_half = ReadHalf::with_max_line_size(stdout, self.max_line_size());
         let stderr_half = StderrHalf::new(stderr);
-        let process_handle = ProcessHandle::new(child);
+        let process_handle = ProcessHandle::new_group_leader(child);
 
         Ok((read_half, write_half, stderr_half, process_handle))
     }
 
+    /// Take the stderr half, leaving the rest of the transport usable.
+    ///
+    /// Returns `None` before `connect()` or if stderr was already taken.
+    pub fn take_stderr(&mut self) -> Option<StderrHalf<ChildStderr>> {
+        self.stderr.take().map(StderrHalf::new)
+    }
+
+    /// Take ownership of the process lifecycle.
+    ///
+    /// Once taken, `close()` no longer terminates the process; the caller
+    /// decides when to wait for or kill it.
+    pub fn take_process_handle(&mut self) -> Option<ProcessHandle> {
+        self.process.take().map(ProcessHandle::new_group_leader)
+    }
+
     /// Connect the transport and prepare for communication.
     ///
-    /// This starts the Claude CLI process with the configured options and sets up
+    /// The CLI's version is checked first (`claude --version`), unless the
+    /// `CLAUDE_AGENT_SDK_SKIP_VERSION_CHECK` environment variable is set. This
+    /// starts the Claude CLI process with the configured options and sets up
     /// stdio pipes for communication. The process is spawned with:
     /// - stdin: piped (for sending messages)
     /// - stdout: piped (for receiving JSON messages)
@@ -522,7 +559,8 @@ impl SubprocessCLITransport {
     ///
     /// # Errors
     ///
-    /// Returns an error if the process fails to spawn.
+    /// Returns `Error::CLIVersion` if the CLI is older than
+    /// [`MINIMUM_CLAUDE_CODE_VERSION`], or an error if the process fails to spawn.
     ///
     /// # Example
     ///
@@ -547,6 +585,13 @@ impl SubprocessCLITransport {
             return Ok(());
         }
 
+        if self.cli_info.is_none() && std::env::var_os(SKIP_VERSION_CHECK_ENV).is_none() {
+            let cli_info = CliInfo::detect(&self.cli_path, MINIMUM_CLAUDE_CODE_VERSION).await?;
+            info!("Claude CLI version {}", cli_info.version);
+            self.warn_unsupported_options(&cli_info.capabilities);
+            self.cli_info = Some(cli_info);
+        }
+
         let cmd_args = self.build_command();
         info!("Starting Claude CLI: {:?}", cmd_args);
         println!(">> Starting Claude CLI: {:?}", cmd_args);
@@ -559,6 +604,11 @@ impl SubprocessCLITransport {
         command.stdout(Stdio::piped());
         command.stderr(Stdio::piped());
 
+        // Run the CLI in its own process group so teardown also reaches the
+        // tools and servers it spawns
+        #[cfg(unix)]
+        command.process_group(0);
+
         // Set working directory if specified
         if let Some(ref cwd) = self.options.cwd {
             command.current_dir(cwd);
@@ -576,10 +626,13 @@ impl SubprocessCLITransport {
         }
 
         // Spawn process
-        let child = command
+        let mut child = command
             .spawn()
             .map_err(|e| Error::Process(format!("Failed to spawn Claude CLI: {}", e)))?;
 
+        self.stdin = child.stdin.take().map(WriteHalf::new);
+        self.stdout = child.stdout.take();
+        self.stderr = child.stderr.take();
         self.process = Some(child);
         self.ready = true;
 
@@ -591,11 +644,152 @@ impl SubprocessCLITransport {
     pub fn is_ready(&self) -> bool {
         self.ready
     }
+
+    // Longest stdout line accepted before it is skipped as an overflow
+    fn max_line_size(&self) -> usize {
+        self.options.max_buffer_size.unwrap_or(DEFAULT_MAX_BUFFER_SIZE)
+    }
+
+    /// Version and capabilities detected by `connect()`.
+    ///
+    /// `None` before connecting or when the version check was skipped.
+    pub fn cli_info(&self) -> Option<&CliInfo> {
+        self.cli_info.as_ref()
+    }
+
+    // Options the CLI doesn't advertise are still passed through (hidden
+    // flags are missing from `--help`), but an unknown flag is the usual cause
+    // of an immediate exit, so say so up front
+    fn warn_unsupported_options(&self, capabilities: &CliCapabilities) {
+        if !capabilities.is_known() {
+            return;
+        }
+        if self.options.max_budget_usd.is_some() && !capabilities.max_budget_usd() {
+            warn!("max_budget_usd is set but this Claude CLI does not list --max-budget-usd");
+        }
+        let wants_schema = self
+            .options
+            .output_format
+            .as_ref()
+            .is_some_and(|format| format.get("schema").is_some());
+        if wants_schema && !capabilities.json_schema() {
+            warn!("output_format has a schema but this Claude CLI does not list --json-schema");
+        }
+    }
+}
+
+/// The `--settings` argument: `options.settings` as given, or combined with
+/// `options.sandbox` into a JSON object.
+fn settings_value(options: &ClaudeAgentOptions) -> Result<Option<String>> {
+    let Some(ref sandbox) = options.sandbox else {
+        return Ok(options.settings.clone());
+    };
+
+    let mut settings = match options.settings.as_deref().map(str::trim) {
+        None => serde_json::json!({}),
+        Some(json) if json.starts_with('{') => serde_json::from_str(json).map_err(|e| {
+            Error::InvalidConfig(format!("settings is not valid JSON: {}", e))
+        })?,
+        Some(path) => {
+            let mut file = PathBuf::from(path);
+            if let (true, Some(cwd)) = (file.is_relative(), options.cwd.as_ref()) {
+                file = cwd.join(file);
+            }
+            let contents = std::fs::read_to_string(&file).map_err(|e| {
+                Error::InvalidConfig(format!("cannot read settings file {}: {}", file.display(), e))
+            })?;
+            serde_json::from_str(&contents).map_err(|e| {
+                Error::InvalidConfig(format!("settings file {} is not valid JSON: {}", file.display(), e))
+            })?
+        }
+    };
+    let Some(object) = settings.as_object_mut() else {
+        return Err(Error::InvalidConfig("settings must be a JSON object".to_string()));
+    };
+
+    let sandbox = serde_json::to_value(sandbox)?;
+    match object.get_mut("sandbox") {
+        Some(existing) => merge_settings(existing, sandbox, "sandbox")?,
+        None => {
+            object.insert("sandbox".to_string(), sandbox);
+        }
+    }
+    Ok(Some(settings.to_string()))
+}
+
+// Merge `value` into `existing`; both may set a key only if they agree
+fn merge_settings(existing: &mut serde_json::Value, value: serde_json::Value, path: &str) -> Result<()> {
+    match (existing, value) {
+        (serde_json::Value::Object(existing), serde_json::Value::Object(value)) => {
+            for (key, value) in value {
+                let path = format!("{}.{}", path, key);
+                match existing.get_mut(&key) {
+                    Some(current) => merge_settings(current, value, &path)?,
+                    None => {
+                        existing.insert(key, value);
+                    }
+                }
+            }
+            Ok(())
+        }
+        (existing, value) if *existing == value => Ok(()),
+        (existing, value) => Err(Error::InvalidConfig(format!(
+            "{} is {} in settings but {} in ClaudeAgentOptions::sandbox",
+            path, existing, value
+        ))),
+    }
+}
+
+#[async_trait]
+impl Transport for SubprocessCLITransport {
+    async fn connect(&mut self) -> Result<()> {
+        SubprocessCLITransport::connect(self).await
+    }
+
+    fn read_messages(&mut self) -> Result<mpsc::Receiver<Result<ProtocolMessage>>> {
+        let stdout = self
+            .stdout
+            .take()
+            .ok_or_else(|| Error::Process("stdout not available".to_string()))?;
+        // Nobody took stderr: drain it so the CLI never blocks on a full pipe
+        if let Some(stderr) = self.take_stderr() {
+            let _ = stderr.read_lines_with_tail(0, self.options.stderr.clone());
+        }
+        Ok(ReadHalf::with_max_line_size(stdout, self.max_line_size()).read_messages())
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        match self.stdin.as_mut() {
+            Some(stdin) => stdin.write(data).await,
+            None => Err(Error::CLIConnection("stdin not available".to_string())),
+        }
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        if let Some(mut stdin) = self.stdin.take() {
+            stdin.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.end_input().await?;
+        if let Some(child) = self.process.take() {
+            ProcessHandle::new_group_leader(child).start_kill();
+        }
+        self.ready = false;
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::sandbox::{SandboxNetworkConfig, SandboxSettings};
 
     #[test]
     fn test_find_cli() {
@@ -651,5 +845,76 @@ mod tests {
         assert!(cmd.contains(&"1.5".to_string()));
     }
 
+    fn sandbox() -> SandboxSettings {
+        SandboxSettings {
+            enabled: Some(true),
+            excluded_commands: Some(vec!["docker".to_string()]),
+            network: Some(SandboxNetworkConfig {
+                http_proxy_port: Some(8080),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn settings_arg(options: ClaudeAgentOptions) -> Result<serde_json::Value> {
+        let transport = SubprocessCLITransport::new(PromptInput::String("test".to_string()), options)?;
+        let cmd = transport.build_command();
+        let index = cmd.iter().position(|arg| arg == "--settings").unwrap();
+        Ok(serde_json::from_str(&cmd[index + 1]).unwrap())
+    }
+
+    #[test]
+    fn test_sandbox_is_passed_as_settings() {
+        let settings = settings_arg(ClaudeAgentOptions::new().with_sandbox(sandbox())).unwrap();
+        assert_eq!(settings["sandbox"]["enabled"], true);
+        assert_eq!(settings["sandbox"]["excludedCommands"], serde_json::json!(["docker"]));
+        assert_eq!(settings["sandbox"]["network"]["httpProxyPort"], 8080);
+    }
+
+    #[test]
+    fn test_sandbox_merges_with_settings_json_and_file() {
+        let mut options = ClaudeAgentOptions::new().with_sandbox(sandbox());
+        options.settings = Some(
+            r#"{"model": "opus", "sandbox": {"enabled": true, "network": {"allowLocalBinding": true}}}"#
+                .to_string(),
+        );
+        let settings = settings_arg(options).unwrap();
+        assert_eq!(settings["model"], "opus");
+        assert_eq!(settings["sandbox"]["network"]["allowLocalBinding"], true);
+        assert_eq!(settings["sandbox"]["network"]["httpProxyPort"], 8080);
+
+        let dir = std::env::temp_dir().join(format!("claude-sdk-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("settings.json"), r#"{"env": {"A": "1"}}"#).unwrap();
+        let mut options = ClaudeAgentOptions::new().with_sandbox(sandbox()).with_cwd(&dir);
+        options.settings = Some("settings.json".to_string());
+        let settings = settings_arg(options).unwrap();
+        assert_eq!(settings["env"]["A"], "1");
+        assert_eq!(settings["sandbox"]["enabled"], true);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sandbox_conflicting_with_settings_is_rejected() {
+        let mut options = ClaudeAgentOptions::new().with_sandbox(sandbox());
+        options.settings = Some(r#"{"sandbox": {"network": {"httpProxyPort": 3128}}}"#.to_string());
+        match settings_arg(options) {
+            Err(Error::InvalidConfig(msg)) => {
+                assert!(msg.contains("sandbox.network.httpProxyPort"), "{}", msg)
+            }
+            other => panic!("expected invalid config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_settings_without_sandbox_are_passed_unchanged() {
+        let mut options = ClaudeAgentOptions::new();
+        options.settings = Some("/does/not/exist.json".to_string());
+        let transport =
+            SubprocessCLITransport::new(PromptInput::String("test".to_string()), options).unwrap();
+        assert!(transport.build_command().contains(&"/does/not/exist.json".to_string()));
+    }
+
 
 }
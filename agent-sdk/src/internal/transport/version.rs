@@ -0,0 +1,235 @@
+//! Claude Code CLI version and capability detection.
+//!
+//! Before spawning the CLI, the subprocess transport runs `claude --version`
+//! and `claude --help` to reject installs that are too old and to record which
+//! command-line flags the binary understands. Results are cached per binary,
+//! so later connections skip the probes until it is replaced.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+use tokio::process::Command;
+
+use crate::types::{Error, Result};
+
+/// Set this environment variable to skip the version check.
+pub const SKIP_VERSION_CHECK_ENV: &str = "CLAUDE_AGENT_SDK_SKIP_VERSION_CHECK";
+
+/// How long `--version` and `--help` may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Binaries are identified by path and modification time, so an upgrade in
+// place is probed again
+type BinaryKey = (PathBuf, Option<SystemTime>);
+
+fn detected() -> &'static Mutex<HashMap<BinaryKey, CliInfo>> {
+    static DETECTED: OnceLock<Mutex<HashMap<BinaryKey, CliInfo>>> = OnceLock::new();
+    DETECTED.get_or_init(Default::default)
+}
+
+/// A `major.minor.patch` Claude Code version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CliVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl CliVersion {
+    /// Create a version from its components.
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Find the first `major.minor.patch` in `text`, e.g. `"2.0.14 (Claude Code)"`.
+    ///
+    /// Pre-release and build suffixes (`-beta.1`, `+abc`) are ignored.
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .find_map(|token| {
+                let mut parts = token.split('.');
+                let major = parts.next()?.parse().ok()?;
+                let minor = parts.next()?.parse().ok()?;
+                let patch = parts.next()?.parse().ok()?;
+                Some(Self::new(major, minor, patch))
+            })
+    }
+}
+
+impl fmt::Display for CliVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Command-line flags advertised by `claude --help`.
+///
+/// Hidden flags are not listed, so a missing flag is a hint rather than proof
+/// that the CLI cannot handle it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliCapabilities {
+    flags: HashSet<String>,
+}
+
+impl CliCapabilities {
+    /// Collect every `--flag` mentioned in help output.
+    pub fn from_help(help: &str) -> Self {
+        let flags = help
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | '=' | '[' | ']' | '(' | ')'))
+            .filter(|token| token.starts_with("--") && token.len() > 2)
+            .map(|token| token.trim_end_matches(|c: char| !c.is_ascii_alphanumeric()).to_string())
+            .collect();
+        Self { flags }
+    }
+
+    /// Whether `flag` (with or without the leading `--`) is advertised.
+    pub fn supports(&self, flag: &str) -> bool {
+        let flag = flag.trim_start_matches('-');
+        self.flags.contains(&format!("--{}", flag))
+    }
+
+    /// Whether `--max-budget-usd` (`ClaudeAgentOptions::max_budget_usd`) is supported.
+    pub fn max_budget_usd(&self) -> bool {
+        self.supports("--max-budget-usd")
+    }
+
+    /// Whether `--json-schema` (`ClaudeAgentOptions::output_format`) is supported.
+    pub fn json_schema(&self) -> bool {
+        self.supports("--json-schema")
+    }
+
+    /// Whether help output was available at all.
+    pub fn is_known(&self) -> bool {
+        !self.flags.is_empty()
+    }
+}
+
+/// What the transport learned about the CLI binary it is about to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliInfo {
+    /// Version reported by `claude --version`.
+    pub version: CliVersion,
+    /// Flags advertised by `claude --help`.
+    pub capabilities: CliCapabilities,
+}
+
+impl CliInfo {
+    /// Run `--version` and `--help` and enforce `minimum`.
+    ///
+    /// The probes run once per binary; later calls reuse their results.
+    ///
+    /// # Errors
+    /// Returns `Error::CLIVersion` if the version cannot be determined or is
+    /// older than `minimum`.
+    pub async fn detect(cli_path: &Path, minimum: CliVersion) -> Result<Self> {
+        let modified = std::fs::metadata(cli_path).and_then(|meta| meta.modified()).ok();
+        let key = (cli_path.to_path_buf(), modified);
+        let cached = detected().lock().unwrap().get(&key).cloned();
+        let info = match cached {
+            Some(info) => info,
+            None => {
+                let info = Self::probe(cli_path).await?;
+                detected().lock().unwrap().insert(key, info.clone());
+                info
+            }
+        };
+
+        if info.version < minimum {
+            return Err(Error::CLIVersion(format!(
+                "Claude Code {} at {} is older than the minimum supported version {}. \
+                Update with:\n  npm install -g @anthropic-ai/claude-code",
+                info.version,
+                cli_path.display(),
+                minimum
+            )));
+        }
+        Ok(info)
+    }
+
+    async fn probe(cli_path: &Path) -> Result<Self> {
+        let (version_output, help_output) =
+            tokio::join!(probe(cli_path, "--version"), probe(cli_path, "--help"));
+
+        let version_output = version_output?;
+        let version = CliVersion::parse(&version_output).ok_or_else(|| {
+            Error::CLIVersion(format!(
+                "could not determine the version of {} from {:?}",
+                cli_path.display(),
+                version_output.trim()
+            ))
+        })?;
+
+        let capabilities = match help_output {
+            Ok(help) => CliCapabilities::from_help(&help),
+            Err(e) => {
+                tracing::warn!("Could not read Claude Code capabilities: {}", e);
+                CliCapabilities::default()
+            }
+        };
+        Ok(Self { version, capabilities })
+    }
+}
+
+async fn probe(cli_path: &Path, arg: &str) -> Result<String> {
+    let output = Command::new(cli_path)
+        .arg(arg)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(PROBE_TIMEOUT, output)
+        .await
+        .map_err(|_| {
+            Error::CLIVersion(format!(
+                "`{} {}` did not finish within {:?}",
+                cli_path.display(),
+                arg,
+                PROBE_TIMEOUT
+            ))
+        })?
+        .map_err(|e| Error::CLIVersion(format!("failed to run {}: {}", cli_path.display(), e)))?;
+
+    if !output.status.success() {
+        return Err(Error::CLIVersion(format!(
+            "`{} {}` failed ({}): {}",
+            cli_path.display(),
+            arg,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            CliVersion::parse("2.0.14 (Claude Code)"),
+            Some(CliVersion::new(2, 0, 14))
+        );
+        assert_eq!(CliVersion::parse("v1.0.3-beta.1"), Some(CliVersion::new(1, 0, 3)));
+        assert_eq!(CliVersion::parse("claude 10.2"), None);
+        assert!(CliVersion::new(1, 9, 99) < CliVersion::new(2, 0, 0));
+        assert!(CliVersion::new(2, 10, 0) > CliVersion::new(2, 9, 1));
+    }
+
+    #[test]
+    fn test_capabilities_from_help() {
+        let help = "Options:\n  --max-budget-usd <amount>  Maximum spend\n  \
+            --json-schema <schema>  Schema\n  -p, --print  Print response\n  \
+            --setting-sources <sources>  Comma-separated list";
+        let capabilities = CliCapabilities::from_help(help);
+        assert!(capabilities.max_budget_usd());
+        assert!(capabilities.json_schema());
+        assert!(capabilities.supports("print"));
+        assert!(capabilities.supports("--setting-sources"));
+        assert!(!capabilities.supports("--fork-session"));
+        assert!(!CliCapabilities::default().is_known());
+    }
+}
@@ -88,6 +88,11 @@ impl<W: AsyncWrite + Unpin + Send> WriteHalf<W> {
         Ok(())
     }
 
+    /// Shut down the writer, signalling end of input to the reader.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.writer.shutdown().await.map_err(Error::Io)
+    }
+
     pub async fn write_with_newline(&mut self, data: &str) -> Result<()> {
         tracing::info!("📤 [STDIN] Writing to Claude Code:\n{}", data);
         self.writer
@@ -27,12 +27,21 @@
 //! - [`types`] - All type definitions (permissions, hooks, messages, etc.)
 //! - [`internal`] - Internal implementation (transport, query, client)
 //! - [`client`] - High-level client API
+//! - [`query`] - One-shot `query()` API
+//! - [`policy`] - Declarative permission policies for `can_use_tool`
+//! - [`testing`] - Scripted mock CLI for offline tests
 
 pub mod client;
 pub mod internal;
+pub mod policy;
+pub mod query;
+pub mod testing;
 pub mod types;
 
 // Re-export all public types at the crate root for convenience
 pub use client::ClaudeClient;
+pub use policy::{PermissionPolicy, PolicyAction, PolicyDecision, PolicyRule};
+pub use query::{query, query_outcome, query_with_transport, QueryOutcome, QueryStream};
+pub use internal::transport::{CliCapabilities, CliInfo, CliVersion, StreamTransport, Transport};
 // pub use internal::InternalClient;
 pub use types::*;
@@ -0,0 +1,148 @@
+//! Minimal glob matching for policy rules.
+//!
+//! Supports `*`, `**` and `?`. With a separator, `*` and `?` stop at it while
+//! `**` crosses it; without one, `*` matches anything.
+
+enum Token {
+    Char(char),
+    // `?`
+    One,
+    // `*`
+    Star,
+    // `**`; may also skip the separator after it, so `a/**/b` matches `a/b`
+    Globstar { skip_separator: bool },
+}
+
+// How a pattern position was reached. A `**` skips the separator after it
+// only when it matches nothing, i.e. when entered rather than looped on.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Reach {
+    No,
+    Looped,
+    Entered,
+}
+
+/// Whether `text` matches `pattern` as a whole.
+///
+/// Runs in `O(pattern × text)`: every pattern position the text could have
+/// reached is tracked at once instead of backtracking.
+pub(crate) fn matches(pattern: &str, text: &str, separator: Option<char>) -> bool {
+    let tokens = tokenize(pattern, separator);
+    let mut states = vec![Reach::No; tokens.len() + 1];
+    states[0] = Reach::Entered;
+    follow_empty(&tokens, &mut states);
+
+    for c in text.chars() {
+        let consumes = Some(c) != separator;
+        let mut next = vec![Reach::No; tokens.len() + 1];
+        for (i, token) in tokens.iter().enumerate().filter(|&(i, _)| states[i] != Reach::No) {
+            match token {
+                Token::Char(p) if *p == c => next[i + 1] = Reach::Entered,
+                Token::One if consumes => next[i + 1] = Reach::Entered,
+                Token::Star if consumes => next[i] = next[i].max(Reach::Looped),
+                Token::Globstar { .. } => next[i] = next[i].max(Reach::Looped),
+                _ => {}
+            }
+        }
+        follow_empty(&tokens, &mut next);
+        if next.iter().all(|&reach| reach == Reach::No) {
+            return false;
+        }
+        states = next;
+    }
+    states[tokens.len()] != Reach::No
+}
+
+fn tokenize(pattern: &str, separator: Option<char>) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        tokens.push(match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                i += 1;
+                Token::Globstar {
+                    skip_separator: separator.is_some() && chars.get(i + 1) == separator.as_ref(),
+                }
+            }
+            '*' => Token::Star,
+            '?' => Token::One,
+            c => Token::Char(c),
+        });
+        i += 1;
+    }
+    tokens
+}
+
+// Add the positions reachable without consuming text; stars may match
+// nothing. These only lead forward, so one pass suffices.
+fn follow_empty(tokens: &[Token], states: &mut [Reach]) {
+    for (i, token) in tokens.iter().enumerate() {
+        match (token, states[i]) {
+            (_, Reach::No) => {}
+            (Token::Star, _) => states[i + 1] = Reach::Entered,
+            (Token::Globstar { skip_separator }, reach) => {
+                states[i + 1] = Reach::Entered;
+                if *skip_separator && reach == Reach::Entered {
+                    states[i + 2] = Reach::Entered;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_globs() {
+        let sep = Some('/');
+        assert!(matches("/etc/*", "/etc/passwd", sep));
+        assert!(!matches("/etc/*", "/etc/ssh/sshd_config", sep));
+        assert!(matches("/etc/**", "/etc/ssh/sshd_config", sep));
+        assert!(matches("src/**/*.rs", "src/main.rs", sep));
+        assert!(matches("src/**/*.rs", "src/policy/glob.rs", sep));
+        assert!(!matches("src/**/*.rs", "tests/policy.rs", sep));
+        assert!(matches("**/.env", ".env", sep));
+        assert!(matches("**/.env", "/app/config/.env", sep));
+        assert!(matches("file?.txt", "file1.txt", sep));
+        assert!(!matches("file?.txt", "file10.txt", sep));
+    }
+
+    #[test]
+    fn test_globs_without_separator() {
+        assert!(matches("git *", "git push origin main", None));
+        assert!(matches("mcp__github__*", "mcp__github__create_issue", None));
+        assert!(!matches("mcp__github__*", "mcp__gitlab__create_issue", None));
+        assert!(matches("npm run *", "npm run test/unit", None));
+        assert!(!matches("Bash", "BashOutput", None));
+    }
+
+    #[test]
+    fn test_globstar_edge_cases() {
+        let sep = Some('/');
+        assert!(matches("a/**/b", "a/b", sep));
+        assert!(matches("a/**/b", "a/x/y/b", sep));
+        assert!(!matches("a/**/b", "a/xb", sep));
+        assert!(matches("a/**", "a/", sep));
+        assert!(matches("**", "", sep));
+        assert!(matches("*", "", sep));
+        assert!(!matches("?", "/", sep));
+        assert!(matches("a/**/**/b", "a/b", sep));
+        assert!(matches("*.rs", "main.rs", sep));
+        assert!(!matches("*.rs", "src/main.rs", sep));
+    }
+
+    #[test]
+    fn test_pathological_patterns_are_fast() {
+        let text = "a".repeat(500);
+        let pattern = format!("{}b", "*a".repeat(50));
+        assert!(!matches(&pattern, &text, None));
+        assert!(!matches(&pattern, &text, Some('/')));
+        let pattern = format!("{}b", "**a".repeat(50));
+        assert!(!matches(&pattern, &text, Some('/')));
+        assert!(matches(&"*a".repeat(50), &text, None));
+    }
+}
@@ -0,0 +1,483 @@
+//! Combinators over [`CanUseTool`].
+//!
+//! - [`PermissionChain`] asks a list of steps in order until one decides.
+//! - [`CachedPermissions`] remembers "allow always" answers.
+//! - [`AuditedPermissions`] reports every decision to an [`AuditSink`].
+//! - [`TimeoutPermissions`] denies when a decision takes too long.
+//!
+//! Each wrapper is itself a `CanUseTool`, so they nest:
+//!
+//! ```
+//! use claude_agent_sdk::policy::{
+//!     AuditedPermissions, CachedPermissions, PermissionChain, PermissionPolicy, PolicyRule,
+//!     TimeoutPermissions,
+//! };
+//! use std::time::Duration;
+//!
+//! let chain = PermissionChain::new()
+//!     .with_policy(PermissionPolicy::new(vec![PolicyRule::allow("Read")]));
+//! let can_use_tool = AuditedPermissions::new(
+//!     TimeoutPermissions::new(CachedPermissions::new(chain), Duration::from_secs(300)),
+//!     |record: &claude_agent_sdk::policy::AuditRecord| println!("{:?}", record),
+//! );
+//! ```
+
+use async_trait::async_trait;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use super::{PermissionPolicy, PolicyAction};
+use crate::types::{
+    CanUseTool, PermissionBehavior, PermissionResult, PermissionResultAllow, PermissionResultDeny,
+    PermissionUpdate, Result, ToolPermissionContext,
+};
+
+/// One link of a [`PermissionChain`], which may pass on a request.
+#[async_trait]
+pub trait PermissionStep: Send + Sync {
+    /// Decide on the tool use, or return `None` to let the next step decide.
+    async fn decide(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<Option<PermissionResult>>;
+}
+
+/// A policy decides with its allow and deny rules and passes on `ask`.
+#[async_trait]
+impl PermissionStep for PermissionPolicy {
+    async fn decide(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        _context: &ToolPermissionContext,
+    ) -> Result<Option<PermissionResult>> {
+        let decision = self.evaluate_request(tool_name, input);
+        Ok((decision.action != PolicyAction::Ask).then(|| decision.into_result()))
+    }
+}
+
+// A plain callback always decides
+struct Decides(Box<dyn CanUseTool>);
+
+#[async_trait]
+impl PermissionStep for Decides {
+    async fn decide(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<Option<PermissionResult>> {
+        self.0.can_use(tool_name, input, context).await.map(Some)
+    }
+}
+
+/// Asks each step in order; the first that decides wins.
+///
+/// When every step passes, the request is denied.
+#[derive(Default)]
+pub struct PermissionChain {
+    steps: Vec<Box<dyn PermissionStep>>,
+}
+
+impl PermissionChain {
+    /// An empty chain, which denies everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a step.
+    pub fn with_step(mut self, step: impl PermissionStep + 'static) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Append a policy; its `ask` decisions fall through to the next step.
+    pub fn with_policy(self, policy: PermissionPolicy) -> Self {
+        self.with_step(policy)
+    }
+
+    /// Append a callback that always decides, such as a user prompt.
+    pub fn with_callback(self, callback: impl CanUseTool + 'static) -> Self {
+        self.with_step(Decides(Box::new(callback)))
+    }
+}
+
+#[async_trait]
+impl CanUseTool for PermissionChain {
+    async fn can_use(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        for step in &self.steps {
+            if let Some(result) = step.decide(tool_name, input, context).await? {
+                return Ok(result);
+            }
+        }
+        Ok(PermissionResult::Deny(PermissionResultDeny {
+            message: format!("No permission step decided on {}", tool_name),
+            ..Default::default()
+        }))
+    }
+}
+
+/// Remembers "allow always" answers so the same tool use is not asked twice.
+///
+/// An answer counts as "allow always" when it allows the tool use and adds an
+/// allow rule through `updated_permissions`. Later requests for the same tool
+/// with the same input (compared after sorting object keys) are allowed
+/// without calling the inner callback.
+pub struct CachedPermissions<C> {
+    inner: C,
+    allowed: Mutex<HashMap<(String, String), Option<Value>>>,
+}
+
+impl<C: CanUseTool> CachedPermissions<C> {
+    /// Cache the answers of `inner`.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            allowed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Number of remembered tool uses.
+    pub fn len(&self) -> usize {
+        self.allowed.lock().unwrap().len()
+    }
+
+    /// Whether nothing is remembered yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget every remembered answer.
+    pub fn clear(&self) {
+        self.allowed.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl<C: CanUseTool> CanUseTool for CachedPermissions<C> {
+    async fn can_use(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        let key = (tool_name.to_string(), canonical_json(input));
+        if let Some(updated_input) = self.allowed.lock().unwrap().get(&key) {
+            return Ok(PermissionResult::Allow(PermissionResultAllow {
+                updated_input: updated_input.clone(),
+                ..Default::default()
+            }));
+        }
+
+        let result = self.inner.can_use(tool_name, input, context).await?;
+        if let PermissionResult::Allow(allow) = &result {
+            if allow.updated_permissions.as_deref().is_some_and(adds_allow_rule) {
+                self.allowed.lock().unwrap().insert(key, allow.updated_input.clone());
+            }
+        }
+        Ok(result)
+    }
+}
+
+fn adds_allow_rule(updates: &[PermissionUpdate]) -> bool {
+    updates.iter().any(|update| {
+        matches!(
+            update,
+            PermissionUpdate::AddRules {
+                behavior: Some(PermissionBehavior::Allow),
+                ..
+            }
+        )
+    })
+}
+
+/// How a permission request ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// The tool use was allowed.
+    Allow,
+    /// The tool use was denied, with the reason given.
+    Deny { message: String },
+    /// The callback failed.
+    Error(String),
+}
+
+/// One audited permission decision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// When the decision was made.
+    pub timestamp: SystemTime,
+    /// Tool that asked for permission.
+    pub tool_name: String,
+    /// The tool use, when the CLI identified it.
+    pub tool_use_id: Option<String>,
+    /// Hex SHA-256 of the input with object keys sorted, so records can be
+    /// correlated without storing the input itself.
+    pub input_hash: String,
+    /// The decision.
+    pub outcome: AuditOutcome,
+}
+
+/// Where [`AuditedPermissions`] sends its records.
+pub trait AuditSink: Send + Sync {
+    /// Record one decision. Called before the answer goes back to the CLI,
+    /// so this should not block for long.
+    fn record(&self, record: &AuditRecord);
+}
+
+impl<F> AuditSink for F
+where
+    F: Fn(&AuditRecord) + Send + Sync,
+{
+    fn record(&self, record: &AuditRecord) {
+        self(record)
+    }
+}
+
+/// Reports every decision of the inner callback to an [`AuditSink`].
+pub struct AuditedPermissions<C> {
+    inner: C,
+    sink: Box<dyn AuditSink>,
+}
+
+impl<C: CanUseTool> AuditedPermissions<C> {
+    /// Audit the decisions of `inner` into `sink`.
+    pub fn new(inner: C, sink: impl AuditSink + 'static) -> Self {
+        Self {
+            inner,
+            sink: Box::new(sink),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: CanUseTool> CanUseTool for AuditedPermissions<C> {
+    async fn can_use(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        let result = self.inner.can_use(tool_name, input, context).await;
+        let outcome = match &result {
+            Ok(PermissionResult::Allow(_)) => AuditOutcome::Allow,
+            Ok(PermissionResult::Deny(deny)) => AuditOutcome::Deny {
+                message: deny.message.clone(),
+            },
+            Err(e) => AuditOutcome::Error(e.to_string()),
+        };
+        self.sink.record(&AuditRecord {
+            timestamp: SystemTime::now(),
+            tool_name: tool_name.to_string(),
+            tool_use_id: context.tool_use_id.clone(),
+            input_hash: input_hash(input),
+            outcome,
+        });
+        result
+    }
+}
+
+/// Answers with a fallback, a denial by default, when the inner callback does
+/// not decide within a time limit.
+pub struct TimeoutPermissions<C> {
+    inner: C,
+    timeout: Duration,
+    fallback: PermissionResult,
+}
+
+impl<C: CanUseTool> TimeoutPermissions<C> {
+    /// Deny requests that `inner` does not answer within `timeout`.
+    pub fn new(inner: C, timeout: Duration) -> Self {
+        Self {
+            inner,
+            timeout,
+            fallback: PermissionResult::Deny(PermissionResultDeny {
+                message: format!("Permission decision timed out after {:?}", timeout),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Answer with `fallback` instead of the default denial on timeout.
+    pub fn with_fallback(mut self, fallback: PermissionResult) -> Self {
+        self.fallback = fallback;
+        self
+    }
+}
+
+#[async_trait]
+impl<C: CanUseTool> CanUseTool for TimeoutPermissions<C> {
+    async fn can_use(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        match tokio::time::timeout(self.timeout, self.inner.can_use(tool_name, input, context)).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!("⏱️ Permission decision for {} timed out after {:?}", tool_name, self.timeout);
+                Ok(self.fallback.clone())
+            }
+        }
+    }
+}
+
+/// Hex SHA-256 of `input` in canonical form.
+pub fn input_hash(input: &Value) -> String {
+    Sha256::digest(canonical_json(input).as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// JSON with object keys sorted, so equal inputs compare equal as strings
+fn canonical_json(value: &Value) -> String {
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let sorted: BTreeMap<&String, Value> = map.iter().map(|(k, v)| (k, sorted(v))).collect();
+                serde_json::to_value(sorted).expect("string keys always serialize")
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    sorted(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::PolicyRule;
+    use crate::types::{PermissionRuleValue, PermissionUpdateDestination};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Allows always, counting its calls.
+    struct AllowAlways(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl CanUseTool for AllowAlways {
+        async fn can_use(
+            &self,
+            tool_name: &str,
+            _input: &Value,
+            _context: &ToolPermissionContext,
+        ) -> Result<PermissionResult> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(PermissionResult::Allow(PermissionResultAllow {
+                updated_permissions: Some(vec![PermissionUpdate::AddRules {
+                    rules: Some(vec![PermissionRuleValue {
+                        tool_name: tool_name.to_string(),
+                        rule_content: None,
+                    }]),
+                    behavior: Some(PermissionBehavior::Allow),
+                    destination: Some(PermissionUpdateDestination::Session),
+                }]),
+                ..Default::default()
+            }))
+        }
+    }
+
+    struct Never;
+
+    #[async_trait]
+    impl CanUseTool for Never {
+        async fn can_use(
+            &self,
+            _tool_name: &str,
+            _input: &Value,
+            _context: &ToolPermissionContext,
+        ) -> Result<PermissionResult> {
+            std::future::pending().await
+        }
+    }
+
+    fn is_allow(result: &PermissionResult) -> bool {
+        matches!(result, PermissionResult::Allow(_))
+    }
+
+    #[tokio::test]
+    async fn test_chain_falls_through_ask() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let chain = PermissionChain::new()
+            .with_policy(PermissionPolicy::new(vec![
+                PolicyRule::deny("Bash").with_command_prefix("rm"),
+                PolicyRule::allow("Read"),
+            ]))
+            .with_callback(AllowAlways(calls.clone()));
+        let context = ToolPermissionContext::default();
+
+        assert!(is_allow(&chain.can_use("Read", &json!({}), &context).await.unwrap()));
+        assert!(!is_allow(&chain.can_use("Bash", &json!({"command": "rm x"}), &context).await.unwrap()));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(is_allow(&chain.can_use("Bash", &json!({"command": "ls"}), &context).await.unwrap()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let empty = PermissionChain::new();
+        assert!(!is_allow(&empty.can_use("Read", &json!({}), &context).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_cache_remembers_allow_always() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cache = CachedPermissions::new(AllowAlways(calls.clone()));
+        let context = ToolPermissionContext::default();
+
+        let first = json!({"command": "make", "timeout": 10});
+        let reordered: Value = serde_json::from_str(r#"{"timeout": 10, "command": "make"}"#).unwrap();
+        assert!(is_allow(&cache.can_use("Bash", &first, &context).await.unwrap()));
+        assert!(is_allow(&cache.can_use("Bash", &reordered, &context).await.unwrap()));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.len(), 1);
+
+        cache.can_use("Bash", &json!({"command": "make test"}), &context).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        cache.clear();
+        cache.can_use("Bash", &first, &context).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_audit_and_timeout() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let records = records.clone();
+            move |record: &AuditRecord| records.lock().unwrap().push(record.clone())
+        };
+        let audited = AuditedPermissions::new(
+            TimeoutPermissions::new(Never, Duration::from_millis(10)),
+            sink,
+        );
+        let context = ToolPermissionContext {
+            tool_use_id: Some("toolu_01".to_string()),
+            ..Default::default()
+        };
+
+        let input = json!({"file_path": "/tmp/x"});
+        assert!(!is_allow(&audited.can_use("Write", &input, &context).await.unwrap()));
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tool_name, "Write");
+        assert_eq!(records[0].tool_use_id.as_deref(), Some("toolu_01"));
+        assert_eq!(records[0].input_hash, input_hash(&input));
+        assert_eq!(records[0].input_hash.len(), 64);
+        assert!(matches!(
+            &records[0].outcome,
+            AuditOutcome::Deny { message } if message.contains("timed out")
+        ));
+    }
+}
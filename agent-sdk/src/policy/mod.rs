@@ -0,0 +1,758 @@
+//! Declarative permission policies.
+//!
+//! A [`PermissionPolicy`] is an ordered list of [`PolicyRule`]s that answers
+//! `can_use_tool` requests without hand-written matching code. The first rule
+//! that matches decides; when none does, the policy's default applies.
+//!
+//! ```toml
+//! default = "ask"
+//!
+//! [[rules]]
+//! tool = "Bash"
+//! command_prefix = "git status"
+//! action = "allow"
+//! reason = "read-only git"
+//!
+//! [[rules]]
+//! tool = "Write|Edit"
+//! path = "/etc/**"
+//! action = "deny"
+//!
+//! [[rules]]
+//! tool = "mcp__github__*"
+//! action = "ask"
+//! ```
+//!
+//! `ask` hands the request to the callback set with
+//! [`PermissionPolicy::with_ask`]; without one, it is denied.
+//!
+//! Relative paths, and tools called without one, are placed in the directory
+//! set with [`PermissionPolicy::with_cwd`]. Without it, such a use never
+//! matches an `allow` path rule and always matches `deny` and `ask` ones.
+
+mod glob;
+mod middleware;
+mod toml;
+
+pub use middleware::{
+    input_hash, AuditOutcome, AuditRecord, AuditSink, AuditedPermissions, CachedPermissions,
+    PermissionChain, PermissionStep, TimeoutPermissions,
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::Path;
+use tracing::info;
+
+use crate::types::{
+    CanUseTool, Error, PermissionResult, PermissionResultAllow, PermissionResultDeny, Result,
+    ToolPermissionContext,
+};
+
+/// Input keys the built-in file tools use for their target path.
+const PATH_KEYS: [&str; 3] = ["file_path", "notebook_path", "path"];
+
+/// What a matching rule decides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    /// Let the tool run.
+    Allow,
+    /// Refuse the tool use.
+    Deny,
+    /// Defer to the policy's ask callback.
+    Ask,
+}
+
+impl fmt::Display for PolicyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PolicyAction::Allow => "allow",
+            PolicyAction::Deny => "deny",
+            PolicyAction::Ask => "ask",
+        })
+    }
+}
+
+/// One policy rule. Every condition that is set must match.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Decision when the rule matches.
+    pub action: PolicyAction,
+    /// Tool name glob; `|` separates alternatives, e.g. `"Write|Edit"` or
+    /// `"mcp__github__*"`. Matches every tool when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Glob matched against the `command` input, e.g. `"npm run *"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Leading words of the `command` input, e.g. `"git status"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_prefix: Option<String>,
+    /// Glob matched against the tool's `file_path`, `notebook_path` or `path`
+    /// input; `*` stays within a directory and `**` crosses directories.
+    ///
+    /// A path the policy cannot make absolute (see
+    /// [`PermissionPolicy::with_cwd`]) matches `deny` and `ask` rules but not
+    /// `allow` rules. Tools called without a path work in the cwd, which
+    /// only `deny` and `ask` rules consider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Why the rule exists; included in the decision's explanation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Fields merged into the tool input when an `allow` rule matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_input: Option<Map<String, Value>>,
+    /// Whether a `deny` rule also interrupts the turn.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupt: bool,
+}
+
+impl PolicyRule {
+    /// A rule with `action` for tools matching `tool`.
+    pub fn new(action: PolicyAction, tool: impl Into<String>) -> Self {
+        Self {
+            action,
+            tool: Some(tool.into()),
+            command: None,
+            command_prefix: None,
+            path: None,
+            reason: None,
+            updated_input: None,
+            interrupt: false,
+        }
+    }
+
+    /// Allow tools matching `tool`.
+    pub fn allow(tool: impl Into<String>) -> Self {
+        Self::new(PolicyAction::Allow, tool)
+    }
+
+    /// Deny tools matching `tool`.
+    pub fn deny(tool: impl Into<String>) -> Self {
+        Self::new(PolicyAction::Deny, tool)
+    }
+
+    /// Ask about tools matching `tool`.
+    pub fn ask(tool: impl Into<String>) -> Self {
+        Self::new(PolicyAction::Ask, tool)
+    }
+
+    /// Only match commands matching the glob `command`.
+    pub fn with_command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    /// Only match commands starting with the words in `prefix`.
+    pub fn with_command_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.command_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Only match paths matching the glob `path`.
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Set the reason reported when the rule matches.
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// Merge `fields` into the tool input when the rule allows it.
+    pub fn with_updated_input(mut self, fields: Map<String, Value>) -> Self {
+        self.updated_input = Some(fields);
+        self
+    }
+
+    /// Interrupt the turn when the rule denies.
+    pub fn with_interrupt(mut self, interrupt: bool) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    fn validate(&self, index: usize) -> Result<()> {
+        if self.updated_input.is_some() && self.action != PolicyAction::Allow {
+            return Err(Error::InvalidConfig(format!(
+                "policy rule {} sets updated_input but its action is {}",
+                index + 1,
+                self.action
+            )));
+        }
+        Ok(())
+    }
+
+    fn matches(&self, tool_name: &str, input: &Value, cwd: Option<&str>) -> bool {
+        if let Some(tools) = &self.tool {
+            if !tools.split('|').any(|tool| glob::matches(tool.trim(), tool_name, None)) {
+                return false;
+            }
+        }
+        if self.command.is_some() || self.command_prefix.is_some() {
+            let Some(command) = input.get("command").and_then(Value::as_str) else {
+                return false;
+            };
+            if !self.matches_command(command) {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.path {
+            let path = match PATH_KEYS.iter().find_map(|key| input.get(*key).and_then(Value::as_str)) {
+                Some(path) => resolve_path(path, cwd),
+                None if self.action == PolicyAction::Allow => return false,
+                None => cwd.and_then(|cwd| resolve_path(cwd, None)),
+            };
+            match path {
+                Some(path) if !glob::matches(pattern, &path, Some('/')) => return false,
+                Some(_) => {}
+                // Fail closed: only allowing needs to know where the path is
+                None if self.action == PolicyAction::Allow => return false,
+                None => {}
+            }
+        }
+        true
+    }
+
+    // An allow rule must cover every command in a compound command line, and
+    // never covers command substitution, parameter expansion or redirection;
+    // deny and ask rules need only one.
+    fn matches_command(&self, command: &str) -> bool {
+        let matches_one = |part: &str| {
+            self.command.as_deref().is_none_or(|pattern| glob::matches(pattern, part, None))
+                && self.command_prefix.as_deref().is_none_or(|prefix| has_prefix(part, prefix))
+        };
+        let parts = split_commands(command);
+        if self.action == PolicyAction::Allow {
+            let substitutes = ["$(", "`", "<(", ">(", "${"].iter().any(|s| command.contains(s));
+            !substitutes
+                && !has_redirection(command)
+                && !parts.is_empty()
+                && parts.into_iter().all(matches_one)
+        } else {
+            parts.into_iter().any(matches_one)
+        }
+    }
+
+    fn describe(&self) -> String {
+        let conditions: Vec<String> = [
+            ("tool", &self.tool),
+            ("command", &self.command),
+            ("command_prefix", &self.command_prefix),
+            ("path", &self.path),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.as_ref().map(|value| format!("{} {:?}", name, value)))
+        .collect();
+        if conditions.is_empty() {
+            "any tool".to_string()
+        } else {
+            conditions.join(", ")
+        }
+    }
+}
+
+/// The outcome of evaluating a policy, with an explanation for audit logs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyDecision {
+    /// What to do with the tool use.
+    pub action: PolicyAction,
+    /// Index of the matching rule, or `None` when the default applied.
+    pub rule: Option<usize>,
+    /// Which rule decided and why.
+    pub explanation: String,
+    /// Rewritten tool input from the rule's `updated_input`.
+    pub updated_input: Option<Value>,
+    /// Whether a denial should interrupt the turn.
+    pub interrupt: bool,
+}
+
+/// An ordered, first-match-wins permission policy usable as `can_use_tool`.
+///
+/// # Examples
+///
+/// ```
+/// use claude_agent_sdk::policy::{PermissionPolicy, PolicyAction, PolicyRule};
+///
+/// let policy = PermissionPolicy::new(vec![
+///     PolicyRule::allow("Bash").with_command_prefix("git status"),
+///     PolicyRule::deny("Write|Edit").with_path("/etc/**"),
+/// ]);
+/// let decision = policy.evaluate("Bash", &serde_json::json!({"command": "git status -s"}));
+/// assert_eq!(decision.action, PolicyAction::Allow);
+/// ```
+pub struct PermissionPolicy {
+    rules: Vec<PolicyRule>,
+    default: PolicyAction,
+    ask: Option<Box<dyn CanUseTool>>,
+    cwd: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default = "default_action")]
+    default: PolicyAction,
+    #[serde(default)]
+    rules: Vec<PolicyRule>,
+}
+
+fn default_action() -> PolicyAction {
+    PolicyAction::Ask
+}
+
+impl PolicyDecision {
+    // The answer for an allow or deny decision; ask is up to the caller
+    fn into_result(self) -> PermissionResult {
+        match self.action {
+            PolicyAction::Allow => PermissionResult::Allow(PermissionResultAllow {
+                updated_input: self.updated_input,
+                ..Default::default()
+            }),
+            PolicyAction::Deny | PolicyAction::Ask => PermissionResult::Deny(PermissionResultDeny {
+                message: self.explanation,
+                interrupt: self.interrupt,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+impl PermissionPolicy {
+    /// A policy with `rules` that asks when no rule matches.
+    pub fn new(rules: Vec<PolicyRule>) -> Self {
+        Self {
+            rules,
+            default: default_action(),
+            ask: None,
+            cwd: None,
+        }
+    }
+
+    /// Parse a policy from JSON: `{"default": "ask", "rules": [...]}`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidConfig(format!("invalid policy JSON: {}", e)))?;
+        Self::from_value(value)
+    }
+
+    /// Parse a policy from TOML, with rules as `[[rules]]` tables.
+    ///
+    /// Only the subset of TOML a policy needs is accepted; dates, times,
+    /// `inf`/`nan` and hex, octal or binary integers are rejected.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let value = toml::parse(toml)
+            .map_err(|e| Error::InvalidConfig(format!("invalid policy TOML: {}", e)))?;
+        Self::from_value(value)
+    }
+
+    /// Load a policy file, read as TOML if its extension is `.toml` and as
+    /// JSON otherwise.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            Error::InvalidConfig(format!("cannot read policy {}: {}", path.display(), e))
+        })?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            Self::from_toml(&contents)
+        } else {
+            Self::from_json(&contents)
+        }
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        let file: PolicyFile = serde_json::from_value(value)
+            .map_err(|e| Error::InvalidConfig(format!("invalid policy: {}", e)))?;
+        for (index, rule) in file.rules.iter().enumerate() {
+            rule.validate(index)?;
+        }
+        Ok(Self {
+            rules: file.rules,
+            default: file.default,
+            ask: None,
+            cwd: None,
+        })
+    }
+
+    /// Append a rule; it applies only if no earlier rule matches.
+    pub fn with_rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Set the action used when no rule matches.
+    pub fn with_default(mut self, action: PolicyAction) -> Self {
+        self.default = action;
+        self
+    }
+
+    /// Set the callback that answers `ask` decisions.
+    pub fn with_ask(mut self, callback: impl CanUseTool + 'static) -> Self {
+        self.ask = Some(Box::new(callback));
+        self
+    }
+
+    /// Resolve relative paths against `cwd`, normally the session's
+    /// `ClaudeAgentOptions::cwd`.
+    ///
+    /// `cwd` must be absolute; a relative one is ignored.
+    pub fn with_cwd(mut self, cwd: impl AsRef<Path>) -> Self {
+        self.cwd = Some(cwd.as_ref().to_string_lossy().into_owned());
+        self
+    }
+
+    /// The rules in evaluation order.
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    /// The action used when no rule matches.
+    pub fn default_action(&self) -> PolicyAction {
+        self.default
+    }
+
+    /// `evaluate` for a live request, logging the explanation.
+    pub(crate) fn evaluate_request(&self, tool_name: &str, input: &Value) -> PolicyDecision {
+        let decision = self.evaluate(tool_name, input);
+        info!("🔐 Policy decision for {}: {}", tool_name, decision.explanation);
+        decision
+    }
+
+    /// Decide on a tool use without running any callback.
+    pub fn evaluate(&self, tool_name: &str, input: &Value) -> PolicyDecision {
+        let Some((index, rule)) = self
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(tool_name, input, self.cwd.as_deref()))
+        else {
+            return PolicyDecision {
+                action: self.default,
+                rule: None,
+                explanation: format!("no rule matched {}; default is {}", tool_name, self.default),
+                updated_input: None,
+                interrupt: false,
+            };
+        };
+
+        let mut explanation = format!("rule {} ({}) says {}", index + 1, rule.describe(), rule.action);
+        if let Some(reason) = &rule.reason {
+            explanation = format!("{}: {}", explanation, reason);
+        }
+        let updated_input = match (&rule.updated_input, input) {
+            (Some(fields), Value::Object(input)) if rule.action == PolicyAction::Allow => {
+                let mut input = input.clone();
+                input.extend(fields.clone());
+                Some(Value::Object(input))
+            }
+            _ => None,
+        };
+        PolicyDecision {
+            action: rule.action,
+            rule: Some(index),
+            explanation,
+            updated_input,
+            interrupt: rule.interrupt,
+        }
+    }
+}
+
+impl fmt::Debug for PermissionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PermissionPolicy")
+            .field("rules", &self.rules)
+            .field("default", &self.default)
+            .field("ask", &self.ask.is_some())
+            .field("cwd", &self.cwd)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl CanUseTool for PermissionPolicy {
+    async fn can_use(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        let decision = self.evaluate_request(tool_name, input);
+        if decision.action != PolicyAction::Ask {
+            return Ok(decision.into_result());
+        }
+        match &self.ask {
+            Some(ask) => ask.can_use(tool_name, input, context).await,
+            None => Ok(PermissionResult::Deny(PermissionResultDeny {
+                message: format!("{}, and no ask callback is set", decision.explanation),
+                ..Default::default()
+            })),
+        }
+    }
+}
+
+/// Split a shell command line on `&&`, `||`, `;`, `|`, `&` and newlines.
+///
+/// Quoting is not interpreted, so a quoted operator also splits; this only
+/// makes allow rules stricter.
+fn split_commands(command: &str) -> Vec<&str> {
+    command
+        .split(['&', '|', ';', '\n'])
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+/// Whether `command` has a `>` or `<` outside of quotes, i.e. redirects
+/// (`>`, `>>`, `&>`), reads a file (`<`) or a heredoc (`<<`).
+fn has_redirection(command: &str) -> bool {
+    let mut quote = None;
+    let mut escaped = false;
+    for c in command.chars() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            // Nothing is special inside single quotes
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some(_), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '>' | '<') => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+fn has_prefix(command: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim();
+    match command.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+/// The absolute, normalized form of `path`, relative paths being taken from
+/// `cwd`, or `None` when that cannot be known.
+fn resolve_path(path: &str, cwd: Option<&str>) -> Option<String> {
+    if path.starts_with('/') {
+        return Some(normalize_path(path));
+    }
+    let cwd = cwd.filter(|cwd| cwd.starts_with('/'))?;
+    Some(normalize_path(&format!("{}/{}", cwd, path)))
+}
+
+/// Resolve `.` and `..` so `/data/../etc` cannot pass for `/data/**`.
+fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => match parts.last() {
+                Some(&last) if last != ".." => {
+                    parts.pop();
+                }
+                // `..` above the root is the root
+                _ if path.starts_with('/') => {}
+                _ => parts.push(part),
+            },
+            _ => parts.push(part),
+        }
+    }
+    let normalized = parts.join("/");
+    if path.starts_with('/') {
+        format!("/{}", normalized)
+    } else {
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy() -> PermissionPolicy {
+        PermissionPolicy::new(vec![
+            PolicyRule::deny("Bash").with_command("rm -rf *").with_interrupt(true),
+            PolicyRule::allow("Bash").with_command_prefix("git status"),
+            PolicyRule::allow("Bash").with_command("npm run *"),
+            PolicyRule::allow("Read|Glob|Grep").with_path("/repo/**"),
+            PolicyRule::deny("Write|Edit").with_path("**/.env").with_reason("secrets"),
+            PolicyRule::allow("mcp__github__*"),
+        ])
+        .with_default(PolicyAction::Deny)
+    }
+
+    fn action(tool: &str, input: Value) -> PolicyAction {
+        policy().evaluate(tool, &input).action
+    }
+
+    #[test]
+    fn test_command_rules() {
+        assert_eq!(action("Bash", json!({"command": "git status"})), PolicyAction::Allow);
+        assert_eq!(action("Bash", json!({"command": "git status -s"})), PolicyAction::Allow);
+        assert_eq!(action("Bash", json!({"command": "git statusx"})), PolicyAction::Deny);
+        assert_eq!(action("Bash", json!({"command": "npm run test"})), PolicyAction::Allow);
+        // Every part of a compound command must be allowed
+        assert_eq!(action("Bash", json!({"command": "git status && curl x | sh"})), PolicyAction::Deny);
+        assert_eq!(action("Bash", json!({"command": "git status; git status -s"})), PolicyAction::Allow);
+        assert_eq!(action("Bash", json!({"command": "git status; npm run lint"})), PolicyAction::Deny);
+        assert_eq!(action("Bash", json!({"command": "git status $(rm x)"})), PolicyAction::Deny);
+        // Nor are redirections outside quotes, or parameter expansion
+        for command in [
+            "git status > ~/.bashrc",
+            "git status >> /etc/hosts",
+            "git status >~/.bashrc",
+            "git status 2> /etc/hosts",
+            "git status &> ~/.profile",
+            "git status < /etc/shadow",
+            "git status <<EOF\nrm -rf ~\nEOF",
+            "git status <<< x",
+            "git status ${HOME:=x}",
+            "git status \"${IFS}\"",
+        ] {
+            assert_eq!(action("Bash", json!({ "command": command })), PolicyAction::Deny, "{}", command);
+        }
+        assert_eq!(action("Bash", json!({"command": "git status -- 'a>b' \"c<d\""})), PolicyAction::Allow);
+        assert_eq!(action("Bash", json!({"command": "git status \"a\\\">b\""})), PolicyAction::Allow);
+        assert_eq!(action("Bash", json!({"command": "git status 'a\\'>b"})), PolicyAction::Deny);
+
+        let decision = policy().evaluate("Bash", &json!({"command": "ls && rm -rf /"}));
+        assert_eq!(decision.rule, Some(0));
+        assert!(decision.interrupt);
+    }
+
+    #[test]
+    fn test_path_and_mcp_rules() {
+        assert_eq!(action("Read", json!({"file_path": "/repo/src/main.rs"})), PolicyAction::Allow);
+        assert_eq!(action("Grep", json!({"pattern": "x", "path": "/repo"})), PolicyAction::Deny);
+        assert_eq!(action("Read", json!({"file_path": "/repo/../etc/passwd"})), PolicyAction::Deny);
+        assert_eq!(action("mcp__github__create_issue", json!({})), PolicyAction::Allow);
+        assert_eq!(action("mcp__gitlab__create_issue", json!({})), PolicyAction::Deny);
+
+        let decision = policy().evaluate("Edit", &json!({"file_path": "/repo/.env"}));
+        assert_eq!(decision.rule, Some(4));
+        assert_eq!(
+            decision.explanation,
+            "rule 5 (tool \"Write|Edit\", path \"**/.env\") says deny: secrets"
+        );
+        assert_eq!(
+            policy().evaluate("Write", &json!({"file_path": "/tmp/x"})).explanation,
+            "no rule matched Write; default is deny"
+        );
+    }
+
+    #[test]
+    fn test_relative_and_missing_paths() {
+        let rules = vec![
+            PolicyRule::deny("Read|Grep").with_path("/etc/**"),
+            PolicyRule::allow("Read|Grep").with_path("/repo/**"),
+        ];
+        let read = |path: &str| json!({ "file_path": path });
+
+        // Without a cwd, paths that cannot be placed are denied, never allowed
+        let policy = PermissionPolicy::new(rules.clone()).with_default(PolicyAction::Ask);
+        assert_eq!(policy.evaluate("Read", &read("../../etc/passwd")).action, PolicyAction::Deny);
+        assert_eq!(policy.evaluate("Read", &read("src/main.rs")).action, PolicyAction::Deny);
+        assert_eq!(policy.evaluate("Grep", &json!({"pattern": "x"})).action, PolicyAction::Deny);
+        assert_eq!(policy.evaluate("Read", &read("/repo/src/main.rs")).action, PolicyAction::Allow);
+
+        let policy = PermissionPolicy::new(rules).with_default(PolicyAction::Ask).with_cwd("/repo/src");
+        assert_eq!(policy.evaluate("Read", &read("../../etc/passwd")).action, PolicyAction::Deny);
+        assert_eq!(policy.evaluate("Read", &read("main.rs")).action, PolicyAction::Allow);
+        assert_eq!(policy.evaluate("Read", &read("../../tmp/x")).action, PolicyAction::Ask);
+        // A path-less search runs in the cwd, but allowing needs an explicit path
+        assert_eq!(policy.evaluate("Grep", &json!({"pattern": "x"})).action, PolicyAction::Ask);
+        let policy = PermissionPolicy::new(vec![PolicyRule::deny("Grep").with_path("/etc/**")]).with_cwd("/etc/ssl");
+        assert_eq!(policy.evaluate("Grep", &json!({"pattern": "x"})).rule, Some(0));
+    }
+
+    #[test]
+    fn test_updated_input() {
+        let mut fields = Map::new();
+        fields.insert("timeout".to_string(), json!(5000));
+        let policy = PermissionPolicy::new(vec![PolicyRule::allow("Bash").with_updated_input(fields)]);
+        let decision = policy.evaluate("Bash", &json!({"command": "make"}));
+        assert_eq!(decision.updated_input, Some(json!({"command": "make", "timeout": 5000})));
+    }
+
+    #[test]
+    fn test_load_json_and_toml() {
+        let json = r#"{"rules": [{"tool": "Bash", "command_prefix": "ls", "action": "allow"}]}"#;
+        let from_json = PermissionPolicy::from_json(json).unwrap();
+        let from_toml = PermissionPolicy::from_toml(
+            "[[rules]]\ntool = \"Bash\"\ncommand_prefix = \"ls\"\naction = \"allow\"\n",
+        )
+        .unwrap();
+        assert_eq!(from_json.rules(), from_toml.rules());
+        assert_eq!(from_toml.default_action(), PolicyAction::Ask);
+
+        assert!(matches!(
+            PermissionPolicy::from_json(r#"{"rules": [{"tool": "Bash", "acton": "allow"}]}"#),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            PermissionPolicy::from_toml("[[rules]]\naction = \"deny\"\nupdated_input = { a = 1 }"),
+            Err(Error::InvalidConfig(msg)) if msg.contains("rule 1 sets updated_input")
+        ));
+    }
+
+    struct AllowAll;
+
+    #[async_trait]
+    impl CanUseTool for AllowAll {
+        async fn can_use(
+            &self,
+            _tool_name: &str,
+            _input: &Value,
+            _context: &ToolPermissionContext,
+        ) -> Result<PermissionResult> {
+            Ok(PermissionResult::Allow(PermissionResultAllow::default()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ask_falls_through_to_callback() {
+        let context = ToolPermissionContext::default();
+        let input = json!({"command": "make"});
+
+        let policy = PermissionPolicy::new(vec![PolicyRule::ask("Bash")]);
+        match policy.can_use("Bash", &input, &context).await.unwrap() {
+            PermissionResult::Deny(deny) => assert!(deny.message.contains("no ask callback")),
+            other => panic!("expected deny, got {:?}", other),
+        }
+
+        let policy = policy.with_ask(AllowAll);
+        assert!(matches!(
+            policy.can_use("Bash", &input, &context).await.unwrap(),
+            PermissionResult::Allow(_)
+        ));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(resolve_path("/a/../b", Some("/c")).as_deref(), Some("/b"));
+        assert_eq!(resolve_path("../x", Some("/a/b")).as_deref(), Some("/a/x"));
+        assert_eq!(resolve_path("x", None), None);
+        assert_eq!(resolve_path("x", Some("rel")), None);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/a/./b/../c"), "/a/c");
+        assert_eq!(normalize_path("/../etc"), "/etc");
+        assert_eq!(normalize_path("a//b/"), "a/b");
+        assert_eq!(normalize_path("../x"), "../x");
+        assert_eq!(normalize_path("/"), "/");
+    }
+}
@@ -0,0 +1,437 @@
+//! A small TOML reader for policy files.
+//!
+//! Only the subset of TOML a policy needs is accepted: tables, arrays of
+//! tables, dotted keys, strings, integers, floats, booleans, arrays and inline
+//! tables. Dates, times, special floats (`inf`, `nan`) and prefixed integers
+//! (`0x`, `0o`, `0b`) are not supported. Tables may be defined only once and
+//! inline tables and arrays cannot be extended, as in TOML itself. The document
+//! is returned as JSON so policies share one serde model with their JSON form.
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Number, Value};
+
+/// Parse a TOML document into a JSON object.
+pub(crate) fn parse(input: &str) -> Result<Value, String> {
+    let mut parser = Parser {
+        chars: input.chars().collect(),
+        pos: 0,
+    };
+    parser.document().map_err(|e| format!("line {}: {}", parser.line(), e))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn document(&mut self) -> Result<Value, String> {
+        let mut root = Map::new();
+        let mut current: Vec<String> = Vec::new();
+        // Tables defined by a header or dotted keys, and values that cannot be
+        // extended. Entries within an array of tables describe its last
+        // element, so they are forgotten when it gets a new one.
+        let mut defined: HashSet<Vec<String>> = HashSet::new();
+        let mut sealed: HashSet<Vec<String>> = HashSet::new();
+
+        loop {
+            self.skip_blank_lines();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.pos += 1;
+                    let array = self.eat('[');
+                    self.skip_spaces();
+                    let path = self.key_path()?;
+                    self.skip_spaces();
+                    self.expect(']')?;
+                    if let Some(end) = (1..=path.len()).find(|&end| sealed.contains(&path[..end])) {
+                        return Err(format!("`{}` cannot be extended", path[..end].join(".")));
+                    }
+                    let (last, parents) = path.split_last().expect("key path is never empty");
+                    let parent = table_mut(&mut root, parents)?;
+                    if array {
+                        self.expect(']')?;
+                        match parent
+                            .entry(last.clone())
+                            .or_insert_with(|| Value::Array(Vec::new()))
+                        {
+                            Value::Array(items) => items.push(Value::Object(Map::new())),
+                            _ => return Err(format!("`{}` is not an array of tables", last)),
+                        }
+                        defined.retain(|defined| !defined.starts_with(&path));
+                        sealed.retain(|sealed| !sealed.starts_with(&path));
+                    } else {
+                        if matches!(parent.get(last), Some(Value::Array(_))) {
+                            return Err(format!("`{}` is an array, not a table", last));
+                        }
+                        if !defined.insert(path.clone()) {
+                            return Err(format!("table `{}` is defined twice", path.join(".")));
+                        }
+                        table_mut(parent, std::slice::from_ref(last))?;
+                    }
+                    current = path;
+                }
+                Some(_) => {
+                    let path = self.key_path()?;
+                    self.skip_spaces();
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    let full: Vec<String> = current.iter().chain(&path).cloned().collect();
+                    for end in current.len() + 1..full.len() {
+                        if sealed.contains(&full[..end]) {
+                            return Err(format!("`{}` cannot be extended", full[..end].join(".")));
+                        }
+                        defined.insert(full[..end].to_vec());
+                    }
+                    if value.is_object() || value.is_array() {
+                        sealed.insert(full);
+                    }
+                    insert(table_mut(&mut root, &current)?, &path, value)?;
+                }
+            }
+            self.end_of_line()?;
+        }
+        Ok(Value::Object(root))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) => self.scalar(),
+            None => Err("expected a value".to_string()),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.eat(']') {
+                break;
+            }
+            items.push(self.value()?);
+            self.skip_blank_lines();
+            if self.eat(']') {
+                break;
+            }
+            self.expect(',')?;
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut table = Map::new();
+        self.skip_spaces();
+        if self.eat('}') {
+            return Ok(Value::Object(table));
+        }
+        loop {
+            self.skip_spaces();
+            let path = self.key_path()?;
+            self.skip_spaces();
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            insert(&mut table, &path, value)?;
+            self.skip_spaces();
+            if self.eat('}') {
+                break;
+            }
+            self.expect(',')?;
+        }
+        Ok(Value::Object(table))
+    }
+
+    fn scalar(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || "+-._".contains(c)) {
+            self.pos += 1;
+        }
+        let token: String = self.chars[start..self.pos].iter().collect();
+        match token.as_str() {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            "" => return Err(format!("unexpected {:?}", self.peek().unwrap_or(' '))),
+            _ => {}
+        }
+        let digits = token.replace('_', "");
+        if let Ok(int) = digits.parse::<i64>() {
+            return Ok(Value::Number(int.into()));
+        }
+        digits
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| format!("invalid value `{}`", token))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.next().ok_or("expected a string")?;
+        let multiline = self.peek() == Some(quote) && self.chars.get(self.pos + 1) == Some(&quote);
+        if multiline {
+            self.pos += 2;
+            // A newline right after the opening quotes is not part of the string
+            if self.eat('\r') || self.peek() == Some('\n') {
+                self.eat('\n');
+            }
+        }
+
+        let mut out = String::new();
+        loop {
+            let c = self.next().ok_or("unterminated string")?;
+            if c == quote {
+                if !multiline {
+                    return Ok(out);
+                }
+                if self.peek() == Some(quote) && self.chars.get(self.pos + 1) == Some(&quote) {
+                    self.pos += 2;
+                    return Ok(out);
+                }
+                out.push(c);
+            } else if c == '\n' && !multiline {
+                return Err("newline in string".to_string());
+            } else if c == '\\' && quote == '"' {
+                out.push(self.escape()?);
+            } else {
+                out.push(c);
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        let c = self.next().ok_or("unterminated string")?;
+        Ok(match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            '"' => '"',
+            '\\' => '\\',
+            'u' | 'U' => {
+                let len = if c == 'u' { 4 } else { 8 };
+                let hex: String = (0..len).filter_map(|_| self.next()).collect();
+                u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid unicode escape \\{}{}", c, hex))?
+            }
+            other => return Err(format!("invalid escape \\{}", other)),
+        })
+    }
+
+    fn key_path(&mut self) -> Result<Vec<String>, String> {
+        let mut path = vec![self.key()?];
+        loop {
+            self.skip_spaces();
+            if !self.eat('.') {
+                return Ok(path);
+            }
+            self.skip_spaces();
+            path.push(self.key()?);
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        if matches!(self.peek(), Some('"') | Some('\'')) {
+            return self.string();
+        }
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(format!("expected a key, found {:?}", self.peek().unwrap_or(' ')));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some('\r') if self.eat('\n') => Ok(()),
+            Some(c) => Err(format!("unexpected {:?} after value", c)),
+        }
+    }
+
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            if !(self.eat('\n') || self.eat('\r')) {
+                return;
+            }
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(match self.peek() {
+                Some(found) => format!("expected {:?}, found {:?}", c, found),
+                None => format!("expected {:?}, found end of input", c),
+            })
+        }
+    }
+
+    fn line(&self) -> usize {
+        self.chars[..self.pos.min(self.chars.len())]
+            .iter()
+            .filter(|&&c| c == '\n')
+            .count()
+            + 1
+    }
+}
+
+/// The table at `path`, created if missing; an array of tables resolves to its
+/// last element.
+fn table_mut<'a>(root: &'a mut Map<String, Value>, path: &[String]) -> Result<&'a mut Map<String, Value>, String> {
+    let mut table = root;
+    for key in path {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        let entry = match entry {
+            Value::Array(items) => items.last_mut().ok_or_else(|| format!("`{}` is not a table", key))?,
+            other => other,
+        };
+        table = entry
+            .as_object_mut()
+            .ok_or_else(|| format!("`{}` is not a table", key))?;
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Map<String, Value>, path: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = path.split_last().expect("key path is never empty");
+    let table = table_mut(table, parents)?;
+    if table.contains_key(last) {
+        return Err(format!("duplicate key `{}`", last));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_document() {
+        let doc = r#"
+# Policy
+default = "ask"
+limits.max = 1_000
+
+[[rules]]
+tool = "Bash"   # trailing comment
+commands = ["git status", 'C:\path', "tab\there"]
+enabled = true
+
+[[rules]]
+tool = "Write"
+updated_input = { mode = "safe", depth = 2.5 }
+
+[rules.extra]
+note = """
+multi
+line"""
+"#;
+        assert_eq!(
+            parse(doc).unwrap(),
+            json!({
+                "default": "ask",
+                "limits": {"max": 1000},
+                "rules": [
+                    {
+                        "tool": "Bash",
+                        "commands": ["git status", "C:\\path", "tab\there"],
+                        "enabled": true
+                    },
+                    {
+                        "tool": "Write",
+                        "updated_input": {"mode": "safe", "depth": 2.5},
+                        "extra": {"note": "multi\nline"}
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_report_line() {
+        assert_eq!(parse("a = 1\na = 2").unwrap_err(), "line 2: duplicate key `a`");
+        assert!(parse("a = \"open").unwrap_err().contains("unterminated string"));
+        assert!(parse("a = 1 b = 2").unwrap_err().starts_with("line 1:"));
+        assert!(parse("[a]\nb = 1\n[a.b]").is_err());
+    }
+
+    #[test]
+    fn test_tables_cannot_be_redefined() {
+        assert_eq!(
+            parse("[a]\nx = 1\n[a]\ny = 2").unwrap_err(),
+            "line 3: table `a` is defined twice"
+        );
+        assert_eq!(
+            parse("[[rules]]\nx = 1\n[rules]\ny = 2").unwrap_err(),
+            "line 3: `rules` is an array, not a table"
+        );
+        assert!(parse("[rules]\n[[rules]]").is_err());
+        assert!(parse("a.b = 1\n[a.b]").is_err());
+        assert!(parse("a = { x = 1 }\n[a]").is_err());
+        assert!(parse("a = { x = 1 }\n[a.b]").is_err());
+        assert!(parse("a = { x = 1 }\na.y = 2").is_err());
+        assert!(parse("a = [1]\n[[a]]").is_err());
+
+        // Each element of an array of tables has its own sub-tables
+        assert_eq!(
+            parse("[[r]]\n[r.x]\nn = 1\n[[r]]\n[r.x]\nn = 2\n[a]\nb.c = 1\n[a.b.d]").unwrap(),
+            json!({"r": [{"x": {"n": 1}}, {"x": {"n": 2}}], "a": {"b": {"c": 1, "d": {}}}})
+        );
+    }
+}
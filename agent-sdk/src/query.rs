@@ -0,0 +1,373 @@
+//! One-shot query API: prompt in, answer out.
+//!
+//! [`query`] runs a single prompt through `claude --print` and streams the
+//! resulting messages. [`query_outcome`] drains that stream into a
+//! [`QueryOutcome`] holding the final answer, result metadata, cost and usage.
+//!
+//! Use [`ClaudeClient`] instead when you need follow-up messages, interrupts,
+//! permission callbacks or hooks.
+
+use async_stream::stream;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use tracing::warn;
+
+use crate::client::{ClaudeClient, ClientPromptInput};
+use crate::internal::transport::Transport;
+use crate::types::{
+    ClaudeAgentOptions, ContentBlock, Error, Message, ProtocolMessage, Result, ResultMessage,
+    Usage,
+};
+
+/// Stream of messages produced by a one-shot query.
+pub type QueryStream = Pin<Box<dyn Stream<Item = Result<Message>> + Send>>;
+
+/// Everything a one-shot query produced, collected from its message stream.
+#[derive(Debug, Clone)]
+pub struct QueryOutcome {
+    /// Final answer: the result text, or the text of the last assistant message.
+    pub text: Option<String>,
+    /// The `result` message that ended the turn.
+    pub result: ResultMessage,
+    /// Structured output, when `output_format` requested a JSON schema.
+    pub structured_output: Option<serde_json::Value>,
+    /// Total cost in USD as reported by the CLI.
+    pub total_cost_usd: Option<f64>,
+    /// Token usage as reported by the CLI.
+    pub usage: Option<Usage>,
+    /// Every message received, in order, including the result.
+    pub messages: Vec<Message>,
+}
+
+impl QueryOutcome {
+    /// Drain a query stream into an outcome.
+    ///
+    /// Output the transport could not deliver (`Error::MessageParse`) and
+    /// messages dropped for a slow reader (`Error::Lagged`) are logged and
+    /// skipped, so one bad line does not lose the result.
+    ///
+    /// # Errors
+    /// Returns the first error that ends the stream, or `Error::Process` if
+    /// the stream ended without a `result` message.
+    pub async fn collect(mut stream: QueryStream) -> Result<Self> {
+        let mut messages = Vec::new();
+        while let Some(message) = stream.next().await {
+            match message {
+                Ok(message) => messages.push(message),
+                Err(e @ (Error::MessageParse(_) | Error::Lagged(_))) => {
+                    warn!("Skipping undeliverable message: {}", e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let result = match messages.last() {
+            Some(Message::Result(result)) => result.clone(),
+            _ => {
+                return Err(Error::Process(
+                    "Claude Code exited without sending a result".to_string(),
+                ))
+            }
+        };
+
+        let text = result.result.clone().or_else(|| last_assistant_text(&messages));
+        Ok(Self {
+            text,
+            structured_output: result.structured_output.clone(),
+            total_cost_usd: result.total_cost_usd,
+            usage: result.usage.clone(),
+            result,
+            messages,
+        })
+    }
+}
+
+/// Run a single prompt and stream the messages Claude produces.
+///
+/// The prompt is a string or a stream of input messages (see
+/// [`ClientPromptInput`]). Input is closed once the prompt has been delivered,
+/// so the CLI exits after answering. `can_use_tool`, hooks and SDK MCP servers
+/// need a stream prompt; input then stays open until the first result so the
+/// callbacks can be answered.
+/// The stream ends after the `result` message, at which point the session is
+/// disconnected.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use claude_agent_sdk::{query, ClaudeAgentOptions, Message};
+/// use futures::StreamExt;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut messages = query("What is 2 + 2?", ClaudeAgentOptions::new()).await?;
+///     while let Some(message) = messages.next().await {
+///         if let Message::Result(result) = message? {
+///             println!("{:?}", result.result);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn query(
+    prompt: impl Into<ClientPromptInput>,
+    options: ClaudeAgentOptions,
+) -> Result<QueryStream> {
+    let mut client = ClaudeClient::new(options);
+    client.connect_with_mode(Some(prompt.into()), true).await?;
+    into_query_stream(client)
+}
+
+/// Run a single prompt over a custom transport.
+///
+/// The prompt is sent as user messages once the transport is connected; see
+/// [`ClaudeClient::with_transport`].
+pub async fn query_with_transport(
+    prompt: impl Into<ClientPromptInput>,
+    options: ClaudeAgentOptions,
+    transport: impl Transport + 'static,
+) -> Result<QueryStream> {
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect_with_mode(Some(prompt.into()), true).await?;
+    into_query_stream(client)
+}
+
+/// Run a single prompt and collect the outcome.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use claude_agent_sdk::{query_outcome, ClaudeAgentOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let outcome = query_outcome("Summarize README.md", ClaudeAgentOptions::new()).await?;
+///     println!("{}", outcome.text.unwrap_or_default());
+///     println!("cost: ${:.4}", outcome.total_cost_usd.unwrap_or(0.0));
+///     Ok(())
+/// }
+/// ```
+pub async fn query_outcome(
+    prompt: impl Into<ClientPromptInput>,
+    options: ClaudeAgentOptions,
+) -> Result<QueryOutcome> {
+    QueryOutcome::collect(query(prompt, options).await?).await
+}
+
+fn into_query_stream(mut client: ClaudeClient) -> Result<QueryStream> {
+    // Use the receiver created with the session so nothing sent before this
+    // point is missed
+    let mut events = client
+        .take_event_receiver()
+        .ok_or_else(|| Error::CLIConnection("Not connected".to_string()))?;
+
+    let message_stream = stream! {
+        loop {
+            let protocol_msg = match events.recv().await {
+                Some(Ok(msg)) => msg,
+                Some(Err(e)) => {
+                    yield Err(e);
+                    continue;
+                }
+                None => {
+                    // The CLI went away before the result
+                    if let Some(exit) = client.process_exit() {
+                        yield Err(exit.to_error());
+                    }
+                    break;
+                }
+            };
+            if matches!(
+                protocol_msg,
+                ProtocolMessage::ControlRequest { .. }
+                    | ProtocolMessage::ControlResponse { .. }
+                    | ProtocolMessage::ControlCancelRequest { .. }
+            ) {
+                continue;
+            }
+
+            match crate::internal::protocol_message_to_message(protocol_msg) {
+                Ok(message) => {
+                    let is_result = matches!(message, Message::Result(_));
+                    yield Ok(message);
+                    if is_result {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    yield Err(e);
+                    break;
+                }
+            }
+        }
+        let _ = client.disconnect().await;
+    };
+
+    Ok(Box::pin(message_stream))
+}
+
+fn last_assistant_text(messages: &[Message]) -> Option<String> {
+    messages.iter().rev().find_map(|message| match message {
+        Message::Assistant(assistant) => {
+            let text: Vec<&str> = assistant
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            (!text.is_empty()).then(|| text.join(""))
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockCli};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_query_outcome_collects_result() {
+        let mut result = testing::result_success("session-1");
+        result["structured_output"] = json!({"answer": 4});
+        let (transport, handle) = MockCli::new()
+            .expect(json!({"type": "user", "message": {"content": "What is 2 + 2?"}}))
+            .emit(testing::system_init("session-1"))
+            .emit(testing::assistant_text("4"))
+            .emit(result)
+            .build();
+
+        let stream = query_with_transport("What is 2 + 2?", ClaudeAgentOptions::new(), transport)
+            .await
+            .unwrap();
+        let outcome = QueryOutcome::collect(stream).await.unwrap();
+
+        assert_eq!(outcome.text.as_deref(), Some("done"));
+        assert_eq!(outcome.structured_output, Some(json!({"answer": 4})));
+        assert_eq!(outcome.total_cost_usd, Some(0.001));
+        assert_eq!(outcome.usage.unwrap().output_tokens, 5);
+        assert_eq!(outcome.messages.len(), 3);
+        handle.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_outcome_skips_undeliverable_lines() {
+        let (transport, handle) = MockCli::new()
+            .expect(json!({"type": "user"}))
+            .emit(testing::assistant_text("4"))
+            .emit_error(Error::MessageParse("line exceeds 1048576 bytes".to_string()))
+            .emit(testing::result_success("session-1"))
+            .build();
+
+        let stream = query_with_transport("Hi", ClaudeAgentOptions::new(), transport)
+            .await
+            .unwrap();
+        let outcome = QueryOutcome::collect(stream).await.unwrap();
+
+        assert_eq!(outcome.text.as_deref(), Some("done"));
+        assert_eq!(outcome.messages.len(), 2);
+        handle.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_fails_when_cli_exits_without_result() {
+        let (transport, handle) = MockCli::new()
+            .expect(json!({"type": "user"}))
+            .emit(testing::system_init("session-1"))
+            .eof()
+            .build();
+
+        let stream = query_with_transport("Hi", ClaudeAgentOptions::new(), transport)
+            .await
+            .unwrap();
+        match QueryOutcome::collect(stream).await {
+            Err(Error::Process(msg)) => assert!(msg.contains("closed its output"), "{}", msg),
+            other => panic!("expected process error, got {:?}", other.map(|o| o.text)),
+        }
+        handle.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_stream_closes_input_when_prompt_ends() {
+        let (transport, handle) = MockCli::new()
+            .expect(json!({"type": "user", "message": {"content": "first"}}))
+            .expect(json!({"type": "user", "message": {"content": "second"}}))
+            .expect_end_of_input()
+            .emit(testing::result_success("session-1"))
+            .build();
+
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        for text in ["first", "second"] {
+            let message = crate::types::InputMessage::user(text, "default".to_string());
+            tx.send(serde_json::to_value(message).unwrap()).await.unwrap();
+        }
+        drop(tx);
+
+        let stream = query_with_transport(rx, ClaudeAgentOptions::new(), transport)
+            .await
+            .unwrap();
+        let outcome = QueryOutcome::collect(stream).await.unwrap();
+        assert_eq!(outcome.messages.len(), 1);
+        handle.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_keeps_input_open_for_callbacks_until_result() {
+        struct AllowAll;
+
+        #[async_trait::async_trait]
+        impl crate::types::CanUseTool for AllowAll {
+            async fn can_use(
+                &self,
+                _tool_name: &str,
+                _input: &serde_json::Value,
+                _context: &crate::types::ToolPermissionContext,
+            ) -> Result<crate::types::PermissionResult> {
+                Ok(crate::types::PermissionResult::Allow(Default::default()))
+            }
+        }
+
+        let (transport, handle) = MockCli::new()
+            .expect(json!({"type": "user", "message": {"content": "list files"}}))
+            .emit(testing::can_use_tool_request("perm_1", "Bash", json!({"command": "ls"})))
+            .expect(json!({
+                "type": "control_response",
+                "response": {"request_id": "perm_1", "response": {"behavior": "allow"}}
+            }))
+            .emit(testing::result_success("session-1"))
+            .expect_end_of_input()
+            .build();
+
+        let (tx, rx) = tokio::sync::mpsc::channel(1);
+        let message = crate::types::InputMessage::user("list files", "default".to_string());
+        tx.send(serde_json::to_value(message).unwrap()).await.unwrap();
+        drop(tx);
+
+        let mut options = ClaudeAgentOptions::new();
+        options.can_use_tool = Some(Box::new(AllowAll));
+        let stream = query_with_transport(rx, options, transport).await.unwrap();
+        QueryOutcome::collect(stream).await.unwrap();
+        handle.finish().await.unwrap();
+    }
+
+    #[test]
+    fn test_last_assistant_text() {
+        let assistant = |blocks: Vec<&str>| {
+            Message::Assistant(crate::types::AssistantMessage {
+                content: blocks
+                    .into_iter()
+                    .map(|text| ContentBlock::Text { text: text.to_string() })
+                    .collect(),
+                model: "m".to_string(),
+                parent_tool_use_id: None,
+                error: None,
+            })
+        };
+        let messages = vec![assistant(vec!["first"]), assistant(vec!["a", "b"])];
+        assert_eq!(last_assistant_text(&messages).as_deref(), Some("ab"));
+        assert_eq!(last_assistant_text(&[]), None);
+    }
+}
@@ -0,0 +1,590 @@
+//! Scripted mock of the Claude Code CLI for deterministic tests.
+//!
+//! [`MockCli`] replays a stream-json conversation through an in-process
+//! [`Transport`], so tests can drive [`ClaudeClient`](crate::ClaudeClient)
+//! through permission flows, hooks and interrupts without the real `claude`
+//! binary or network access. Every line the SDK writes is recorded and can be
+//! asserted on.
+//!
+//! # Example
+//!
+//! ```rust
+//! use claude_agent_sdk::testing::{self, MockCli};
+//! use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+//! use serde_json::json;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let (transport, handle) = MockCli::new()
+//!     .expect(json!({"type": "user", "message": {"content": "Hi"}}))
+//!     .emit(testing::system_init("session-1"))
+//!     .emit(testing::assistant_text("Hello!"))
+//!     .emit(testing::result_success("session-1"))
+//!     .build();
+//!
+//! let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+//! client.connect(None).await?;
+//! client.send_to_cc("Hi", None).await?;
+//!
+//! handle.finish().await.unwrap();
+//! client.disconnect().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::internal::transport::Transport;
+use crate::types::{Error, ProtocolMessage, Result};
+
+/// How long an `expect` step waits for the SDK before failing.
+const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Step {
+    Emit(Value),
+    EmitError(Error),
+    Expect(Value),
+    RespondToControl {
+        subtype: String,
+        result: std::result::Result<Value, String>,
+    },
+    Eof,
+    ExpectEndOfInput,
+}
+
+/// Builder for a scripted conversation.
+///
+/// Steps run in order. `emit` steps send a message to the SDK immediately;
+/// `expect` and `respond_to_control` steps wait for the SDK's next write.
+/// Control requests whose subtype is auto-acknowledged (by default only
+/// `initialize`) are answered with an empty success wherever they occur, so
+/// scripts do not need to account for connection setup.
+pub struct MockCli {
+    steps: Vec<Step>,
+    auto_ack: HashSet<String>,
+    step_timeout: Duration,
+}
+
+impl MockCli {
+    /// Create an empty script that auto-acknowledges `initialize`.
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            auto_ack: HashSet::from(["initialize".to_string()]),
+            step_timeout: DEFAULT_STEP_TIMEOUT,
+        }
+    }
+
+    /// Send a message to the SDK as if the CLI printed it on stdout.
+    pub fn emit(mut self, message: Value) -> Self {
+        self.steps.push(Step::Emit(message));
+        self
+    }
+
+    /// Report a read failure to the SDK, as the transport does for a line it
+    /// cannot deliver (for example one over the size limit).
+    pub fn emit_error(mut self, error: Error) -> Self {
+        self.steps.push(Step::EmitError(error));
+        self
+    }
+
+    /// Wait for the SDK to write a message containing `pattern`.
+    ///
+    /// Matching is structural: every key in `pattern` must be present with a
+    /// matching value, while extra keys in the written message are ignored.
+    pub fn expect(mut self, pattern: Value) -> Self {
+        self.steps.push(Step::Expect(pattern));
+        self
+    }
+
+    /// Wait for a control request of `subtype` and answer it with success.
+    pub fn respond_to_control(mut self, subtype: impl Into<String>, response: Value) -> Self {
+        self.steps.push(Step::RespondToControl {
+            subtype: subtype.into(),
+            result: Ok(response),
+        });
+        self
+    }
+
+    /// Wait for a control request of `subtype` and answer it with an error.
+    pub fn fail_control(mut self, subtype: impl Into<String>, error: impl Into<String>) -> Self {
+        self.steps.push(Step::RespondToControl {
+            subtype: subtype.into(),
+            result: Err(error.into()),
+        });
+        self
+    }
+
+    /// Wait for the SDK to close its input side (stdin), as a one-shot query does.
+    ///
+    /// Auto-acknowledged control requests may still arrive first; any other
+    /// write fails the step.
+    pub fn expect_end_of_input(mut self) -> Self {
+        self.steps.push(Step::ExpectEndOfInput);
+        self
+    }
+
+    /// Close the message stream, as if the CLI exited.
+    pub fn eof(mut self) -> Self {
+        self.steps.push(Step::Eof);
+        self
+    }
+
+    /// Answer control requests of `subtype` with an empty success whenever they arrive.
+    pub fn auto_ack(mut self, subtype: impl Into<String>) -> Self {
+        self.auto_ack.insert(subtype.into());
+        self
+    }
+
+    /// Set how long each waiting step may take (default: 5s).
+    pub fn with_step_timeout(mut self, timeout: Duration) -> Self {
+        self.step_timeout = timeout;
+        self
+    }
+
+    /// Build the transport to hand to the client and the handle to assert with.
+    pub fn build(self) -> (MockTransport, MockCliHandle) {
+        let (message_tx, message_rx) = mpsc::channel(100);
+        let unread = message_tx.downgrade();
+        let (written_tx, written_rx) = mpsc::unbounded_channel();
+        let (done_tx, done_rx) = oneshot::channel();
+        let written = Arc::new(Mutex::new(Vec::new()));
+
+        let driver = Driver {
+            steps: self.steps,
+            auto_ack: self.auto_ack,
+            step_timeout: self.step_timeout,
+            message_tx: Some(message_tx),
+            written_rx,
+        };
+
+        let transport = MockTransport {
+            driver: Some((driver, done_tx)),
+            message_rx: Some(message_rx),
+            written_tx: Some(written_tx),
+            written: written.clone(),
+        };
+        let handle = MockCliHandle {
+            written,
+            unread,
+            done_rx,
+        };
+        (transport, handle)
+    }
+}
+
+impl Default for MockCli {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// In-process transport playing back a [`MockCli`] script.
+///
+/// The script starts running when the transport is connected.
+pub struct MockTransport {
+    driver: Option<(Driver, oneshot::Sender<std::result::Result<(), String>>)>,
+    message_rx: Option<mpsc::Receiver<Result<ProtocolMessage>>>,
+    written_tx: Option<mpsc::UnboundedSender<Value>>,
+    written: Arc<Mutex<Vec<Value>>>,
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn connect(&mut self) -> Result<()> {
+        if let Some((driver, done_tx)) = self.driver.take() {
+            tokio::spawn(driver.run(done_tx));
+        }
+        Ok(())
+    }
+
+    fn read_messages(&mut self) -> Result<mpsc::Receiver<Result<ProtocolMessage>>> {
+        self.message_rx
+            .take()
+            .ok_or_else(|| Error::CLIConnection("Messages already being read".to_string()))
+    }
+
+    async fn write(&mut self, data: &str) -> Result<()> {
+        let written_tx = self
+            .written_tx
+            .as_ref()
+            .ok_or_else(|| Error::CLIConnection("Transport input closed".to_string()))?;
+
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            let value = serde_json::from_str(line).unwrap_or_else(|_| Value::String(line.to_string()));
+            self.written.lock().unwrap().push(value.clone());
+            let _ = written_tx.send(value);
+        }
+        Ok(())
+    }
+
+    async fn end_input(&mut self) -> Result<()> {
+        self.written_tx = None;
+        Ok(())
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.end_input().await
+    }
+
+    fn is_ready(&self) -> bool {
+        self.written_tx.is_some()
+    }
+}
+
+/// Handle for inspecting what the SDK wrote and whether the script passed.
+pub struct MockCliHandle {
+    written: Arc<Mutex<Vec<Value>>>,
+    // Does not keep the message stream open past `eof`
+    unread: mpsc::WeakSender<Result<ProtocolMessage>>,
+    done_rx: oneshot::Receiver<std::result::Result<(), String>>,
+}
+
+impl MockCliHandle {
+    /// All messages the SDK has written so far, in order.
+    pub fn written(&self) -> Vec<Value> {
+        self.written.lock().unwrap().clone()
+    }
+
+    /// Number of emitted messages the SDK has not read yet.
+    ///
+    /// Reports 0 once the script has closed the message stream.
+    pub fn unread(&self) -> usize {
+        match self.unread.upgrade() {
+            Some(tx) => tx.max_capacity() - tx.capacity(),
+            None => 0,
+        }
+    }
+
+    /// Wait for the script to complete.
+    ///
+    /// Returns a description of the first failed step, e.g. an unexpected
+    /// message or a step that timed out.
+    pub async fn finish(self) -> std::result::Result<(), String> {
+        self.done_rx
+            .await
+            .unwrap_or_else(|_| Err("mock CLI was never connected".to_string()))
+    }
+}
+
+struct Driver {
+    steps: Vec<Step>,
+    auto_ack: HashSet<String>,
+    step_timeout: Duration,
+    message_tx: Option<mpsc::Sender<Result<ProtocolMessage>>>,
+    written_rx: mpsc::UnboundedReceiver<Value>,
+}
+
+impl Driver {
+    async fn run(mut self, done_tx: oneshot::Sender<std::result::Result<(), String>>) {
+        let steps = std::mem::take(&mut self.steps);
+        let mut result = Ok(());
+        for (index, step) in steps.into_iter().enumerate() {
+            if let Err(e) = self.run_step(step).await {
+                result = Err(format!("step {}: {}", index, e));
+                break;
+            }
+        }
+        let _ = done_tx.send(result);
+
+        // Keep acknowledging control requests until the SDK goes away
+        while let Some(value) = self.written_rx.recv().await {
+            self.try_auto_ack(&value).await;
+        }
+    }
+
+    async fn run_step(&mut self, step: Step) -> std::result::Result<(), String> {
+        match step {
+            Step::Emit(message) => self.emit(message).await,
+            Step::EmitError(error) => {
+                let message_tx = self
+                    .message_tx
+                    .as_ref()
+                    .ok_or_else(|| "cannot emit after eof".to_string())?;
+                message_tx
+                    .send(Err(error))
+                    .await
+                    .map_err(|_| "SDK stopped reading messages".to_string())
+            }
+            Step::Expect(pattern) => loop {
+                let value = self.next_written(&pattern).await?;
+                let acked = self.try_auto_ack(&value).await;
+                if json_contains(&value, &pattern) {
+                    return Ok(());
+                }
+                if !acked {
+                    return Err(format!("expected message matching {}, got {}", pattern, value));
+                }
+            },
+            Step::RespondToControl { subtype, result } => {
+                let pattern = json!({"type": "control_request", "request": {"subtype": subtype}});
+                loop {
+                    let value = self.next_written(&pattern).await?;
+                    if json_contains(&value, &pattern) {
+                        let request_id = value["request_id"].clone();
+                        let response = match result {
+                            Ok(response) => json!({
+                                "subtype": "success",
+                                "request_id": request_id,
+                                "response": response,
+                            }),
+                            Err(error) => json!({
+                                "subtype": "error",
+                                "request_id": request_id,
+                                "error": error,
+                            }),
+                        };
+                        return self
+                            .emit(json!({"type": "control_response", "response": response}))
+                            .await;
+                    }
+                    if !self.try_auto_ack(&value).await {
+                        return Err(format!("expected {} control request, got {}", subtype, value));
+                    }
+                }
+            }
+            Step::Eof => {
+                self.message_tx = None;
+                Ok(())
+            }
+            Step::ExpectEndOfInput => loop {
+                match tokio::time::timeout(self.step_timeout, self.written_rx.recv()).await {
+                    Ok(None) => return Ok(()),
+                    Ok(Some(value)) => {
+                        if !self.try_auto_ack(&value).await {
+                            return Err(format!("expected end of input, got {}", value));
+                        }
+                    }
+                    Err(_) => return Err("timed out waiting for end of input".to_string()),
+                }
+            },
+        }
+    }
+
+    async fn next_written(&mut self, waiting_for: &Value) -> std::result::Result<Value, String> {
+        match tokio::time::timeout(self.step_timeout, self.written_rx.recv()).await {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Err(format!("SDK closed input while waiting for {}", waiting_for)),
+            Err(_) => Err(format!("timed out waiting for {}", waiting_for)),
+        }
+    }
+
+    async fn emit(&mut self, message: Value) -> std::result::Result<(), String> {
+        let parsed: ProtocolMessage = serde_json::from_value(message.clone())
+            .map_err(|e| format!("invalid scripted message {}: {}", message, e))?;
+        let message_tx = self
+            .message_tx
+            .as_ref()
+            .ok_or_else(|| "cannot emit after eof".to_string())?;
+        message_tx
+            .send(Ok(parsed))
+            .await
+            .map_err(|_| "SDK stopped reading messages".to_string())
+    }
+
+    /// Answer `value` if it is an auto-acknowledged control request.
+    async fn try_auto_ack(&mut self, value: &Value) -> bool {
+        let is_auto_ack = value["type"] == "control_request"
+            && value["request"]["subtype"]
+                .as_str()
+                .is_some_and(|subtype| self.auto_ack.contains(subtype));
+        if !is_auto_ack {
+            return false;
+        }
+
+        let response = json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": value["request_id"],
+                "response": {},
+            }
+        });
+        let _ = self.emit(response).await;
+        true
+    }
+}
+
+/// Check that `actual` contains everything in `expected`.
+///
+/// Objects match when every expected key matches; arrays must have the same
+/// length and match element-wise; other values must be equal.
+pub fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual_value| json_contains(actual_value, value))
+        }),
+        (Value::Array(actual), Value::Array(expected)) => {
+            actual.len() == expected.len()
+                && actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(actual, expected)| json_contains(actual, expected))
+        }
+        _ => actual == expected,
+    }
+}
+
+/// `system` `init` message announcing a session.
+pub fn system_init(session_id: &str) -> Value {
+    json!({
+        "type": "system",
+        "subtype": "init",
+        "session_id": session_id,
+        "cwd": "/tmp",
+        "model": "claude-sonnet-4-5",
+        "permissionMode": "default",
+        "tools": ["Bash", "Read", "Write"],
+        "mcp_servers": [],
+        "slash_commands": [],
+        "apiKeySource": "none",
+        "output_style": "default",
+        "agents": [],
+        "skills": [],
+        "plugins": [],
+        "uuid": uuid::Uuid::new_v4().to_string(),
+    })
+}
+
+/// Assistant message with a single text block.
+pub fn assistant_text(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "model": "claude-sonnet-4-5",
+            "id": format!("msg_{}", uuid::Uuid::new_v4().simple()),
+            "role": "assistant",
+            "content": [{"type": "text", "text": text}],
+        },
+        "parent_tool_use_id": null,
+        "session_id": "default",
+    })
+}
+
+/// Assistant message requesting a tool call.
+pub fn assistant_tool_use(tool_use_id: &str, tool_name: &str, input: Value) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "model": "claude-sonnet-4-5",
+            "id": format!("msg_{}", uuid::Uuid::new_v4().simple()),
+            "role": "assistant",
+            "content": [{"type": "tool_use", "id": tool_use_id, "name": tool_name, "input": input}],
+        },
+        "parent_tool_use_id": null,
+        "session_id": "default",
+    })
+}
+
+/// `can_use_tool` control request asking the SDK for permission.
+pub fn can_use_tool_request(request_id: &str, tool_name: &str, input: Value) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": request_id,
+        "request": {
+            "subtype": "can_use_tool",
+            "tool_name": tool_name,
+            "input": input,
+            "permission_suggestions": [],
+        }
+    })
+}
+
+/// `hook_callback` control request invoking a registered hook.
+pub fn hook_callback_request(request_id: &str, callback_id: &str, input: Value) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": request_id,
+        "request": {
+            "subtype": "hook_callback",
+            "callback_id": callback_id,
+            "input": input,
+        }
+    })
+}
+
+/// `control_cancel_request` withdrawing an earlier control request.
+pub fn control_cancel_request(request_id: &str) -> Value {
+    json!({
+        "type": "control_cancel_request",
+        "request_id": request_id,
+    })
+}
+
+/// Successful `result` message ending a turn.
+pub fn result_success(session_id: &str) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1200,
+        "duration_api_ms": 1000,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": session_id,
+        "total_cost_usd": 0.001,
+        "usage": {"input_tokens": 10, "output_tokens": 5},
+        "result": "done",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_contains() {
+        let actual = json!({"type": "user", "message": {"role": "user", "content": "Hi"}});
+        assert!(json_contains(&actual, &json!({"type": "user"})));
+        assert!(json_contains(&actual, &json!({"message": {"content": "Hi"}})));
+        assert!(!json_contains(&actual, &json!({"message": {"content": "Bye"}})));
+        assert!(!json_contains(&actual, &json!({"session_id": "x"})));
+        assert!(json_contains(&json!([1, {"a": 1, "b": 2}]), &json!([1, {"a": 1}])));
+        assert!(!json_contains(&json!([1, 2]), &json!([1])));
+    }
+
+    #[test]
+    fn test_helpers_parse_as_protocol_messages() {
+        for message in [
+            system_init("s"),
+            assistant_text("hi"),
+            assistant_tool_use("toolu_1", "Bash", json!({"command": "ls"})),
+            can_use_tool_request("req_1", "Bash", json!({"command": "ls"})),
+            hook_callback_request("req_2", "hook_0", json!({})),
+            result_success("s"),
+        ] {
+            serde_json::from_value::<ProtocolMessage>(message.clone())
+                .unwrap_or_else(|e| panic!("{} failed to parse: {}", message, e));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_write_fails_script() {
+        let (mut transport, handle) = MockCli::new()
+            .expect(json!({"type": "user"}))
+            .build();
+        transport.connect().await.unwrap();
+        transport.write("{\"type\":\"other\"}\n").await.unwrap();
+
+        let err = handle.finish().await.unwrap_err();
+        assert!(err.contains("expected message matching"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_step_timeout() {
+        let (mut transport, handle) = MockCli::new()
+            .with_step_timeout(Duration::from_millis(20))
+            .expect(json!({"type": "user"}))
+            .build();
+        transport.connect().await.unwrap();
+
+        let err = handle.finish().await.unwrap_err();
+        assert!(err.contains("timed out"), "{}", err);
+    }
+}
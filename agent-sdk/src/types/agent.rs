@@ -3,12 +3,17 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::types::hooks::{HookEvent, HookMatcher};
 use crate::types::mcp::{McpServerConfig, SdkPluginConfig};
 use crate::types::permissions::{CanUseTool, PermissionMode};
 use crate::types::sandbox::SandboxSettings;
 
+/// Callback receiving each line the CLI writes to stderr.
+pub type StderrCallback = Arc<dyn Fn(String) + Send + Sync>;
+
 /// Setting source types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -121,7 +126,7 @@ pub struct ClaudeAgentOptions {
     pub cwd: Option<PathBuf>,
     /// Path to Claude CLI.
     pub cli_path: Option<PathBuf>,
-    /// Settings file path.
+    /// Settings file path, or a JSON object as a string.
     pub settings: Option<String>,
     /// Additional directories to add.
     pub add_dirs: Vec<PathBuf>,
@@ -129,10 +134,13 @@ pub struct ClaudeAgentOptions {
     pub env: HashMap<String, String>,
     /// Extra CLI arguments.
     pub extra_args: HashMap<String, Option<String>>,
-    /// Maximum buffer size for CLI stdout.
+    /// Longest stdout line accepted from the CLI, in bytes (default 1MB).
+    /// Longer lines are skipped and reported as `Error::MessageParse`.
     pub max_buffer_size: Option<usize>,
-    /// Callback for stderr output from CLI.
-    pub stderr: Option<Box<dyn Fn(String) + Send + Sync>>,
+    /// Callback for stderr output from CLI, called once per line.
+    pub stderr: Option<StderrCallback>,
+    /// Number of stderr lines kept for `ClaudeClient::stderr_lines()` (default: 100).
+    pub stderr_buffer_lines: Option<usize>,
     /// Tool permission callback.
     pub can_use_tool: Option<Box<dyn CanUseTool>>,
     /// Hook configurations.
@@ -147,7 +155,8 @@ pub struct ClaudeAgentOptions {
     pub agents: Option<HashMap<String, AgentDefinition>>,
     /// Setting sources to load (user, project, local).
     pub setting_sources: Option<Vec<SettingSource>>,
-    /// Sandbox configuration for bash command isolation.
+    /// Sandbox configuration for bash command isolation, passed to the CLI as
+    /// the `sandbox` key of `--settings` (merged with `settings` if set).
     pub sandbox: Option<SandboxSettings>,
     /// Plugin configurations.
     pub plugins: Vec<SdkPluginConfig>,
@@ -157,6 +166,13 @@ pub struct ClaudeAgentOptions {
     pub output_format: Option<serde_json::Value>,
     /// Enable file checkpointing to track file changes during the session.
     pub enable_file_checkpointing: bool,
+    /// How long to wait for the CLI to answer a control request (default: 60s).
+    pub control_request_timeout: Option<Duration>,
+    /// Messages buffered per message stream subscriber (default: 100).
+    pub event_buffer_capacity: Option<usize>,
+    /// Never drop messages for slow subscribers: the session stops reading
+    /// from the CLI until every subscriber has caught up.
+    pub lossless_events: bool,
 }
 
 impl ClaudeAgentOptions {
@@ -168,7 +184,8 @@ impl ClaudeAgentOptions {
     /// Clone the options, excluding non-cloneable fields (callbacks, hooks).
     ///
     /// This creates a shallow clone that copies all configuration values
-    /// but does not clone trait objects (can_use_tool, hooks, stderr).
+    /// but does not clone trait objects (can_use_tool, hooks). The stderr
+    /// callback is shared.
     #[allow(clippy::should_implement_trait)]
     pub fn clone(&self) -> Self {
         Self {
@@ -193,7 +210,8 @@ impl ClaudeAgentOptions {
             env: self.env.clone(),
             extra_args: self.extra_args.clone(),
             max_buffer_size: self.max_buffer_size,
-            stderr: None,       // Cannot clone function pointer
+            stderr: self.stderr.clone(),
+            stderr_buffer_lines: self.stderr_buffer_lines,
             can_use_tool: None, // Cannot clone trait object
             hooks: None,        // Cannot clone hooks
             user: self.user.clone(),
@@ -206,6 +224,9 @@ impl ClaudeAgentOptions {
             max_thinking_tokens: self.max_thinking_tokens,
             output_format: self.output_format.clone(),
             enable_file_checkpointing: self.enable_file_checkpointing,
+            control_request_timeout: self.control_request_timeout,
+            event_buffer_capacity: self.event_buffer_capacity,
+            lossless_events: self.lossless_events,
         }
     }
 
@@ -252,11 +273,52 @@ impl ClaudeAgentOptions {
     }
 
     /// Enable sandbox.
+    ///
+    /// Combined with `settings` when both are set; keys set to different
+    /// values in both fail with `Error::InvalidConfig`.
     pub fn with_sandbox(mut self, sandbox: SandboxSettings) -> Self {
         self.sandbox = Some(sandbox);
         self
     }
 
+    /// Set the timeout for control requests such as `set_model` or `interrupt`.
+    pub fn with_control_request_timeout(mut self, timeout: Duration) -> Self {
+        self.control_request_timeout = Some(timeout);
+        self
+    }
+
+    /// Set how many messages each message stream may fall behind.
+    pub fn with_event_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.event_buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Apply backpressure instead of dropping messages for slow streams.
+    ///
+    /// Every open stream must keep being polled, or the session stalls.
+    pub fn with_lossless_events(mut self, lossless: bool) -> Self {
+        self.lossless_events = lossless;
+        self
+    }
+
+    /// Call `callback` with each line the CLI writes to stderr.
+    pub fn with_stderr(mut self, callback: impl Fn(String) + Send + Sync + 'static) -> Self {
+        self.stderr = Some(Arc::new(callback));
+        self
+    }
+
+    /// Set how many stderr lines the client keeps.
+    pub fn with_stderr_buffer_lines(mut self, lines: usize) -> Self {
+        self.stderr_buffer_lines = Some(lines);
+        self
+    }
+
+    /// Set the longest stdout line accepted from the CLI, in bytes.
+    pub fn with_max_buffer_size(mut self, max_buffer_size: usize) -> Self {
+        self.max_buffer_size = Some(max_buffer_size);
+        self
+    }
+
     /// Add an MCP server.
     pub fn add_mcp_server(mut self, name: impl Into<String>, config: McpServerConfig) -> Self {
         match &mut self.mcp_servers {
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use crate::types::hooks::HookEvent;
 use crate::types::permissions::PermissionUpdate;
-use crate::types::messages::{ResultMessage, StreamEvent, SystemMessage};
+use crate::types::messages::{ResultMessage, StreamEvent, SystemMessage, Usage};
 
 /// SDK Control Interrupt Request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,9 +67,17 @@ pub enum SDKControlRequestType {
         tool_name: String,
         input: serde_json::Value,
         #[serde(skip_serializing_if = "Option::is_none")]
+        tool_use_id: Option<String>,
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "deserialize_suggestions"
+        )]
         permission_suggestions: Option<Vec<PermissionUpdate>>,
         #[serde(skip_serializing_if = "Option::is_none")]
         blocked_path: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        agent_id: Option<String>,
     },
     Initialize {
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -93,6 +101,26 @@ pub enum SDKControlRequestType {
     },
 }
 
+/// Parse permission suggestions, dropping any this SDK does not understand
+/// rather than failing the whole permission request.
+fn deserialize_suggestions<'de, D>(deserializer: D) -> Result<Option<Vec<PermissionUpdate>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw: Option<Vec<serde_json::Value>> = Option::deserialize(deserializer)?;
+    Ok(raw.map(|suggestions| {
+        suggestions
+            .into_iter()
+            .filter_map(|suggestion| match serde_json::from_value(suggestion) {
+                Ok(update) => Some(update),
+                Err(e) => {
+                    tracing::warn!("Ignoring unrecognized permission suggestion: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }))
+}
 
 /// SDK Control Response (union of success and error).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,6 +162,12 @@ impl SDKControlResponse {
             },
         }
     }
+    pub fn error(request_id: String, error: String) -> Self {
+        Self {
+            type_: "control_response".to_string(),
+            response: SDKControlResponseType::Error { request_id, error },
+        }
+    }
 }
 
 /// Protocol message types (top-level message envelope).
@@ -152,6 +186,10 @@ pub enum ProtocolMessage {
     ControlResponse {
         response: SDKControlResponseType,
     },
+    /// The CLI no longer needs the answer to an earlier control request
+    ControlCancelRequest {
+        request_id: String,
+    },
     /// User message (tool results, etc.)
     User {
         message: UserMessageInner,
@@ -194,7 +232,7 @@ pub struct AssistantMessageInner {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop_reason: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub usage: Option<serde_json::Value>,
+    pub usage: Option<Usage>,
 }
 
 /// Inner user message content from CLI.
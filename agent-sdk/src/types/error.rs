@@ -37,6 +37,10 @@ pub enum Error {
     #[error("Claude CLI not found: {0}")]
     CLINotFound(String),
 
+    /// The Claude CLI is too old, or its version could not be determined.
+    #[error("Unsupported Claude CLI version: {0}")]
+    CLIVersion(String),
+
     /// CLI connection error.
     #[error("CLI connection error: {0}")]
     CLIConnection(String),
@@ -49,14 +53,78 @@ pub enum Error {
     #[error("Process error: {0}")]
     Process(String),
 
+    /// A message stream fell behind and this many messages were dropped for it.
+    #[error("Event stream lagged: {0} messages dropped")]
+    Lagged(u64),
+
     /// Timeout error.
     #[error("Timeout: {0}")]
     Timeout(String),
 
+    /// An image or document could not be attached to a message.
+    #[error("Invalid attachment: {0}")]
+    InvalidAttachment(String),
+
     /// Unknown error.
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
 
+/// How the Claude Code process ended, as observed by the session.
+///
+/// Recorded when the CLI closes its output or exits on its own; see
+/// `ClaudeClient::process_exit()`.
+#[derive(Debug, Clone)]
+pub struct ProcessExit {
+    /// Exit status, or `None` if it is unknown (custom transports, or the
+    /// process kept running after closing stdout).
+    pub status: Option<std::process::ExitStatus>,
+    /// The last lines the process wrote to stderr.
+    pub stderr_tail: Vec<String>,
+}
+
+impl ProcessExit {
+    /// Whether the process is known to have exited successfully.
+    pub fn success(&self) -> bool {
+        self.status.is_some_and(|status| status.success())
+    }
+
+    /// Convert into the `Error::Process` delivered to pending requests and streams.
+    pub fn to_error(&self) -> Error {
+        Error::Process(self.to_string())
+    }
+}
+
+impl std::fmt::Display for ProcessExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.status {
+            Some(status) => write!(f, "Claude Code exited ({})", status)?,
+            None => write!(f, "Claude Code closed its output stream")?,
+        }
+        if !self.stderr_tail.is_empty() {
+            write!(f, "; stderr:\n{}", self.stderr_tail.join("\n"))?;
+        }
+        Ok(())
+    }
+}
+
 /// Result type alias for the Claude Agent SDK.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_exit_display() {
+        let exit = ProcessExit {
+            status: None,
+            stderr_tail: vec!["Error: invalid API key".to_string()],
+        };
+        assert!(!exit.success());
+        assert_eq!(
+            exit.to_error().to_string(),
+            "Process error: Claude Code closed its output stream; stderr:\nError: invalid API key"
+        );
+    }
+}
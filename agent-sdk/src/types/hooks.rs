@@ -4,6 +4,8 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::types::signal::CancellationSignal;
+
 /// Hook event types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum HookEvent {
@@ -186,8 +188,9 @@ pub enum HookJSONOutput {
 /// Context information for hook callbacks.
 #[derive(Debug, Clone)]
 pub struct HookContext {
-    /// Reserved for future abort signal support. Currently always None.
-    pub signal: Option<()>,
+    /// Cancelled when the answer is no longer needed (CLI cancel, interrupt,
+    /// disconnect).
+    pub signal: Option<CancellationSignal>,
 }
 
 /// Trait for hook callbacks.
@@ -1,7 +1,9 @@
 //! MCP (Model Context Protocol) server configuration types.
 
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
 
 /// MCP server configuration (union of different server types).
@@ -32,13 +34,202 @@ pub enum McpServerConfig {
     /// SDK server configuration.
     Sdk {
         name: String,
-        /// MCP Server instance (skipped during serialization).
-        /// Use Arc<dyn Any> for type erasure since we can't reference the MCP Server trait directly.
+        /// In-process server instance (skipped during serialization).
+        /// The CLI only sees the name and routes `mcp_message` requests back to us.
         #[serde(skip)]
-        instance: Option<Arc<dyn std::any::Any + Send + Sync>>,
+        instance: Option<Arc<SdkMcpServer>>,
     },
 }
 
+impl McpServerConfig {
+    /// Create a configuration for an in-process SDK MCP server.
+    pub fn sdk(server: SdkMcpServer) -> Self {
+        McpServerConfig::Sdk {
+            name: server.name.clone(),
+            instance: Some(Arc::new(server)),
+        }
+    }
+}
+
+/// Content item returned by an SDK MCP tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum McpToolContent {
+    /// Plain text content.
+    Text { text: String },
+    /// Base64-encoded image content.
+    Image {
+        data: String,
+        #[serde(rename = "mimeType")]
+        mime_type: String,
+    },
+}
+
+/// Result of an SDK MCP tool call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpToolResult {
+    /// Content returned to Claude.
+    pub content: Vec<McpToolContent>,
+    /// Whether the tool call failed.
+    #[serde(rename = "isError", default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl McpToolResult {
+    /// Create a successful result with a single text item.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![McpToolContent::Text { text: text.into() }],
+            is_error: false,
+        }
+    }
+
+    /// Create an error result with a single text item.
+    pub fn error(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![McpToolContent::Text { text: text.into() }],
+            is_error: true,
+        }
+    }
+}
+
+/// Handler invoked when Claude calls an SDK MCP tool.
+///
+/// Implemented for async closures taking the tool arguments, so most tools
+/// can be declared inline with [`SdkMcpServer::tool`].
+#[async_trait]
+pub trait SdkMcpToolHandler: Send + Sync {
+    /// Execute the tool with the arguments Claude supplied.
+    async fn call(&self, args: serde_json::Value) -> crate::types::error::Result<McpToolResult>;
+}
+
+#[async_trait]
+impl<F, Fut> SdkMcpToolHandler for F
+where
+    F: Fn(serde_json::Value) -> Fut + Send + Sync,
+    Fut: Future<Output = crate::types::error::Result<McpToolResult>> + Send,
+{
+    async fn call(&self, args: serde_json::Value) -> crate::types::error::Result<McpToolResult> {
+        (self)(args).await
+    }
+}
+
+/// Tool exposed by an SDK MCP server.
+#[derive(Clone)]
+pub struct SdkMcpTool {
+    /// Tool name (Claude sees it as `mcp__<server>__<name>`).
+    pub name: String,
+    /// Description shown to Claude.
+    pub description: String,
+    /// JSON schema of the tool arguments.
+    pub input_schema: serde_json::Value,
+    /// Handler executing the tool.
+    pub handler: Arc<dyn SdkMcpToolHandler>,
+}
+
+impl SdkMcpTool {
+    /// Create a new tool.
+    pub fn new(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: serde_json::Value,
+        handler: impl SdkMcpToolHandler + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            handler: Arc::new(handler),
+        }
+    }
+}
+
+impl std::fmt::Debug for SdkMcpTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SdkMcpTool")
+            .field("name", &self.name)
+            .field("description", &self.description)
+            .field("input_schema", &self.input_schema)
+            .finish_non_exhaustive()
+    }
+}
+
+/// MCP server running inside the SDK process.
+///
+/// Register it through [`McpServerConfig::sdk`]; the session answers the
+/// CLI's `initialize`, `tools/list` and `tools/call` requests for it.
+///
+/// # Example
+///
+/// ```rust
+/// use claude_agent_sdk::types::{ClaudeAgentOptions, McpServerConfig, McpToolResult, SdkMcpServer};
+///
+/// let server = SdkMcpServer::new("tickets").tool(
+///     "lookup",
+///     "Look up a ticket by ID",
+///     serde_json::json!({
+///         "type": "object",
+///         "properties": { "id": { "type": "string" } },
+///         "required": ["id"]
+///     }),
+///     |args: serde_json::Value| async move {
+///         Ok(McpToolResult::text(format!("Ticket {} is open", args["id"])))
+///     },
+/// );
+///
+/// let options = ClaudeAgentOptions::new().add_mcp_server("tickets", McpServerConfig::sdk(server));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SdkMcpServer {
+    /// Server name reported to the CLI.
+    pub name: String,
+    /// Server version reported to the CLI.
+    pub version: String,
+    /// Tools exposed by the server.
+    pub tools: Vec<SdkMcpTool>,
+}
+
+impl SdkMcpServer {
+    /// Create an empty server with version `1.0.0`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: "1.0.0".to_string(),
+            tools: Vec::new(),
+        }
+    }
+
+    /// Set the server version.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Add a tool backed by an async handler.
+    pub fn tool(
+        mut self,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: serde_json::Value,
+        handler: impl SdkMcpToolHandler + 'static,
+    ) -> Self {
+        self.tools
+            .push(SdkMcpTool::new(name, description, input_schema, handler));
+        self
+    }
+
+    /// Add a prebuilt tool.
+    pub fn add_tool(mut self, tool: SdkMcpTool) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Find a tool by name.
+    pub fn get_tool(&self, name: &str) -> Option<&SdkMcpTool> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+}
+
 /// SDK plugin configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SdkPluginConfig {
@@ -80,6 +271,28 @@ mod tests {
         assert_eq!(json["url"], "https://example.com/sse");
     }
 
+    #[test]
+    fn test_mcp_sdk_config_serialization() {
+        let server = SdkMcpServer::new("tickets").tool(
+            "lookup",
+            "Look up a ticket",
+            serde_json::json!({"type": "object"}),
+            |_args: serde_json::Value| async { Ok(McpToolResult::text("open")) },
+        );
+        let config = McpServerConfig::sdk(server);
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json, serde_json::json!({"type": "sdk", "name": "tickets"}));
+    }
+
+    #[test]
+    fn test_mcp_tool_result_serialization() {
+        let json = serde_json::to_value(McpToolResult::text("done")).unwrap();
+        assert_eq!(json, serde_json::json!({"content": [{"type": "text", "text": "done"}]}));
+
+        let json = serde_json::to_value(McpToolResult::error("boom")).unwrap();
+        assert_eq!(json["isError"], true);
+    }
+
     #[test]
     fn test_sdk_plugin_config_serialization() {
         let config = SdkPluginConfig {
@@ -0,0 +1,334 @@
+//! Images and documents attached to input messages.
+//!
+//! Attachments become `image` and `document` content blocks with inline base64
+//! data. The media type is sniffed from the bytes rather than trusted from a
+//! file extension, and sizes are checked up front so an oversized screenshot
+//! fails here instead of as an API error mid-session.
+
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+
+use crate::types::{ContentBlock, Error, InputMessage, MediaSource, MessageContent, Result};
+
+/// Largest image the API accepts, measured as base64 (5MB).
+pub const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+
+/// Largest document the API accepts, measured as base64 (32MB).
+pub const MAX_DOCUMENT_SIZE: usize = 32 * 1024 * 1024;
+
+/// Image media types the API accepts.
+pub const IMAGE_MEDIA_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Media type of `bytes` from their leading magic number.
+///
+/// Recognizes the image types in [`IMAGE_MEDIA_TYPES`] and `application/pdf`.
+pub fn sniff_media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    }
+}
+
+impl ContentBlock {
+    /// A text block.
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text { text: text.into() }
+    }
+
+    /// An image block from a PNG, JPEG, GIF or WebP file.
+    pub fn image_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = read_file(path, MAX_IMAGE_SIZE)?;
+        Self::image_bytes(&bytes).map_err(|e| in_file(path, e))
+    }
+
+    /// An image block from raw bytes; the media type is sniffed.
+    pub fn image_bytes(bytes: &[u8]) -> Result<Self> {
+        let media_type = sniff_media_type(bytes)
+            .filter(|media_type| IMAGE_MEDIA_TYPES.contains(media_type))
+            .ok_or_else(|| {
+                Error::InvalidAttachment("not a PNG, JPEG, GIF or WebP image".to_string())
+            })?;
+        let data = encode(bytes, MAX_IMAGE_SIZE)?;
+        Ok(ContentBlock::Image {
+            source: MediaSource::Base64 {
+                media_type: media_type.to_string(),
+                data,
+            },
+        })
+    }
+
+    /// An image block from base64 `data` of the given media type.
+    ///
+    /// The data must decode to an image of type `media_type`.
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Result<Self> {
+        let media_type = media_type.into();
+        if !IMAGE_MEDIA_TYPES.contains(&media_type.as_str()) {
+            return Err(Error::InvalidAttachment(format!(
+                "unsupported image type `{}`",
+                media_type
+            )));
+        }
+        let data = check_base64(&media_type, data.into(), MAX_IMAGE_SIZE)?;
+        Ok(ContentBlock::Image {
+            source: MediaSource::Base64 { media_type, data },
+        })
+    }
+
+    /// A document block from a PDF or UTF-8 text file.
+    pub fn document_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = read_file(path, MAX_DOCUMENT_SIZE)?;
+        let mut block = Self::document_bytes(&bytes).map_err(|e| in_file(path, e))?;
+        if let ContentBlock::Document { title, .. } = &mut block {
+            *title = path.file_name().map(|name| name.to_string_lossy().into_owned());
+        }
+        Ok(block)
+    }
+
+    /// A document block from raw bytes: a PDF, or UTF-8 text sent as
+    /// `text/plain`.
+    pub fn document_bytes(bytes: &[u8]) -> Result<Self> {
+        let source = match sniff_media_type(bytes) {
+            Some("application/pdf") => MediaSource::Base64 {
+                media_type: "application/pdf".to_string(),
+                data: encode(bytes, MAX_DOCUMENT_SIZE)?,
+            },
+            Some(other) => {
+                return Err(Error::InvalidAttachment(format!(
+                    "{} is not a document type; attach it as an image",
+                    other
+                )))
+            }
+            None => {
+                let text = std::str::from_utf8(bytes).map_err(|_| {
+                    Error::InvalidAttachment("not a PDF or UTF-8 text document".to_string())
+                })?;
+                check_size(text.len(), MAX_DOCUMENT_SIZE)?;
+                MediaSource::Text {
+                    media_type: "text/plain".to_string(),
+                    data: text.to_string(),
+                }
+            }
+        };
+        Ok(ContentBlock::Document {
+            source,
+            title: None,
+            context: None,
+            citations: None,
+        })
+    }
+
+    /// A PDF document block from base64 `data`, which must decode to a PDF.
+    pub fn document_base64(data: impl Into<String>) -> Result<Self> {
+        let media_type = "application/pdf".to_string();
+        let data = check_base64(&media_type, data.into(), MAX_DOCUMENT_SIZE)?;
+        Ok(ContentBlock::Document {
+            source: MediaSource::Base64 { media_type, data },
+            title: None,
+            context: None,
+            citations: None,
+        })
+    }
+}
+
+impl InputMessage {
+    /// Append a content block, turning string content into a text block first.
+    pub fn with_block(mut self, block: ContentBlock) -> Self {
+        let blocks = match std::mem::replace(&mut self.message.content, MessageContent::Blocks(Vec::new())) {
+            MessageContent::String(text) if text.is_empty() => vec![block],
+            MessageContent::String(text) => vec![ContentBlock::text(text), block],
+            MessageContent::Blocks(mut blocks) => {
+                blocks.push(block);
+                blocks
+            }
+        };
+        self.message.content = MessageContent::Blocks(blocks);
+        self
+    }
+
+    /// Attach an image file; see [`ContentBlock::image_file`].
+    pub fn with_image(self, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(self.with_block(ContentBlock::image_file(path)?))
+    }
+
+    /// Attach a document file; see [`ContentBlock::document_file`].
+    pub fn with_document(self, path: impl AsRef<Path>) -> Result<Self> {
+        Ok(self.with_block(ContentBlock::document_file(path)?))
+    }
+}
+
+/// Read `path`, refusing files that would exceed `limit` once encoded.
+fn read_file(path: &Path, limit: usize) -> Result<Vec<u8>> {
+    let io_error = |e: std::io::Error| {
+        Error::Io(std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+    };
+    let len = std::fs::metadata(path).map_err(io_error)?.len();
+    check_size(encoded_len(usize::try_from(len).unwrap_or(usize::MAX)), limit)
+        .map_err(|e| in_file(path, e))?;
+    std::fs::read(path).map_err(io_error)
+}
+
+fn encode(bytes: &[u8], limit: usize) -> Result<String> {
+    check_size(encoded_len(bytes.len()), limit)?;
+    Ok(STANDARD.encode(bytes))
+}
+
+fn check_base64(media_type: &str, data: String, limit: usize) -> Result<String> {
+    check_size(data.len(), limit)?;
+    let bytes = STANDARD
+        .decode(&data)
+        .map_err(|e| Error::InvalidAttachment(format!("invalid base64 data: {}", e)))?;
+    match sniff_media_type(&bytes) {
+        Some(sniffed) if sniffed == media_type => Ok(data),
+        Some(sniffed) => Err(Error::InvalidAttachment(format!(
+            "data is {} but was declared as {}",
+            sniffed, media_type
+        ))),
+        None => Err(Error::InvalidAttachment(format!("data is not {}", media_type))),
+    }
+}
+
+fn check_size(size: usize, limit: usize) -> Result<()> {
+    if size > limit {
+        return Err(Error::InvalidAttachment(format!(
+            "{} bytes exceeds the {} byte limit",
+            size, limit
+        )));
+    }
+    Ok(())
+}
+
+fn encoded_len(len: usize) -> usize {
+    len.div_ceil(3).saturating_mul(4)
+}
+
+fn in_file(path: &Path, error: Error) -> Error {
+    match error {
+        Error::InvalidAttachment(message) => {
+            Error::InvalidAttachment(format!("{}: {}", path.display(), message))
+        }
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_sniff_media_type() {
+        assert_eq!(sniff_media_type(PNG), Some("image/png"));
+        assert_eq!(sniff_media_type(b"\xff\xd8\xff\xe0\0\x10JFIF"), Some("image/jpeg"));
+        assert_eq!(sniff_media_type(b"GIF89a\x01\0"), Some("image/gif"));
+        assert_eq!(sniff_media_type(b"RIFF\x24\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_media_type(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff_media_type(b"hello"), None);
+    }
+
+    #[test]
+    fn test_image_bytes_block_wire_format() {
+        let block = ContentBlock::image_bytes(PNG).unwrap();
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({
+                "type": "image",
+                "source": {"type": "base64", "media_type": "image/png", "data": STANDARD.encode(PNG)}
+            })
+        );
+        assert!(matches!(
+            ContentBlock::image_bytes(b"%PDF-1.7"),
+            Err(Error::InvalidAttachment(_))
+        ));
+    }
+
+    #[test]
+    fn test_image_base64_checks_data() {
+        let data = STANDARD.encode(PNG);
+        assert!(ContentBlock::image_base64("image/png", data.clone()).is_ok());
+
+        let err = ContentBlock::image_base64("image/jpeg", data).unwrap_err();
+        assert!(err.to_string().contains("image/png but was declared as image/jpeg"));
+        assert!(ContentBlock::image_base64("image/png", "not base64!").is_err());
+        assert!(ContentBlock::image_base64("image/tiff", STANDARD.encode(PNG)).is_err());
+        let err = ContentBlock::image_base64("image/png", STANDARD.encode("plain text")).unwrap_err();
+        assert!(err.to_string().contains("data is not image/png"));
+
+        let oversized = "A".repeat(MAX_IMAGE_SIZE + 4);
+        let err = ContentBlock::image_base64("image/png", oversized).unwrap_err();
+        assert!(err.to_string().contains("exceeds the 5242880 byte limit"));
+    }
+
+    #[test]
+    fn test_document_base64_checks_data() {
+        assert!(ContentBlock::document_base64(STANDARD.encode(b"%PDF-1.7\n")).is_ok());
+        assert!(ContentBlock::document_base64(STANDARD.encode(PNG)).is_err());
+        assert!(ContentBlock::document_base64(STANDARD.encode("not a pdf")).is_err());
+    }
+
+    #[test]
+    fn test_document_bytes() {
+        let block = ContentBlock::document_bytes(b"%PDF-1.7\n").unwrap();
+        assert!(matches!(
+            block,
+            ContentBlock::Document { source: MediaSource::Base64 { ref media_type, .. }, .. }
+                if media_type == "application/pdf"
+        ));
+
+        let block = ContentBlock::document_bytes("Release notes\n".as_bytes()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&block).unwrap(),
+            serde_json::json!({
+                "type": "document",
+                "source": {"type": "text", "media_type": "text/plain", "data": "Release notes\n"}
+            })
+        );
+
+        assert!(ContentBlock::document_bytes(PNG).is_err());
+        assert!(ContentBlock::document_bytes(b"\xff\xfe\x00").is_err());
+    }
+
+    #[test]
+    fn test_files_and_input_message_builders() {
+        let dir = std::env::temp_dir().join(format!("claude-sdk-media-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let screenshot = dir.join("failing-ui.png");
+        std::fs::write(&screenshot, PNG).unwrap();
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, "steps to reproduce").unwrap();
+
+        let message = InputMessage::user("Why is this button cut off?", "default".to_string())
+            .with_image(&screenshot)
+            .unwrap()
+            .with_document(&notes)
+            .unwrap();
+        let json = serde_json::to_value(&message).unwrap();
+        let content = &json["message"]["content"];
+        assert_eq!(content[0], serde_json::json!({"type": "text", "text": "Why is this button cut off?"}));
+        assert_eq!(content[1]["type"], "image");
+        assert_eq!(content[1]["source"]["media_type"], "image/png");
+        assert_eq!(content[2]["type"], "document");
+        assert_eq!(content[2]["title"], "notes.txt");
+
+        let err = ContentBlock::image_file(&notes).unwrap_err();
+        assert!(err.to_string().contains("notes.txt: not a PNG"));
+        let err = ContentBlock::image_file(dir.join("missing.png")).unwrap_err();
+        assert!(matches!(err, Error::Io(_)));
+        assert!(err.to_string().contains("missing.png"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,10 @@
 //! Message types for Claude Agent SDK.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::types::hooks::CompactTrigger;
 
 /// Assistant message error types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -15,6 +19,9 @@ pub enum AssistantMessageError {
 }
 
 /// Content block types.
+///
+/// Blocks of a type this SDK does not model, and known blocks whose fields do
+/// not parse, are kept as `Unknown` so the rest of the message still arrives.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentBlock {
@@ -25,6 +32,10 @@ pub enum ContentBlock {
         thinking: String,
         signature: String,
     },
+    /// Thinking the API returned encrypted; pass it back unchanged.
+    RedactedThinking {
+        data: String,
+    },
     ToolUse {
         id: String,
         name: String,
@@ -37,6 +48,77 @@ pub enum ContentBlock {
         #[serde(skip_serializing_if = "Option::is_none")]
         is_error: Option<bool>,
     },
+    Image {
+        source: MediaSource,
+    },
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        citations: Option<serde_json::Value>,
+    },
+    /// A tool run by the API itself, such as web search.
+    ServerToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    /// Results of a `web_search` server tool call: an array of results, or a
+    /// `web_search_tool_result_error` object.
+    WebSearchToolResult {
+        tool_use_id: String,
+        content: serde_json::Value,
+    },
+    /// Any other block, as received.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+impl ContentBlock {
+    /// The block's `type` tag.
+    pub fn block_type(&self) -> &str {
+        match self {
+            ContentBlock::Text { .. } => "text",
+            ContentBlock::Thinking { .. } => "thinking",
+            ContentBlock::RedactedThinking { .. } => "redacted_thinking",
+            ContentBlock::ToolUse { .. } => "tool_use",
+            ContentBlock::ToolResult { .. } => "tool_result",
+            ContentBlock::Image { .. } => "image",
+            ContentBlock::Document { .. } => "document",
+            ContentBlock::ServerToolUse { .. } => "server_tool_use",
+            ContentBlock::WebSearchToolResult { .. } => "web_search_tool_result",
+            ContentBlock::Unknown(value) => value["type"].as_str().unwrap_or("unknown"),
+        }
+    }
+}
+
+/// Where the data of an image or document block comes from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    /// Inline base64 data, e.g. `image/png` or `application/pdf`.
+    Base64 {
+        media_type: String,
+        data: String,
+    },
+    /// Inline plain text (documents only).
+    Text {
+        media_type: String,
+        data: String,
+    },
+    Url {
+        url: String,
+    },
+    /// A file uploaded through the Files API.
+    File {
+        file_id: String,
+    },
+    /// Any other source, as received.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
 }
 
 /// Content for tool result blocks (can be string or array).
@@ -63,6 +145,10 @@ pub struct UserMessage {
     pub uuid: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_tool_use_id: Option<String>,
+    /// The CLI's structured result of the tool this message answers; its
+    /// shape depends on the tool. See [`crate::types::tools`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_result: Option<serde_json::Value>,
 }
 
 /// Assistant message with content blocks.
@@ -88,10 +174,327 @@ pub struct SystemMessage {
     pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+impl SystemMessage {
+    /// Decode the message by subtype.
+    pub fn event(&self) -> SystemEvent {
+        SystemEvent::from(self.clone())
+    }
+}
+
+/// A system message decoded by subtype.
+///
+/// Subtypes this SDK does not model, and known subtypes whose fields do not
+/// parse, are kept as `Other`.
+#[derive(Debug, Clone)]
+pub enum SystemEvent {
+    /// The session started (`init`).
+    Init(Box<SystemInit>),
+    /// The conversation was compacted (`compact_boundary`).
+    CompactBoundary(CompactBoundary),
+    /// The CLI's status changed (`status`).
+    Status(SystemStatus),
+    /// Any other system message.
+    Other(SystemMessage),
+}
+
+impl From<SystemMessage> for SystemEvent {
+    fn from(message: SystemMessage) -> Self {
+        fn decode<T: serde::de::DeserializeOwned>(message: &SystemMessage) -> Option<T> {
+            serde_json::from_value(serde_json::Value::Object(message.extra.clone()))
+                .map_err(|e| tracing::warn!("Could not decode system {} message: {}", message.subtype, e))
+                .ok()
+        }
+        let event = match message.subtype.as_str() {
+            "init" => decode(&message).map(|init| SystemEvent::Init(Box::new(init))),
+            "compact_boundary" => decode(&message).map(SystemEvent::CompactBoundary),
+            "status" => decode(&message).map(SystemEvent::Status),
+            _ => None,
+        };
+        event.unwrap_or(SystemEvent::Other(message))
+    }
+}
+
+/// Session details announced by the `init` system message.
+///
+/// Decoding is tolerant: a field with an unexpected shape falls back to its
+/// default and malformed list entries are skipped, so a newer CLI's `init`
+/// still decodes. The raw fields remain in [`SystemMessage::extra`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemInit {
+    #[serde(default, deserialize_with = "lenient")]
+    pub session_id: String,
+    #[serde(default, deserialize_with = "lenient", skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    pub cwd: String,
+    #[serde(default, deserialize_with = "lenient")]
+    pub model: String,
+    /// Permission mode in its wire form, e.g. `"acceptEdits"`.
+    #[serde(rename = "permissionMode", default, deserialize_with = "lenient")]
+    pub permission_mode: String,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub tools: Vec<String>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub mcp_servers: Vec<McpServerStatus>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub slash_commands: Vec<String>,
+    #[serde(rename = "apiKeySource", default, deserialize_with = "lenient")]
+    pub api_key_source: String,
+    #[serde(default, deserialize_with = "lenient", skip_serializing_if = "Option::is_none")]
+    pub claude_code_version: Option<String>,
+    #[serde(default, deserialize_with = "lenient", skip_serializing_if = "Option::is_none")]
+    pub output_style: Option<String>,
+    /// Agent names; agents listed as objects contribute their `name`.
+    #[serde(default, deserialize_with = "agent_names")]
+    pub agents: Vec<String>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub skills: Vec<String>,
+    #[serde(default, deserialize_with = "lenient_list")]
+    pub plugins: Vec<PluginInfo>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// An MCP server and its connection status, as listed in `init`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpServerStatus {
+    pub name: String,
+    /// `"connected"`, `"failed"`, `"pending"`, ...
+    pub status: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A plugin loaded by the CLI, as listed in `init`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginInfo {
+    pub name: String,
+    pub path: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Decode a field, falling back to its default when it has an unexpected shape.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: serde::de::DeserializeOwned + Default,
+{
+    let raw = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(raw).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring malformed init field: {}", e);
+        T::default()
+    }))
+}
+
+/// Decode a list, skipping entries with an unexpected shape.
+fn lenient_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let raw: Vec<serde_json::Value> = lenient(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .filter_map(|item| match serde_json::from_value(item) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                tracing::warn!("Ignoring malformed init list entry: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+fn agent_names<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw: Vec<serde_json::Value> = lenient(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .filter_map(|agent| match agent {
+            serde_json::Value::String(name) => Some(name),
+            serde_json::Value::Object(mut agent) => match agent.remove("name") {
+                Some(serde_json::Value::String(name)) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect())
+}
+
+/// Marks where the conversation was compacted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactBoundary {
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    pub compact_metadata: CompactMetadata,
+}
+
+/// What triggered a compaction and how large the conversation was before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactMetadata {
+    pub trigger: CompactTrigger,
+    /// Tokens in the conversation before compaction.
+    pub pre_tokens: u64,
+}
+
+/// A change in what the CLI is doing, e.g. `status: "compacting"`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemStatus {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
+    /// The new status, or `None` when the previous one ended.
+    #[serde(default)]
+    pub status: Option<String>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// How a turn ended, from the `subtype` of a result message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ResultSubtype {
+    /// The turn completed.
+    Success,
+    /// `max_turns` was reached.
+    ErrorMaxTurns,
+    /// The turn failed while running.
+    ErrorDuringExecution,
+    /// `max_budget_usd` was exceeded.
+    ErrorMaxBudgetUsd,
+    /// A subtype this SDK does not know yet.
+    Unknown(String),
+}
+
+impl ResultSubtype {
+    /// The subtype as it appears on the wire.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ResultSubtype::Success => "success",
+            ResultSubtype::ErrorMaxTurns => "error_max_turns",
+            ResultSubtype::ErrorDuringExecution => "error_during_execution",
+            ResultSubtype::ErrorMaxBudgetUsd => "error_max_budget_usd",
+            ResultSubtype::Unknown(subtype) => subtype,
+        }
+    }
+
+    /// Whether the subtype reports a failure.
+    pub fn is_error(&self) -> bool {
+        self.as_str().starts_with("error")
+    }
+}
+
+impl From<String> for ResultSubtype {
+    fn from(subtype: String) -> Self {
+        match subtype.as_str() {
+            "success" => ResultSubtype::Success,
+            "error_max_turns" => ResultSubtype::ErrorMaxTurns,
+            "error_during_execution" => ResultSubtype::ErrorDuringExecution,
+            "error_max_budget_usd" => ResultSubtype::ErrorMaxBudgetUsd,
+            _ => ResultSubtype::Unknown(subtype),
+        }
+    }
+}
+
+impl From<ResultSubtype> for String {
+    fn from(subtype: ResultSubtype) -> Self {
+        match subtype {
+            ResultSubtype::Unknown(subtype) => subtype,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ResultSubtype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Token usage of a message or a whole turn.
+///
+/// Counts the API leaves out or sends as `null` read as zero. Fields this SDK
+/// does not model are kept in `extra`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub input_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub output_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub cache_read_input_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_tool_use: Option<ServerToolUse>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Usage {
+    /// Input tokens including those written to and read from the cache.
+    pub fn total_input_tokens(&self) -> u64 {
+        self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens
+    }
+
+    /// All input and output tokens.
+    pub fn total_tokens(&self) -> u64 {
+        self.total_input_tokens() + self.output_tokens
+    }
+}
+
+/// Server-side tool calls counted in [`Usage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerToolUse {
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub web_search_requests: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub web_fetch_requests: u64,
+}
+
+/// Usage and cost of one model over a turn, from a result's `modelUsage`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub input_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub output_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub cache_read_input_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub cache_creation_input_tokens: u64,
+    #[serde(default, deserialize_with = "default_if_null")]
+    pub web_search_requests: u64,
+    #[serde(rename = "costUSD", default, deserialize_with = "default_if_null")]
+    pub cost_usd: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u64>,
+}
+
+fn default_if_null<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
 /// Result message with cost and usage information.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResultMessage {
-    pub subtype: String,
+    pub subtype: ResultSubtype,
     pub duration_ms: i64,
     pub duration_api_ms: i64,
     pub is_error: bool,
@@ -100,7 +503,15 @@ pub struct ResultMessage {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_cost_usd: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub usage: Option<serde_json::Value>,
+    pub usage: Option<Usage>,
+    /// Usage and cost per model, keyed by model ID.
+    #[serde(
+        rename = "modelUsage",
+        default,
+        deserialize_with = "default_if_null",
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    pub model_usage: HashMap<String, ModelUsage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -212,10 +623,233 @@ mod tests {
         assert_eq!(json, "Hello");
     }
 
+    #[test]
+    fn test_content_block_media_and_server_tools() {
+        let blocks: Vec<ContentBlock> = serde_json::from_value(serde_json::json!([
+            {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBOR"}},
+            {"type": "document", "source": {"type": "url", "url": "https://example.com/a.pdf"}, "title": "A"},
+            {"type": "redacted_thinking", "data": "EmwKAhgB"},
+            {"type": "server_tool_use", "id": "srvtoolu_1", "name": "web_search", "input": {"query": "rust"}},
+            {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_1", "content": [
+                {"type": "web_search_result", "url": "https://www.rust-lang.org", "title": "Rust"}
+            ]}
+        ]))
+        .unwrap();
+
+        assert!(matches!(
+            &blocks[0],
+            ContentBlock::Image { source: MediaSource::Base64 { media_type, .. } } if media_type == "image/png"
+        ));
+        assert!(matches!(
+            &blocks[1],
+            ContentBlock::Document { source: MediaSource::Url { .. }, title: Some(title), .. } if title == "A"
+        ));
+        assert!(matches!(&blocks[2], ContentBlock::RedactedThinking { data } if data == "EmwKAhgB"));
+        assert!(matches!(&blocks[3], ContentBlock::ServerToolUse { name, .. } if name == "web_search"));
+        assert!(matches!(
+            &blocks[4],
+            ContentBlock::WebSearchToolResult { tool_use_id, content } if tool_use_id == "srvtoolu_1" && content.is_array()
+        ));
+        assert_eq!(blocks[4].block_type(), "web_search_tool_result");
+    }
+
+    #[test]
+    fn test_content_block_unknown_round_trips() {
+        let raw = serde_json::json!({"type": "container_upload", "file_id": "file_1"});
+        let block: ContentBlock = serde_json::from_value(raw.clone()).unwrap();
+        assert!(matches!(block, ContentBlock::Unknown(_)));
+        assert_eq!(block.block_type(), "container_upload");
+        assert_eq!(serde_json::to_value(&block).unwrap(), raw);
+
+        // A known type with an unexpected shape is kept rather than rejected
+        let block: ContentBlock = serde_json::from_value(serde_json::json!({"type": "text"})).unwrap();
+        assert!(matches!(block, ContentBlock::Unknown(_)));
+
+        let source: MediaSource =
+            serde_json::from_value(serde_json::json!({"type": "content", "content": []})).unwrap();
+        assert!(matches!(source, MediaSource::Unknown(_)));
+    }
+
+    #[test]
+    fn test_assistant_message_keeps_unrecognized_blocks() {
+        let msg: crate::types::ProtocolMessage = serde_json::from_value(serde_json::json!({
+            "type": "assistant",
+            "message": {
+                "model": "claude-sonnet-4",
+                "content": [
+                    {"type": "text", "text": "Here it is"},
+                    {"type": "code_execution_tool_result", "tool_use_id": "srvtoolu_2", "content": {}}
+                ]
+            },
+            "session_id": "s1"
+        }))
+        .unwrap();
+        let crate::types::ProtocolMessage::Assistant { message, .. } = msg else {
+            panic!("expected an assistant message");
+        };
+        assert_eq!(message.content.len(), 2);
+        assert!(matches!(message.content[1], ContentBlock::Unknown(_)));
+    }
+
     #[test]
     fn test_assistant_message_error() {
         let error = AssistantMessageError::RateLimit;
         let json = serde_json::to_string(&error).unwrap();
         assert_eq!(json, r#""rate_limit""#);
     }
+
+    #[test]
+    fn test_result_subtype_round_trip() {
+        let subtype: ResultSubtype = serde_json::from_str(r#""error_max_budget_usd""#).unwrap();
+        assert_eq!(subtype, ResultSubtype::ErrorMaxBudgetUsd);
+        assert!(subtype.is_error());
+
+        let subtype: ResultSubtype = serde_json::from_str(r#""error_something_new""#).unwrap();
+        assert_eq!(subtype, ResultSubtype::Unknown("error_something_new".to_string()));
+        assert_eq!(serde_json::to_string(&subtype).unwrap(), r#""error_something_new""#);
+        assert_eq!(serde_json::to_string(&ResultSubtype::Success).unwrap(), r#""success""#);
+    }
+
+    #[test]
+    fn test_result_usage_parsing() {
+        let result: ResultMessage = serde_json::from_value(serde_json::json!({
+            "subtype": "success",
+            "duration_ms": 1200,
+            "duration_api_ms": 1000,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "s",
+            "usage": {
+                "input_tokens": 10,
+                "output_tokens": 5,
+                "cache_creation_input_tokens": null,
+                "cache_read_input_tokens": 100,
+                "server_tool_use": {"web_search_requests": 2},
+                "service_tier": "standard"
+            },
+            "modelUsage": {
+                "claude-sonnet-4-5": {
+                    "inputTokens": 10,
+                    "outputTokens": 5,
+                    "cacheReadInputTokens": 100,
+                    "costUSD": 0.0012,
+                    "contextWindow": 200000
+                }
+            }
+        }))
+        .unwrap();
+
+        let usage = result.usage.unwrap();
+        assert_eq!(usage.cache_creation_input_tokens, 0);
+        assert_eq!(usage.total_tokens(), 115);
+        assert_eq!(usage.server_tool_use.unwrap().web_search_requests, 2);
+        assert_eq!(usage.extra["service_tier"], "standard");
+
+        let model = &result.model_usage["claude-sonnet-4-5"];
+        assert_eq!(model.cache_read_input_tokens, 100);
+        assert_eq!(model.cost_usd, 0.0012);
+        assert_eq!(model.context_window, Some(200000));
+    }
+
+    #[test]
+    fn test_result_null_model_usage() {
+        let result: ResultMessage = serde_json::from_str(
+            r#"{"subtype":"success","duration_ms":1,"duration_api_ms":1,"is_error":false,"num_turns":1,"session_id":"s","modelUsage":null}"#,
+        )
+        .unwrap();
+        assert!(result.model_usage.is_empty());
+
+        let result: ResultMessage = serde_json::from_str(
+            r#"{"subtype":"success","duration_ms":1,"duration_api_ms":1,"is_error":false,"num_turns":1,"session_id":"s","modelUsage":{"m":{"inputTokens":3,"costUSD":null}}}"#,
+        )
+        .unwrap();
+        assert_eq!(result.model_usage["m"].input_tokens, 3);
+        assert_eq!(result.model_usage["m"].cost_usd, 0.0);
+    }
+
+    fn system(value: serde_json::Value) -> SystemMessage {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_system_event_decoding() {
+        let init = system(serde_json::json!({
+            "subtype": "init",
+            "session_id": "s",
+            "model": "claude-sonnet-4-5",
+            "permissionMode": "acceptEdits",
+            "tools": ["Bash"],
+            "mcp_servers": [{"name": "github", "status": "connected"}],
+            "plugins": [{"name": "p", "path": "/plugins/p"}],
+            "claude_code_version": "2.0.14",
+            "betas": ["b"]
+        }));
+        match init.event() {
+            SystemEvent::Init(init) => {
+                assert_eq!(init.permission_mode, "acceptEdits");
+                assert_eq!(init.mcp_servers[0].status, "connected");
+                assert_eq!(init.plugins[0].path, "/plugins/p");
+                assert_eq!(init.claude_code_version.as_deref(), Some("2.0.14"));
+                assert_eq!(init.extra["betas"], serde_json::json!(["b"]));
+            }
+            other => panic!("expected init, got {:?}", other),
+        }
+
+        let boundary = system(serde_json::json!({
+            "subtype": "compact_boundary",
+            "session_id": "s",
+            "compact_metadata": {"trigger": "auto", "pre_tokens": 150000}
+        }));
+        match boundary.event() {
+            SystemEvent::CompactBoundary(boundary) => {
+                assert_eq!(boundary.compact_metadata.trigger, CompactTrigger::Auto);
+                assert_eq!(boundary.compact_metadata.pre_tokens, 150000);
+            }
+            other => panic!("expected compact boundary, got {:?}", other),
+        }
+
+        let status = system(serde_json::json!({"subtype": "status", "status": "compacting"}));
+        assert!(matches!(
+            status.event(),
+            SystemEvent::Status(SystemStatus { status: Some(s), .. }) if s == "compacting"
+        ));
+
+        let hook = system(serde_json::json!({"subtype": "hook_response", "hook_name": "x"}));
+        assert!(matches!(hook.event(), SystemEvent::Other(m) if m.subtype == "hook_response"));
+        let malformed = system(serde_json::json!({"subtype": "compact_boundary"}));
+        assert!(matches!(malformed.event(), SystemEvent::Other(_)));
+    }
+
+    #[test]
+    fn test_init_tolerates_schema_drift() {
+        let init = system(serde_json::json!({
+            "subtype": "init",
+            "session_id": "s",
+            "model": null,
+            "tools": ["Bash", 3, "Read"],
+            "mcp_servers": [
+                {"name": "github", "status": "connected", "serverInfo": {"version": "1"}},
+                {"name": "slack"},
+                "broken"
+            ],
+            "plugins": [{"name": "p", "path": "/plugins/p", "source": "market"}, {"path": 1}],
+            "agents": ["reviewer", {"name": "planner", "model": "opus"}, {"id": 1}],
+            "slash_commands": {"compact": {}},
+            "skills": null
+        }));
+        let SystemEvent::Init(init) = init.event() else {
+            panic!("expected init");
+        };
+        assert_eq!(init.session_id, "s");
+        assert_eq!(init.model, "");
+        assert_eq!(init.tools, vec!["Bash", "Read"]);
+        assert_eq!(init.mcp_servers.len(), 2);
+        assert_eq!(init.mcp_servers[0].extra["serverInfo"]["version"], "1");
+        assert_eq!(init.mcp_servers[1].status, "");
+        assert_eq!(init.plugins.len(), 1);
+        assert_eq!(init.plugins[0].extra["source"], "market");
+        assert_eq!(init.agents, vec!["reviewer", "planner"]);
+        assert!(init.slash_commands.is_empty());
+        assert!(init.skills.is_empty());
+    }
 }
@@ -5,14 +5,18 @@ pub mod control;
 pub mod error;
 pub mod hooks;
 pub mod mcp;
+pub mod media;
 pub mod messages;
 pub mod permissions;
 pub mod sandbox;
+pub mod signal;
+pub mod stream;
+pub mod tools;
 
 // Re-export commonly used types
 pub use agent::{
     AgentDefinition, AgentModel, ClaudeAgentOptions, McpServersConfig, SdkBeta, SettingSource,
-    SystemPromptConfig, ToolsConfig,
+    StderrCallback, SystemPromptConfig, ToolsConfig,
 };
 pub use control::{
     AssistantMessageInner, ProtocolMessage,  SDKControlInterruptRequest,
@@ -21,17 +25,22 @@ pub use control::{
     SDKControlRewindFilesRequest, SDKControlSetPermissionModeRequest, SDKHookCallbackRequest,
     UserMessageInner,
 };
-pub use error::{Error, Result};
+pub use error::{Error, ProcessExit, Result};
 pub use hooks::{
     AsyncHookJSONOutput, BaseHookInput, CompactTrigger, HookCallback, HookConfig, HookContext,
     HookEvent, HookInput, HookJSONOutput, HookMatcher, HookSpecificOutput, PermissionDecision,
     PostToolUseHookSpecificOutput, PreToolUseHookSpecificOutput, SessionStartHookSpecificOutput,
     SyncHookJSONOutput, UserPromptSubmitHookSpecificOutput,
 };
-pub use mcp::{McpServerConfig, SdkPluginConfig};
+pub use mcp::{
+    McpServerConfig, McpToolContent, McpToolResult, SdkMcpServer, SdkMcpTool, SdkMcpToolHandler,
+    SdkPluginConfig,
+};
 pub use messages::{
     AssistantMessage, AssistantMessageError, ContentBlock, ContentBlockContent, InputMessage,
-    Message, MessageContent, ResultMessage, StreamEvent, SystemMessage, UserMessage,
+    CompactBoundary, CompactMetadata, McpServerStatus, MediaSource, Message, MessageContent, ModelUsage,
+    PluginInfo, ResultMessage, ResultSubtype, ServerToolUse, StreamEvent, SystemEvent, SystemInit,
+    SystemMessage, SystemStatus, Usage, UserMessage,
 };
 pub use permissions::{
     CanUseTool, PermissionBehavior, PermissionMode, PermissionResult, PermissionResultAllow,
@@ -39,3 +48,8 @@ pub use permissions::{
     ToolPermissionContext,
 };
 pub use sandbox::{SandboxIgnoreViolations, SandboxNetworkConfig, SandboxSettings};
+pub use signal::CancellationSignal;
+pub use stream::{
+    ContentBlockDelta, MessageDelta, MessageStreamEvent, PartialMessageAccumulator, PartialUpdate,
+};
+pub use tools::{BuiltinToolInput, BuiltinToolResult};
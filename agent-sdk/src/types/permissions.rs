@@ -3,6 +3,8 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+use crate::types::signal::CancellationSignal;
+
 /// Permission modes for the SDK.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -45,6 +47,8 @@ pub enum PermissionUpdateDestination {
     LocalSettings,
     /// Session settings.
     Session,
+    /// Rules given on the CLI command line.
+    CliArg,
 }
 
 /// Permission rule value.
@@ -110,12 +114,23 @@ pub enum PermissionUpdate {
 }
 
 /// Context information for tool permission callbacks.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ToolPermissionContext {
-    /// Future: abort signal support (currently always None).
-    pub signal: Option<()>,
+    /// Cancelled when the answer is no longer needed (CLI cancel, interrupt,
+    /// disconnect).
+    pub signal: Option<CancellationSignal>,
     /// Permission suggestions from CLI.
+    ///
+    /// Returning one of these in `PermissionResultAllow::updated_permissions`
+    /// applies it, e.g. to allow a tool for a whole directory.
     pub suggestions: Vec<PermissionUpdate>,
+    /// The tool use awaiting permission.
+    pub tool_use_id: Option<String>,
+    /// The path that triggered the request, when it is outside the allowed
+    /// directories.
+    pub blocked_path: Option<String>,
+    /// The subagent asking, or `None` for the main agent.
+    pub agent_id: Option<String>,
 }
 
 /// Permission result for allowing an action.
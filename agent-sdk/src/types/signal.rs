@@ -0,0 +1,76 @@
+//! Cancellation signal passed to permission and hook callbacks.
+
+use tokio_util::sync::CancellationToken;
+
+/// Tells a callback that its answer is no longer needed.
+///
+/// Set when the CLI cancels the request, the turn is interrupted, or the
+/// client disconnects. It also names the tool use and session the request
+/// belongs to, so a UI can find and dismiss the matching prompt. Callbacks
+/// that wait on something slow (a human, a network call) can select on
+/// [`cancelled`](Self::cancelled) to stop early; the session stops waiting
+/// for them either way.
+///
+/// # Example
+///
+/// ```rust
+/// use claude_agent_sdk::types::CancellationSignal;
+///
+/// # async fn example() {
+/// let signal = CancellationSignal::new();
+/// let watcher = signal.clone();
+/// signal.cancel();
+/// watcher.cancelled().await;
+/// assert!(watcher.is_cancelled());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationSignal {
+    token: CancellationToken,
+    tool_use_id: Option<String>,
+    session_id: Option<String>,
+}
+
+impl CancellationSignal {
+    /// Create a signal that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn for_request(
+        token: CancellationToken,
+        tool_use_id: Option<String>,
+        session_id: Option<String>,
+    ) -> Self {
+        Self {
+            token,
+            tool_use_id,
+            session_id,
+        }
+    }
+
+    /// The tool use the cancelled request was about, if any.
+    pub fn tool_use_id(&self) -> Option<&str> {
+        self.tool_use_id.as_deref()
+    }
+
+    /// The session the request belongs to, once the CLI has reported it.
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Cancel the signal and every clone of it.
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Whether the signal has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Wait until the signal is cancelled.
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await;
+    }
+}
@@ -0,0 +1,597 @@
+//! Typed `stream_event` payloads and a partial-message accumulator.
+//!
+//! With `include_partial_messages` the CLI forwards the API's raw message
+//! stream events as [`StreamEvent`]s. [`StreamEvent::decode`] parses one into a
+//! [`MessageStreamEvent`], and [`PartialMessageAccumulator`] folds them into
+//! the [`AssistantMessage`] being streamed, reporting each change as a
+//! [`PartialUpdate`].
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::types::{AssistantMessage, AssistantMessageInner, ContentBlock, StreamEvent, Usage};
+
+/// An event of the API's message stream.
+///
+/// Event types this SDK does not model are kept as `Unknown`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MessageStreamEvent {
+    /// A message began. Its content is usually empty.
+    MessageStart { message: AssistantMessageInner },
+    /// A content block began at `index`.
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    /// More content for the block at `index`.
+    ContentBlockDelta {
+        index: usize,
+        delta: ContentBlockDelta,
+    },
+    /// The block at `index` is complete.
+    ContentBlockStop { index: usize },
+    /// Message-level changes: the stop reason and cumulative usage.
+    MessageDelta {
+        delta: MessageDelta,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        usage: Option<Usage>,
+    },
+    /// The message is complete.
+    MessageStop,
+    Ping,
+    Error { error: serde_json::Value },
+    /// Any other event, as received.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+/// The change carried by a `content_block_delta` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlockDelta {
+    TextDelta { text: String },
+    ThinkingDelta { thinking: String },
+    SignatureDelta { signature: String },
+    /// A fragment of a tool use block's input; fragments only form valid JSON
+    /// once the block stops.
+    InputJsonDelta { partial_json: String },
+    CitationsDelta { citation: serde_json::Value },
+    /// Any other delta, as received.
+    #[serde(untagged)]
+    Unknown(serde_json::Value),
+}
+
+/// Message-level fields changed by a `message_delta` event.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MessageDelta {
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+    #[serde(default)]
+    pub stop_sequence: Option<String>,
+}
+
+impl StreamEvent {
+    /// Parse the raw event.
+    pub fn decode(&self) -> MessageStreamEvent {
+        serde_json::from_value(self.event.clone())
+            .unwrap_or_else(|_| MessageStreamEvent::Unknown(self.event.clone()))
+    }
+}
+
+/// A change to the message being streamed, as reported by
+/// [`PartialMessageAccumulator::push`].
+#[derive(Debug, Clone)]
+pub enum PartialUpdate {
+    /// Text was added to the block at `index`; `text` is the block so far.
+    Text {
+        index: usize,
+        delta: String,
+        text: String,
+    },
+    /// Thinking was added to the block at `index`; `thinking` is the block so far.
+    Thinking {
+        index: usize,
+        delta: String,
+        thinking: String,
+    },
+    /// A tool use block started or its input grew.
+    ///
+    /// `partial_json` is the raw input received so far and `input` the most
+    /// complete value it parses to, with unterminated strings, arrays and
+    /// objects closed. Past 16KB, `input` is refreshed only once the raw input
+    /// has grown by an eighth.
+    ToolInput {
+        index: usize,
+        id: String,
+        name: String,
+        partial_json: String,
+        input: serde_json::Value,
+    },
+    /// The block at `index` is complete.
+    BlockStop { index: usize, block: ContentBlock },
+    /// The message is complete.
+    Complete(AssistantMessage),
+}
+
+/// Folds the stream events of one message at a time into an
+/// [`AssistantMessage`].
+///
+/// Subagents stream their messages with a `parent_tool_use_id`; keep one
+/// accumulator per `parent_tool_use_id` when their events may interleave.
+#[derive(Debug, Clone, Default)]
+pub struct PartialMessageAccumulator {
+    message: Option<AssistantMessage>,
+    id: Option<String>,
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+    /// Input of tool use blocks still streaming, by block index.
+    tool_inputs: HashMap<usize, PartialJson>,
+}
+
+impl PartialMessageAccumulator {
+    /// An accumulator waiting for a `message_start` event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold in a stream event, returning what changed.
+    ///
+    /// Events whose `parent_tool_use_id` differs from the current message's
+    /// belong to another message and are ignored.
+    pub fn push(&mut self, event: &StreamEvent) -> Option<PartialUpdate> {
+        let decoded = event.decode();
+        let started = matches!(decoded, MessageStreamEvent::MessageStart { .. });
+        if let Some(message) = &self.message {
+            if !started && message.parent_tool_use_id != event.parent_tool_use_id {
+                debug!(
+                    "Ignoring stream event for parent tool use {:?} while accumulating {:?}",
+                    event.parent_tool_use_id, message.parent_tool_use_id
+                );
+                return None;
+            }
+        }
+        let update = self.apply(decoded);
+        if started {
+            if let Some(message) = &mut self.message {
+                message.parent_tool_use_id = event.parent_tool_use_id.clone();
+            }
+        }
+        update
+    }
+
+    /// Fold in an already decoded event, returning what changed.
+    ///
+    /// A `message_start` discards the previous message.
+    pub fn apply(&mut self, event: MessageStreamEvent) -> Option<PartialUpdate> {
+        match event {
+            MessageStreamEvent::MessageStart { message } => {
+                self.id = message.id;
+                self.stop_reason = message.stop_reason;
+                self.usage = message.usage;
+                self.tool_inputs.clear();
+                self.message = Some(AssistantMessage {
+                    content: message.content,
+                    model: message.model,
+                    parent_tool_use_id: None,
+                    error: None,
+                });
+                None
+            }
+            MessageStreamEvent::ContentBlockStart { index, content_block } => {
+                let content = &mut self.message.as_mut()?.content;
+                let update = match &content_block {
+                    ContentBlock::ToolUse { id, name, input }
+                    | ContentBlock::ServerToolUse { id, name, input } => {
+                        self.tool_inputs.insert(index, PartialJson::default());
+                        Some(PartialUpdate::ToolInput {
+                            index,
+                            id: id.clone(),
+                            name: name.clone(),
+                            partial_json: String::new(),
+                            input: input.clone(),
+                        })
+                    }
+                    ContentBlock::Text { text } if !text.is_empty() => Some(PartialUpdate::Text {
+                        index,
+                        delta: text.clone(),
+                        text: text.clone(),
+                    }),
+                    _ => None,
+                };
+                if index < content.len() {
+                    content[index] = content_block;
+                } else {
+                    if index > content.len() {
+                        warn!("Content block {} started after only {} blocks", index, content.len());
+                    }
+                    content.push(content_block);
+                }
+                update
+            }
+            MessageStreamEvent::ContentBlockDelta { index, delta } => {
+                let block = self.message.as_mut()?.content.get_mut(index)?;
+                match (block, delta) {
+                    (ContentBlock::Text { text }, ContentBlockDelta::TextDelta { text: delta }) => {
+                        text.push_str(&delta);
+                        Some(PartialUpdate::Text { index, delta, text: text.clone() })
+                    }
+                    (
+                        ContentBlock::Thinking { thinking, .. },
+                        ContentBlockDelta::ThinkingDelta { thinking: delta },
+                    ) => {
+                        thinking.push_str(&delta);
+                        Some(PartialUpdate::Thinking { index, delta, thinking: thinking.clone() })
+                    }
+                    (
+                        ContentBlock::Thinking { signature, .. },
+                        ContentBlockDelta::SignatureDelta { signature: delta },
+                    ) => {
+                        signature.push_str(&delta);
+                        None
+                    }
+                    (
+                        ContentBlock::ToolUse { id, name, input }
+                        | ContentBlock::ServerToolUse { id, name, input },
+                        ContentBlockDelta::InputJsonDelta { partial_json },
+                    ) => {
+                        let json = self.tool_inputs.entry(index).or_default();
+                        if let Some(snapshot) = json.push(&partial_json) {
+                            *input = snapshot;
+                        }
+                        Some(PartialUpdate::ToolInput {
+                            index,
+                            id: id.clone(),
+                            name: name.clone(),
+                            partial_json: json.text.clone(),
+                            input: input.clone(),
+                        })
+                    }
+                    (block, delta) => {
+                        debug!("Ignoring {:?} for {} block {}", delta, block.block_type(), index);
+                        None
+                    }
+                }
+            }
+            MessageStreamEvent::ContentBlockStop { index } => {
+                let block = self.message.as_mut()?.content.get_mut(index)?;
+                if let Some(json) = self.tool_inputs.remove(&index) {
+                    if let ContentBlock::ToolUse { input, .. } | ContentBlock::ServerToolUse { input, .. } = block {
+                        if !json.text.trim().is_empty() {
+                            match serde_json::from_str(&json.text) {
+                                Ok(value) => *input = value,
+                                Err(e) => warn!("Input of tool use block {} is not valid JSON: {}", index, e),
+                            }
+                        }
+                    }
+                }
+                Some(PartialUpdate::BlockStop { index, block: block.clone() })
+            }
+            MessageStreamEvent::MessageDelta { delta, usage } => {
+                if delta.stop_reason.is_some() {
+                    self.stop_reason = delta.stop_reason;
+                }
+                if let Some(delta) = usage {
+                    let usage = self.usage.get_or_insert_with(Usage::default);
+                    // Output tokens are cumulative; input counts only appear when they changed
+                    usage.output_tokens = delta.output_tokens;
+                    for (total, count) in [
+                        (&mut usage.input_tokens, delta.input_tokens),
+                        (&mut usage.cache_creation_input_tokens, delta.cache_creation_input_tokens),
+                        (&mut usage.cache_read_input_tokens, delta.cache_read_input_tokens),
+                    ] {
+                        if count > 0 {
+                            *total = count;
+                        }
+                    }
+                    if delta.server_tool_use.is_some() {
+                        usage.server_tool_use = delta.server_tool_use;
+                    }
+                }
+                None
+            }
+            MessageStreamEvent::MessageStop => self.message.clone().map(PartialUpdate::Complete),
+            MessageStreamEvent::Error { error } => {
+                warn!("Message stream error: {}", error);
+                None
+            }
+            MessageStreamEvent::Ping | MessageStreamEvent::Unknown(_) => None,
+        }
+    }
+
+    /// The message streamed so far, once a `message_start` arrived.
+    pub fn message(&self) -> Option<&AssistantMessage> {
+        self.message.as_ref()
+    }
+
+    /// The API's message ID.
+    pub fn message_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Why the message ended, once a `message_delta` said so.
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.stop_reason.as_deref()
+    }
+
+    /// Token usage reported so far.
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+}
+
+/// Inputs up to this many bytes get a fresh snapshot on every delta.
+const EAGER_SNAPSHOT_LEN: usize = 16 * 1024;
+
+/// The JSON input of a tool use block, scanned as it streams in so a
+/// snapshot can close unterminated strings, arrays and objects.
+#[derive(Debug, Clone, Default)]
+struct PartialJson {
+    text: String,
+    // Length of `text` when the last snapshot was taken
+    snapshot_len: usize,
+    // Scanner state at the end of `text`
+    closers: Vec<char>,
+    in_string: bool,
+    escaped: bool,
+    expect_key: bool,
+    string_is_key: bool,
+    // The longest prefix that parses once closed, with the closers it needs
+    complete: Option<(usize, Vec<char>)>,
+}
+
+impl PartialJson {
+    /// Append `fragment`, returning a new snapshot when one is due.
+    ///
+    /// Only the fragment is scanned, but a snapshot parses the whole input, so
+    /// past `EAGER_SNAPSHOT_LEN` one is taken only once the input has grown by
+    /// an eighth. That keeps the total work linear in the input size.
+    fn push(&mut self, fragment: &str) -> Option<serde_json::Value> {
+        self.scan(fragment);
+        let len = self.text.len();
+        if len > EAGER_SNAPSHOT_LEN && len - self.snapshot_len < len / 8 {
+            return None;
+        }
+        self.snapshot_len = len;
+        self.snapshot()
+    }
+
+    fn scan(&mut self, fragment: &str) {
+        let offset = self.text.len();
+        self.text.push_str(fragment);
+        for (i, c) in fragment.char_indices() {
+            let i = offset + i;
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if c == '\\' {
+                    self.escaped = true;
+                } else if c == '"' {
+                    self.in_string = false;
+                    if !self.string_is_key {
+                        self.complete = Some((i + 1, self.closers.clone()));
+                    }
+                }
+                continue;
+            }
+            match c {
+                '"' => {
+                    self.in_string = true;
+                    self.string_is_key = self.expect_key;
+                }
+                '{' | '[' => {
+                    self.closers.push(if c == '{' { '}' } else { ']' });
+                    self.expect_key = c == '{';
+                    self.complete = Some((i + 1, self.closers.clone()));
+                }
+                '}' | ']' => {
+                    self.closers.pop();
+                    self.complete = Some((i + 1, self.closers.clone()));
+                }
+                ',' => {
+                    self.complete = Some((i, self.closers.clone()));
+                    self.expect_key = self.closers.last() == Some(&'}');
+                }
+                ':' => self.expect_key = false,
+                _ => {}
+            }
+        }
+    }
+
+    /// The most complete value the input so far parses to, or `None` until
+    /// some prefix parses.
+    fn snapshot(&self) -> Option<serde_json::Value> {
+        let close = |prefix: &str, closers: &[char]| {
+            let mut candidate = prefix.to_string();
+            candidate.extend(closers.iter().rev());
+            serde_json::from_str(&candidate).ok()
+        };
+        let json = self.text.as_str();
+        if self.in_string && !self.string_is_key {
+            // Drop a dangling escape before closing the string
+            let prefix = if self.escaped { &json[..json.len() - 1] } else { json };
+            if let Some(value) = close(&format!("{}\"", prefix), &self.closers) {
+                return Some(value);
+            }
+        } else if !self.in_string {
+            // The whole input, or a trailing number or literal, may already be whole
+            if let Some(value) = close(json, &self.closers) {
+                return Some(value);
+            }
+        }
+        let (end, closers) = self.complete.as_ref()?;
+        close(&json[..*end], closers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stream_event(event: serde_json::Value) -> StreamEvent {
+        StreamEvent {
+            uuid: "u1".to_string(),
+            session_id: "s1".to_string(),
+            event,
+            parent_tool_use_id: None,
+        }
+    }
+
+    #[test]
+    fn test_decode_stream_events() {
+        let event = stream_event(json!({
+            "type": "content_block_delta",
+            "index": 1,
+            "delta": {"type": "input_json_delta", "partial_json": "{\"comm"}
+        }));
+        assert!(matches!(
+            event.decode(),
+            MessageStreamEvent::ContentBlockDelta { index: 1, delta: ContentBlockDelta::InputJsonDelta { .. } }
+        ));
+        assert!(matches!(
+            stream_event(json!({"type": "message_stop"})).decode(),
+            MessageStreamEvent::MessageStop
+        ));
+        assert!(matches!(
+            stream_event(json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 12}})).decode(),
+            MessageStreamEvent::MessageDelta { usage: Some(Usage { output_tokens: 12, .. }), .. }
+        ));
+        assert!(matches!(
+            stream_event(json!({"type": "turn_started"})).decode(),
+            MessageStreamEvent::Unknown(_)
+        ));
+    }
+
+    fn parse_partial_json(json: &str) -> Option<serde_json::Value> {
+        PartialJson::default().push(json)
+    }
+
+    #[test]
+    fn test_parse_partial_json() {
+        assert_eq!(parse_partial_json(""), None);
+        assert_eq!(parse_partial_json("{"), Some(json!({})));
+        assert_eq!(parse_partial_json(r#"{"command": "git st"#), Some(json!({"command": "git st"})));
+        assert_eq!(parse_partial_json(r#"{"command": "ls", "descr"#), Some(json!({"command": "ls"})));
+        assert_eq!(parse_partial_json(r#"{"command": "ls", "description":"#), Some(json!({"command": "ls"})));
+        assert_eq!(parse_partial_json(r#"{"limit": 20"#), Some(json!({"limit": 20})));
+        assert_eq!(parse_partial_json(r#"{"all": tr"#), Some(json!({})));
+        assert_eq!(parse_partial_json(r#"{"paths": ["a", "b"#), Some(json!({"paths": ["a", "b"]})));
+        assert_eq!(parse_partial_json(r#"{"edits": [{"old": "x\"#), Some(json!({"edits": [{"old": "x"}]})));
+        assert_eq!(parse_partial_json(r#"{"a": {"b": 1}, "c"#), Some(json!({"a": {"b": 1}})));
+    }
+
+    #[test]
+    fn test_partial_json_scans_incrementally() {
+        let input = r#"{"edits": [{"old": "a \"quoted\" }", "new": "b"}], "replace_all": false, "n": 12}"#;
+        let mut json = PartialJson::default();
+        for (i, c) in input.char_indices() {
+            let snapshot = json.push(&c.to_string());
+            assert_eq!(snapshot, parse_partial_json(&input[..i + c.len_utf8()]), "after {:?}", &input[..=i]);
+        }
+        assert_eq!(json.snapshot(), serde_json::from_str(input).ok());
+    }
+
+    #[test]
+    fn test_large_partial_json_snapshots_are_throttled() {
+        let mut json = PartialJson::default();
+        json.push(r#"{"content": ""#);
+        let mut snapshots = 0;
+        for _ in 0..10_000 {
+            if json.push("0123456789").is_some() {
+                snapshots += 1;
+            }
+        }
+        // Every delta below the eager limit, then one per eighth of growth
+        assert!(snapshots < EAGER_SNAPSHOT_LEN / 10 + 40, "{} snapshots", snapshots);
+        let content = json.snapshot().unwrap()["content"].as_str().unwrap().len();
+        assert_eq!(content, 100_000);
+    }
+
+    #[test]
+    fn test_accumulator_folds_text_thinking_and_tool_input() {
+        let events = [
+            json!({"type": "message_start", "message": {
+                "id": "msg_1", "type": "message", "role": "assistant", "model": "claude-sonnet-4",
+                "content": [], "stop_reason": null, "usage": {"input_tokens": 10, "output_tokens": 1}
+            }}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": "", "signature": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "List files"}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "sig"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "Let me "}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "text_delta", "text": "check."}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "content_block_start", "index": 2, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "{\"command\": \"ls -"}}),
+            json!({"type": "content_block_delta", "index": 2, "delta": {"type": "input_json_delta", "partial_json": "la\"}"}}),
+            json!({"type": "content_block_stop", "index": 2}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 42}}),
+            json!({"type": "message_stop"}),
+        ];
+
+        let mut accumulator = PartialMessageAccumulator::new();
+        let updates: Vec<_> = events
+            .into_iter()
+            .filter_map(|event| accumulator.push(&stream_event(event)))
+            .collect();
+
+        assert!(matches!(&updates[0], PartialUpdate::Thinking { thinking, .. } if thinking == "List files"));
+        assert!(matches!(&updates[2], PartialUpdate::Text { index: 1, text, .. } if text == "Let me "));
+        assert!(matches!(&updates[3], PartialUpdate::Text { delta, text, .. } if delta == "check." && text == "Let me check."));
+        assert!(matches!(&updates[5], PartialUpdate::ToolInput { name, input, .. } if name == "Bash" && input == &json!({})));
+        assert!(matches!(&updates[6], PartialUpdate::ToolInput { input, .. } if input == &json!({"command": "ls -"})));
+        assert!(matches!(
+            &updates[7],
+            PartialUpdate::ToolInput { partial_json, input, .. }
+                if partial_json == "{\"command\": \"ls -la\"}" && input == &json!({"command": "ls -la"})
+        ));
+        assert!(matches!(&updates[8], PartialUpdate::BlockStop { index: 2, block: ContentBlock::ToolUse { .. } }));
+
+        let PartialUpdate::Complete(message) = updates.last().unwrap() else {
+            panic!("expected the message to complete");
+        };
+        assert_eq!(message.model, "claude-sonnet-4");
+        assert_eq!(message.content.len(), 3);
+        assert!(matches!(&message.content[0], ContentBlock::Thinking { signature, .. } if signature == "sig"));
+        assert!(matches!(&message.content[2], ContentBlock::ToolUse { input, .. } if input == &json!({"command": "ls -la"})));
+        assert_eq!(accumulator.message_id(), Some("msg_1"));
+        assert_eq!(accumulator.stop_reason(), Some("tool_use"));
+        let usage = accumulator.usage().unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (10, 42));
+    }
+
+    #[test]
+    fn test_accumulator_ignores_events_before_message_start() {
+        let mut accumulator = PartialMessageAccumulator::new();
+        let delta = json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "hi"}});
+        assert!(accumulator.push(&stream_event(delta)).is_none());
+        assert!(accumulator.push(&stream_event(json!({"type": "message_stop"}))).is_none());
+        assert!(accumulator.message().is_none());
+    }
+
+    #[test]
+    fn test_accumulator_ignores_events_for_other_parents() {
+        let for_parent = |parent: Option<&str>, event| StreamEvent {
+            parent_tool_use_id: parent.map(str::to_string),
+            ..stream_event(event)
+        };
+        let start = json!({"type": "message_start", "message": {"model": "m", "content": []}});
+        let block = json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}});
+        let delta = json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "hi"}});
+
+        let mut accumulator = PartialMessageAccumulator::new();
+        accumulator.push(&for_parent(Some("toolu_1"), start));
+        accumulator.push(&for_parent(Some("toolu_1"), block));
+        assert!(accumulator.push(&for_parent(None, delta.clone())).is_none());
+        assert!(accumulator.push(&for_parent(Some("toolu_2"), delta.clone())).is_none());
+        assert!(accumulator.push(&for_parent(Some("toolu_1"), delta)).is_some());
+
+        let message = accumulator.message().unwrap();
+        assert_eq!(message.parent_tool_use_id.as_deref(), Some("toolu_1"));
+        assert!(matches!(&message.content[0], ContentBlock::Text { text } if text == "hi"));
+    }
+}
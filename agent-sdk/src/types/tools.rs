@@ -0,0 +1,810 @@
+//! Typed inputs and results of Claude Code's built-in tools.
+//!
+//! Tool inputs follow the schemas the CLI advertises (catalogued in
+//! `docs/tools.json`). [`BuiltinToolInput::parse`] decodes a tool use by tool
+//! name, and [`BuiltinToolResult::parse`] decodes the `tool_use_result` the
+//! CLI attaches to the user message answering it. Tools this SDK does not
+//! model, and payloads that do not parse, are kept as `Unknown`.
+//!
+//! Fields not modelled here are kept in `extra`, so re-serializing an input
+//! (e.g. for `updated_input`) preserves everything the CLI sent. Result fields
+//! are all optional since the CLI does not publish their schemas.
+
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{ContentBlock, ContentBlockContent, MessageContent, Result, Usage, UserMessage};
+
+/// Input of the `Bash` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BashInput {
+    pub command: String,
+    /// Timeout in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_in_background: Option<bool>,
+    #[serde(rename = "dangerouslyDisableSandbox", default, skip_serializing_if = "Option::is_none")]
+    pub dangerously_disable_sandbox: Option<bool>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `Read` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadInput {
+    pub file_path: String,
+    /// First line to read, counting from 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `Write` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteInput {
+    pub file_path: String,
+    pub content: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `Edit` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditInput {
+    pub file_path: String,
+    pub old_string: String,
+    pub new_string: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replace_all: Option<bool>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `MultiEdit` tool: edits applied to one file in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultiEditInput {
+    pub file_path: String,
+    pub edits: Vec<EditOperation>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// One edit of a [`MultiEditInput`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditOperation {
+    pub old_string: String,
+    pub new_string: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replace_all: Option<bool>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `Glob` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobInput {
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `Grep` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrepInput {
+    pub pattern: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glob: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_mode: Option<GrepOutputMode>,
+    /// Lines of context before each match (`-B`).
+    #[serde(rename = "-B", default, skip_serializing_if = "Option::is_none")]
+    pub before_context: Option<u64>,
+    /// Lines of context after each match (`-A`).
+    #[serde(rename = "-A", default, skip_serializing_if = "Option::is_none")]
+    pub after_context: Option<u64>,
+    /// Lines of context around each match (`-C`).
+    #[serde(rename = "-C", default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<u64>,
+    /// Show line numbers (`-n`).
+    #[serde(rename = "-n", default, skip_serializing_if = "Option::is_none")]
+    pub line_numbers: Option<bool>,
+    /// Case insensitive search (`-i`).
+    #[serde(rename = "-i", default, skip_serializing_if = "Option::is_none")]
+    pub case_insensitive: Option<bool>,
+    /// File type filter, e.g. `rust`.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub file_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multiline: Option<bool>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What the `Grep` tool reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrepOutputMode {
+    Content,
+    FilesWithMatches,
+    Count,
+}
+
+/// Input of the `WebFetch` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebFetchInput {
+    pub url: String,
+    pub prompt: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `WebSearch` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebSearchInput {
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_domains: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_domains: Option<Vec<String>>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `TodoWrite` tool: the whole updated list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoWriteInput {
+    pub todos: Vec<Todo>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// An item of the agent's todo list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Todo {
+    pub content: String,
+    pub status: TodoStatus,
+    /// The item phrased as ongoing work, e.g. "Running tests".
+    #[serde(rename = "activeForm")]
+    pub active_form: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Progress of a [`Todo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+/// Input of the `Task` tool, which runs a subagent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskInput {
+    pub description: String,
+    pub prompt: String,
+    pub subagent_type: String,
+    /// `sonnet`, `opus` or `haiku`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Agent ID of a previous run to continue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_in_background: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `AskUserQuestion` tool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AskUserQuestionInput {
+    pub questions: Vec<Question>,
+    /// Answers by question text, filled in by whoever answered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answers: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A question asked through `AskUserQuestion`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Question {
+    pub question: String,
+    /// Short label shown as a chip.
+    pub header: String,
+    pub options: Vec<QuestionOption>,
+    #[serde(rename = "multiSelect", default)]
+    pub multi_select: bool,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A choice offered by a [`Question`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuestionOption {
+    pub label: String,
+    pub description: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Input of the `ExitPlanMode` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExitPlanModeInput {
+    /// The plan, as sent by CLI versions that pass it in the input.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<String>,
+    /// Permissions the plan asks for, by category of action.
+    #[serde(rename = "allowedPrompts", default, skip_serializing_if = "Option::is_none")]
+    pub allowed_prompts: Option<Vec<AllowedPrompt>>,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A permission requested by [`ExitPlanModeInput`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowedPrompt {
+    pub tool: String,
+    /// The kind of action, e.g. "run tests".
+    pub prompt: String,
+    /// Fields not modelled above.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A built-in tool's input, decoded by tool name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuiltinToolInput {
+    Bash(BashInput),
+    Read(ReadInput),
+    Write(WriteInput),
+    Edit(EditInput),
+    MultiEdit(MultiEditInput),
+    Glob(GlobInput),
+    Grep(GrepInput),
+    WebFetch(WebFetchInput),
+    WebSearch(WebSearchInput),
+    TodoWrite(TodoWriteInput),
+    Task(TaskInput),
+    AskUserQuestion(AskUserQuestionInput),
+    ExitPlanMode(ExitPlanModeInput),
+    /// Any other tool (MCP tools included), or input that does not parse.
+    Unknown { name: String, input: serde_json::Value },
+}
+
+impl BuiltinToolInput {
+    /// Decode the input of a `tool_name` tool use.
+    pub fn parse(tool_name: &str, input: &serde_json::Value) -> Self {
+        let parsed = match tool_name {
+            "Bash" => decode(tool_name, input).map(Self::Bash),
+            "Read" => decode(tool_name, input).map(Self::Read),
+            "Write" => decode(tool_name, input).map(Self::Write),
+            "Edit" => decode(tool_name, input).map(Self::Edit),
+            "MultiEdit" => decode(tool_name, input).map(Self::MultiEdit),
+            "Glob" => decode(tool_name, input).map(Self::Glob),
+            "Grep" => decode(tool_name, input).map(Self::Grep),
+            "WebFetch" => decode(tool_name, input).map(Self::WebFetch),
+            "WebSearch" => decode(tool_name, input).map(Self::WebSearch),
+            "TodoWrite" => decode(tool_name, input).map(Self::TodoWrite),
+            "Task" => decode(tool_name, input).map(Self::Task),
+            "AskUserQuestion" => decode(tool_name, input).map(Self::AskUserQuestion),
+            "ExitPlanMode" => decode(tool_name, input).map(Self::ExitPlanMode),
+            _ => None,
+        };
+        parsed.unwrap_or_else(|| Self::Unknown {
+            name: tool_name.to_string(),
+            input: input.clone(),
+        })
+    }
+
+    /// The tool's name.
+    pub fn tool_name(&self) -> &str {
+        match self {
+            Self::Bash(_) => "Bash",
+            Self::Read(_) => "Read",
+            Self::Write(_) => "Write",
+            Self::Edit(_) => "Edit",
+            Self::MultiEdit(_) => "MultiEdit",
+            Self::Glob(_) => "Glob",
+            Self::Grep(_) => "Grep",
+            Self::WebFetch(_) => "WebFetch",
+            Self::WebSearch(_) => "WebSearch",
+            Self::TodoWrite(_) => "TodoWrite",
+            Self::Task(_) => "Task",
+            Self::AskUserQuestion(_) => "AskUserQuestion",
+            Self::ExitPlanMode(_) => "ExitPlanMode",
+            Self::Unknown { name, .. } => name,
+        }
+    }
+
+    /// The input as JSON, e.g. for a permission result's `updated_input`.
+    pub fn to_value(&self) -> serde_json::Value {
+        let value = match self {
+            Self::Bash(input) => serde_json::to_value(input),
+            Self::Read(input) => serde_json::to_value(input),
+            Self::Write(input) => serde_json::to_value(input),
+            Self::Edit(input) => serde_json::to_value(input),
+            Self::MultiEdit(input) => serde_json::to_value(input),
+            Self::Glob(input) => serde_json::to_value(input),
+            Self::Grep(input) => serde_json::to_value(input),
+            Self::WebFetch(input) => serde_json::to_value(input),
+            Self::WebSearch(input) => serde_json::to_value(input),
+            Self::TodoWrite(input) => serde_json::to_value(input),
+            Self::Task(input) => serde_json::to_value(input),
+            Self::AskUserQuestion(input) => serde_json::to_value(input),
+            Self::ExitPlanMode(input) => serde_json::to_value(input),
+            Self::Unknown { input, .. } => return input.clone(),
+        };
+        value.expect("tool inputs serialize to JSON")
+    }
+}
+
+/// `tool_use_result` of the `Bash` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BashResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub interrupted: bool,
+    pub is_image: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_task_id: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `Read` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadResult {
+    /// What was read: `text`, `image`, `notebook` or `pdf`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub file: Option<ReadFile>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The file section of a [`ReadResult`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReadFile {
+    pub file_path: String,
+    pub content: String,
+    pub num_lines: u64,
+    pub start_line: u64,
+    pub total_lines: u64,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `Write`, `Edit` and `MultiEdit` tools.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FileEditResult {
+    /// `create` or `update` for `Write`; absent for edits.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    pub file_path: String,
+    /// Diff hunks of the change.
+    pub structured_patch: Vec<serde_json::Value>,
+    pub user_modified: bool,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `Glob` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GlobResult {
+    pub filenames: Vec<String>,
+    pub num_files: u64,
+    pub truncated: bool,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `Grep` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GrepResult {
+    pub mode: Option<GrepOutputMode>,
+    pub filenames: Vec<String>,
+    pub num_files: u64,
+    /// Matching lines, in `content` mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_lines: Option<u64>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `WebFetch` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WebFetchResult {
+    pub url: String,
+    /// HTTP status code.
+    pub code: u16,
+    pub code_text: String,
+    pub bytes: u64,
+    /// The fetched page as processed by the prompt.
+    pub result: String,
+    pub duration_ms: u64,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `WebSearch` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WebSearchResult {
+    pub query: String,
+    pub results: Vec<serde_json::Value>,
+    pub duration_seconds: f64,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `TodoWrite` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TodoWriteResult {
+    pub old_todos: Vec<Todo>,
+    pub new_todos: Vec<Todo>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `Task` tool.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TaskResult {
+    pub status: String,
+    pub prompt: String,
+    pub agent_id: String,
+    /// The subagent's final answer.
+    pub content: Vec<ContentBlock>,
+    pub total_duration_ms: u64,
+    pub total_tokens: u64,
+    pub total_tool_use_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TaskResult {
+    /// The text blocks of the subagent's answer, joined by newlines.
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `tool_use_result` of the `AskUserQuestion` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AskUserQuestionResult {
+    pub questions: Vec<Question>,
+    /// Answers by question text.
+    pub answers: HashMap<String, String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `tool_use_result` of the `ExitPlanMode` tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExitPlanModeResult {
+    pub plan: Option<String>,
+    pub file_path: Option<String>,
+    pub is_agent: bool,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// A built-in tool's `tool_use_result`, decoded by tool name.
+///
+/// The result does not name its tool; look the name up from the tool use
+/// with the same ID.
+#[derive(Debug, Clone)]
+pub enum BuiltinToolResult {
+    Bash(BashResult),
+    Read(ReadResult),
+    /// `Write`, `Edit` or `MultiEdit`.
+    FileEdit(FileEditResult),
+    Glob(GlobResult),
+    Grep(GrepResult),
+    WebFetch(WebFetchResult),
+    WebSearch(WebSearchResult),
+    TodoWrite(TodoWriteResult),
+    Task(Box<TaskResult>),
+    AskUserQuestion(AskUserQuestionResult),
+    ExitPlanMode(ExitPlanModeResult),
+    /// Any other tool, or a result that is not an object (failed tools
+    /// report their error as a string).
+    Unknown { name: String, result: serde_json::Value },
+}
+
+impl BuiltinToolResult {
+    /// Decode the `tool_use_result` of a `tool_name` tool use.
+    pub fn parse(tool_name: &str, result: &serde_json::Value) -> Self {
+        let parsed = if result.is_object() {
+            match tool_name {
+                "Bash" => decode(tool_name, result).map(Self::Bash),
+                "Read" => decode(tool_name, result).map(Self::Read),
+                "Write" | "Edit" | "MultiEdit" => decode(tool_name, result).map(Self::FileEdit),
+                "Glob" => decode(tool_name, result).map(Self::Glob),
+                "Grep" => decode(tool_name, result).map(Self::Grep),
+                "WebFetch" => decode(tool_name, result).map(Self::WebFetch),
+                "WebSearch" => decode(tool_name, result).map(Self::WebSearch),
+                "TodoWrite" => decode(tool_name, result).map(Self::TodoWrite),
+                "Task" => decode(tool_name, result).map(|task| Self::Task(Box::new(task))),
+                "AskUserQuestion" => decode(tool_name, result).map(Self::AskUserQuestion),
+                "ExitPlanMode" => decode(tool_name, result).map(Self::ExitPlanMode),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        parsed.unwrap_or_else(|| Self::Unknown {
+            name: tool_name.to_string(),
+            result: result.clone(),
+        })
+    }
+}
+
+fn decode<T: DeserializeOwned>(tool_name: &str, value: &serde_json::Value) -> Option<T> {
+    serde_json::from_value(value.clone())
+        .map_err(|e| tracing::debug!("Could not decode {} tool payload: {}", tool_name, e))
+        .ok()
+}
+
+impl ContentBlock {
+    /// The typed input of a `tool_use` block.
+    pub fn as_builtin_tool(&self) -> Option<BuiltinToolInput> {
+        match self {
+            ContentBlock::ToolUse { name, input, .. } => Some(BuiltinToolInput::parse(name, input)),
+            _ => None,
+        }
+    }
+}
+
+impl UserMessage {
+    /// The ID of the tool use this message answers, from its first
+    /// `tool_result` block.
+    pub fn tool_use_id(&self) -> Option<&str> {
+        match &self.content {
+            MessageContent::Blocks(blocks) => blocks.iter().find_map(|block| match block {
+                ContentBlock::ToolResult { tool_use_id, .. } => Some(tool_use_id.as_str()),
+                _ => None,
+            }),
+            MessageContent::String(_) => None,
+        }
+    }
+
+    /// The text of the first `tool_result` block, as the model saw it.
+    pub fn tool_result_text(&self) -> Option<String> {
+        let MessageContent::Blocks(blocks) = &self.content else {
+            return None;
+        };
+        blocks.iter().find_map(|block| match block {
+            ContentBlock::ToolResult { content: Some(ContentBlockContent::String(text)), .. } => {
+                Some(text.clone())
+            }
+            ContentBlock::ToolResult { content: Some(ContentBlockContent::Array(items)), .. } => Some(
+                items
+                    .iter()
+                    .filter_map(|item| item["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            _ => None,
+        })
+    }
+
+    /// Decode `tool_use_result` as `T`, e.g. [`BashResult`].
+    pub fn tool_use_result_as<T: DeserializeOwned>(&self) -> Option<Result<T>> {
+        let result = self.tool_use_result.as_ref()?;
+        Some(serde_json::from_value(result.clone()).map_err(Into::into))
+    }
+
+    /// Decode `tool_use_result` as the result of the built-in `tool_name`.
+    pub fn builtin_tool_result(&self, tool_name: &str) -> Option<BuiltinToolResult> {
+        self.tool_use_result
+            .as_ref()
+            .map(|result| BuiltinToolResult::parse(tool_name, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_builtin_tool_inputs() {
+        let input = BuiltinToolInput::parse(
+            "Bash",
+            &json!({"command": "cargo test", "timeout": 60000, "description": "Run tests"}),
+        );
+        assert!(matches!(&input, BuiltinToolInput::Bash(bash) if bash.command == "cargo test" && bash.timeout == Some(60000)));
+        assert_eq!(input.tool_name(), "Bash");
+
+        let input = BuiltinToolInput::parse(
+            "Grep",
+            &json!({"pattern": "fn main", "output_mode": "content", "-n": true, "-C": 2, "type": "rust"}),
+        );
+        let BuiltinToolInput::Grep(grep) = input else {
+            panic!("expected Grep input");
+        };
+        assert_eq!(grep.output_mode, Some(GrepOutputMode::Content));
+        assert_eq!((grep.line_numbers, grep.context), (Some(true), Some(2)));
+        assert_eq!(grep.file_type.as_deref(), Some("rust"));
+
+        let input = BuiltinToolInput::parse(
+            "TodoWrite",
+            &json!({"todos": [{"content": "Fix bug", "status": "in_progress", "activeForm": "Fixing bug"}]}),
+        );
+        assert!(matches!(&input, BuiltinToolInput::TodoWrite(todo) if todo.todos[0].status == TodoStatus::InProgress));
+
+        let input = BuiltinToolInput::parse(
+            "AskUserQuestion",
+            &json!({"questions": [{
+                "question": "Which database?", "header": "Database", "multiSelect": false,
+                "options": [{"label": "Postgres", "description": "Relational"}, {"label": "Redis", "description": "In-memory"}]
+            }]}),
+        );
+        assert!(matches!(&input, BuiltinToolInput::AskUserQuestion(ask) if ask.questions[0].options.len() == 2));
+
+        let input = BuiltinToolInput::parse(
+            "MultiEdit",
+            &json!({"file_path": "/src/lib.rs", "edits": [{"old_string": "a", "new_string": "b"}]}),
+        );
+        assert!(matches!(&input, BuiltinToolInput::MultiEdit(edit) if edit.edits.len() == 1));
+    }
+
+    #[test]
+    fn test_unknown_tools_and_bad_input_fall_back() {
+        let input = json!({"owner": "acme", "repo": "app"});
+        let parsed = BuiltinToolInput::parse("mcp__github__create_issue", &input);
+        assert!(matches!(&parsed, BuiltinToolInput::Unknown { name, .. } if name == "mcp__github__create_issue"));
+        assert_eq!(parsed.to_value(), input);
+
+        // A required field is missing
+        let parsed = BuiltinToolInput::parse("Read", &json!({"path": "/etc/hosts"}));
+        assert!(matches!(parsed, BuiltinToolInput::Unknown { .. }));
+    }
+
+    #[test]
+    fn test_tool_input_round_trips_to_wire_form() {
+        let block = ContentBlock::ToolUse {
+            id: "toolu_1".to_string(),
+            name: "Edit".to_string(),
+            input: json!({"file_path": "/a.rs", "old_string": "x", "new_string": "y"}),
+        };
+        let Some(BuiltinToolInput::Edit(mut edit)) = block.as_builtin_tool() else {
+            panic!("expected Edit input");
+        };
+        edit.replace_all = Some(true);
+        assert_eq!(
+            BuiltinToolInput::Edit(edit).to_value(),
+            json!({"file_path": "/a.rs", "old_string": "x", "new_string": "y", "replace_all": true})
+        );
+        assert!(ContentBlock::text("hi").as_builtin_tool().is_none());
+    }
+
+    #[test]
+    fn test_unmodelled_input_fields_survive_round_trip() {
+        let inputs = [
+            ("Bash", json!({"command": "sed -i s/a/b/ x", "_simulatedSedEdit": {"file_path": "x"}})),
+            ("Read", json!({"file_path": "/a.rs", "pages": "1-3"})),
+            ("Write", json!({"file_path": "/a.rs", "content": "x", "mode": 420})),
+            ("Edit", json!({"file_path": "/a.rs", "old_string": "x", "new_string": "y", "hint": 1})),
+            ("MultiEdit", json!({"file_path": "/a.rs", "edits": [{"old_string": "a", "new_string": "b", "note": "n"}], "dry_run": true})),
+            ("Glob", json!({"pattern": "*.rs", "limit": 5})),
+            ("Grep", json!({"pattern": "x", "-n": true, "multiline": true, "head_limit": 3, "offset": 2})),
+            ("WebFetch", json!({"url": "https://example.com", "prompt": "p", "format": "md"})),
+            ("WebSearch", json!({"query": "q", "region": "eu"})),
+            ("TodoWrite", json!({"todos": [{"content": "c", "status": "pending", "activeForm": "a", "id": "1"}], "merge": false})),
+            ("Task", json!({"description": "d", "prompt": "p", "subagent_type": "general", "priority": 1})),
+            ("AskUserQuestion", json!({"questions": [{
+                "question": "q", "header": "h", "multiSelect": false, "id": "q1",
+                "options": [{"label": "a", "description": "b", "preview": "c"}]
+            }], "source": "plan"})),
+            ("ExitPlanMode", json!({"plan": "p", "allowedPrompts": [{"tool": "Bash", "prompt": "run tests", "scope": "repo"}], "x": 1})),
+        ];
+        for (tool, input) in &inputs {
+            let parsed = BuiltinToolInput::parse(tool, input);
+            assert!(!matches!(parsed, BuiltinToolInput::Unknown { .. }), "{} did not parse", tool);
+            assert_eq!(&parsed.to_value(), input, "{}", tool);
+        }
+
+        let BuiltinToolInput::Bash(bash) = BuiltinToolInput::parse("Bash", &inputs[0].1) else {
+            panic!("expected Bash input");
+        };
+        assert_eq!(bash.extra["_simulatedSedEdit"]["file_path"], "x");
+    }
+
+    #[test]
+    fn test_user_message_tool_use_result() {
+        let message: UserMessage = serde_json::from_value(json!({
+            "content": [{"type": "tool_result", "tool_use_id": "toolu_1", "content": "hello"}],
+            "tool_use_result": {"stdout": "hello", "stderr": "", "interrupted": false, "isImage": false}
+        }))
+        .unwrap();
+        assert_eq!(message.tool_use_id(), Some("toolu_1"));
+        assert_eq!(message.tool_result_text().as_deref(), Some("hello"));
+
+        let bash: BashResult = message.tool_use_result_as().unwrap().unwrap();
+        assert_eq!(bash.stdout, "hello");
+        assert!(matches!(message.builtin_tool_result("Bash"), Some(BuiltinToolResult::Bash(_))));
+
+        let task = BuiltinToolResult::parse(
+            "Task",
+            &json!({
+                "status": "completed", "agentId": "ae10688",
+                "content": [{"type": "text", "text": "Review done"}],
+                "totalDurationMs": 10777, "totalToolUseCount": 2,
+                "usage": {"input_tokens": 0, "output_tokens": 0}
+            }),
+        );
+        let BuiltinToolResult::Task(task) = task else {
+            panic!("expected Task result");
+        };
+        assert_eq!((task.agent_id.as_str(), task.total_tool_use_count), ("ae10688", 2));
+        assert_eq!(task.text(), "Review done");
+
+        // Failed tools report a plain string
+        let failed = BuiltinToolResult::parse("Bash", &json!("Error: Exit code 1"));
+        assert!(matches!(failed, BuiltinToolResult::Unknown { .. }));
+    }
+}
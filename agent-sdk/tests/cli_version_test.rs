@@ -0,0 +1,95 @@
+//! Tests for the CLI version check done before spawning `claude`.
+
+#![cfg(unix)]
+
+mod common;
+
+use claude_agent_sdk::types::Error;
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient, CliVersion};
+use common::{CliScript, ANSWER_INITIALIZE};
+use std::path::PathBuf;
+
+// A fake `claude` reporting `version`; a real session run leaves a `spawned`
+// file next to it
+fn fake_cli(name: &str, version: &str, help: &str) -> CliScript {
+    CliScript::without_probe(
+        name,
+        &format!(
+            "case \"$1\" in\n  --version) echo '{}'; exit 0;;\n  --help) printf '%s\\n' '{}'; exit 0;;\nesac\ntouch \"$(dirname \"$0\")/spawned\"\n{}while read line; do :; done\n",
+            version, help, ANSWER_INITIALIZE
+        ),
+    )
+}
+
+fn client_for(cli: PathBuf) -> ClaudeClient {
+    ClaudeClient::new(ClaudeAgentOptions {
+        cli_path: Some(cli),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_outdated_cli_is_rejected_before_spawning() {
+    let cli = fake_cli("old-version", "1.0.128 (Claude Code)", "");
+    let mut client = client_for(cli.path());
+
+    match client.connect(None).await {
+        Err(Error::CLIVersion(msg)) => {
+            assert!(msg.contains("1.0.128"), "{}", msg);
+            assert!(msg.contains("2.0.0"), "{}", msg);
+            assert!(msg.contains("npm install -g @anthropic-ai/claude-code"), "{}", msg);
+        }
+        other => panic!("expected version error, got {:?}", other),
+    }
+    assert!(!cli.file("spawned").exists());
+    assert!(client.cli_info().is_none());
+}
+
+#[tokio::test]
+async fn test_unparseable_version_is_rejected() {
+    let cli = fake_cli("bad-version", "claude (unknown build)", "");
+    let mut client = client_for(cli.path());
+
+    match client.connect(None).await {
+        Err(Error::CLIVersion(msg)) => assert!(msg.contains("unknown build"), "{}", msg),
+        other => panic!("expected version error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_supported_cli_exposes_version_and_capabilities() {
+    let cli = fake_cli(
+        "new-version",
+        "2.0.14 (Claude Code)",
+        "Options:\n  --max-budget-usd <amount>  Maximum dollar amount to spend",
+    );
+    let mut client = client_for(cli.path());
+    client.connect(None).await.unwrap();
+    assert!(cli.file("spawned").exists());
+
+    let info = client.cli_info().unwrap();
+    assert_eq!(info.version, CliVersion::new(2, 0, 14));
+    assert!(info.capabilities.max_budget_usd());
+    assert!(!info.capabilities.json_schema());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cli_is_probed_once_per_binary() {
+    let cli = CliScript::without_probe(
+        "probe-once",
+        &format!(
+            "case \"$1\" in\n  --version) echo probed >> \"$(dirname \"$0\")/probes\"; echo '2.1.0 (Claude Code)'; exit 0;;\n  --help) exit 0;;\nesac\n{}while read line; do :; done\n",
+            ANSWER_INITIALIZE
+        ),
+    );
+
+    for _ in 0..2 {
+        let mut client = client_for(cli.path());
+        client.connect(None).await.unwrap();
+        assert_eq!(client.cli_info().unwrap().version, CliVersion::new(2, 1, 0));
+        client.disconnect().await.unwrap();
+    }
+    let probes = std::fs::read_to_string(cli.file("probes")).unwrap();
+    assert_eq!(probes.lines().count(), 1);
+}
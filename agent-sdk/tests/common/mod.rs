@@ -0,0 +1,79 @@
+//! Helpers for integration tests that run a shell script in place of `claude`.
+
+#![allow(dead_code)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Answers the version check made before the CLI is spawned for real.
+pub const VERSION_PROBE: &str =
+    "case \"$1\" in --version) echo '2.1.0 (Claude Code)'; exit 0;; --help) exit 0;; esac\n";
+
+/// Reads the `initialize` request and answers it with success.
+pub const ANSWER_INITIALIZE: &str = r#"read line
+id=$(printf '%s' "$line" | sed 's/.*"request_id":"\([^"]*\)".*/\1/')
+printf '{"type":"control_response","response":{"subtype":"success","request_id":"%s","response":{}}}\n' "$id"
+"#;
+
+/// A fake `claude` script in its own temporary directory, which is removed
+/// when the value is dropped.
+///
+/// Scripts can keep files next to themselves with `"$(dirname "$0")"`.
+pub struct CliScript {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl CliScript {
+    /// A CLI that passes the version check, then runs `body`.
+    pub fn new(name: &str, body: &str) -> Self {
+        Self::without_probe(name, &format!("{}{}", VERSION_PROBE, body))
+    }
+
+    /// A CLI that passes the version check, answers `initialize`, then runs
+    /// `body`.
+    pub fn initialized(name: &str, body: &str) -> Self {
+        Self::new(name, &format!("{}{}", ANSWER_INITIALIZE, body))
+    }
+
+    /// A CLI running `body` as is; it must answer `--version` itself.
+    pub fn without_probe(name: &str, body: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("claude-sdk-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("claude");
+        std::fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Self { dir, path }
+    }
+
+    /// The script, for `ClaudeAgentOptions::cli_path`.
+    pub fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// A file next to the script.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+}
+
+impl Drop for CliScript {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Whether `pid` is alive and not a zombie waiting to be reaped.
+#[cfg(target_os = "linux")]
+pub fn is_running(pid: u32) -> bool {
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => !stat.rsplit(") ").next().unwrap_or("").starts_with('Z'),
+        Err(_) => false,
+    }
+}
+
+/// Read a PID written by a script.
+pub fn read_pid(path: &Path) -> u32 {
+    std::fs::read_to_string(path).unwrap().trim().parse().unwrap()
+}
@@ -0,0 +1,561 @@
+//! End-to-end client tests against the scripted mock CLI.
+//!
+//! These cover permission flows, hooks and interrupts without the real
+//! `claude` binary.
+
+use async_trait::async_trait;
+use claude_agent_sdk::testing::{self, MockCli};
+use claude_agent_sdk::client::ClientPromptInput;
+use claude_agent_sdk::types::{
+    CanUseTool, CancellationSignal, Error, HookCallback, HookContext, HookEvent, HookInput,
+    HookJSONOutput, HookMatcher, InputMessage, Message, PermissionResult, PermissionResultAllow,
+    PermissionResultDeny, PermissionUpdate, Result, SyncHookJSONOutput, ToolPermissionContext,
+};
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+use futures::StreamExt;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+struct DenyBash;
+
+#[async_trait]
+impl CanUseTool for DenyBash {
+    async fn can_use(
+        &self,
+        tool_name: &str,
+        _input: &serde_json::Value,
+        _context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        assert_eq!(tool_name, "Bash");
+        Ok(PermissionResult::Deny(PermissionResultDeny {
+            message: "Bash is disabled".to_string(),
+            ..Default::default()
+        }))
+    }
+}
+
+struct StopHook;
+
+#[async_trait]
+impl HookCallback for StopHook {
+    async fn call(
+        &self,
+        input: HookInput,
+        _tool_use_id: Option<String>,
+        _context: HookContext,
+    ) -> Result<HookJSONOutput> {
+        assert!(matches!(input, HookInput::PreToolUse { .. }));
+        Ok(HookJSONOutput::Sync(SyncHookJSONOutput {
+            continue_: Some(false),
+            suppress_output: None,
+            stop_reason: Some("blocked by test".to_string()),
+            decision: None,
+            system_message: None,
+            reason: None,
+            hook_specific_output: None,
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_conversation_replay() {
+    let (transport, handle) = MockCli::new()
+        .expect(json!({"type": "user", "message": {"role": "user", "content": "Hello"}}))
+        .emit(testing::system_init("session-1"))
+        .emit(testing::assistant_text("Hi there"))
+        .emit(testing::result_success("session-1"))
+        .build();
+
+    let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+    client.connect(None).await.unwrap();
+    let mut response = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+
+    let mut messages = Vec::new();
+    while let Some(message) = response.next().await {
+        messages.push(message.unwrap());
+    }
+    assert_eq!(messages.len(), 3);
+    assert!(matches!(messages[0], Message::System(_)));
+    assert!(matches!(messages[1], Message::Assistant(_)));
+    assert!(matches!(messages[2], Message::Result(_)));
+
+    let info = client.get_server_info().await.unwrap().unwrap();
+    assert_eq!(info.session_id, "session-1");
+    assert_eq!(info.tools, ["Bash", "Read", "Write"]);
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_permission_denied() {
+    let (transport, handle) = MockCli::new()
+        .emit(testing::can_use_tool_request("perm_1", "Bash", json!({"command": "rm -rf /"})))
+        .expect(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": "perm_1",
+                "response": {"behavior": "deny", "message": "Bash is disabled", "interrupt": false}
+            }
+        }))
+        .build();
+
+    let mut options = ClaudeAgentOptions::new();
+    options.can_use_tool = Some(Box::new(DenyBash));
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+/// Accepts whatever the CLI suggests and keeps the context it was given.
+struct AcceptSuggestions {
+    context_tx: Mutex<Option<oneshot::Sender<ToolPermissionContext>>>,
+}
+
+#[async_trait]
+impl CanUseTool for AcceptSuggestions {
+    async fn can_use(
+        &self,
+        _tool_name: &str,
+        _input: &serde_json::Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        if let Some(tx) = self.context_tx.lock().unwrap().take() {
+            let _ = tx.send(context.clone());
+        }
+        Ok(PermissionResult::Allow(PermissionResultAllow {
+            updated_permissions: Some(context.suggestions.clone()),
+            ..Default::default()
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_permission_context_carries_request_details() {
+    let mut request = testing::can_use_tool_request("perm_1", "Read", json!({"file_path": "/data/a.txt"}));
+    request["request"]["tool_use_id"] = json!("toolu_01");
+    request["request"]["blocked_path"] = json!("/data/a.txt");
+    request["request"]["agent_id"] = json!("agent-7");
+    request["request"]["permission_suggestions"] = json!([
+        {"type": "addDirectories", "directories": ["/data"], "destination": "session"},
+        {"type": "somethingNewer", "destination": "session"}
+    ]);
+    let (transport, handle) = MockCli::new()
+        .emit(request)
+        .expect(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": "perm_1",
+                "response": {
+                    "behavior": "allow",
+                    "updatedPermissions": [
+                        {"type": "addDirectories", "directories": ["/data"], "destination": "session"}
+                    ]
+                }
+            }
+        }))
+        .build();
+
+    let (context_tx, context_rx) = oneshot::channel();
+    let mut options = ClaudeAgentOptions::new();
+    options.can_use_tool = Some(Box::new(AcceptSuggestions {
+        context_tx: Mutex::new(Some(context_tx)),
+    }));
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    let context = context_rx.await.unwrap();
+    assert_eq!(context.tool_use_id.as_deref(), Some("toolu_01"));
+    assert_eq!(context.blocked_path.as_deref(), Some("/data/a.txt"));
+    assert_eq!(context.agent_id.as_deref(), Some("agent-7"));
+    assert!(matches!(
+        context.suggestions.as_slice(),
+        [PermissionUpdate::AddDirectories { directories, .. }] if directories == &["/data"]
+    ));
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_hook_callback_round_trip() {
+    let (transport, handle) = MockCli::new()
+        .expect(json!({
+            "type": "control_request",
+            "request": {
+                "subtype": "initialize",
+                "hooks": {"PreToolUse": [{"matcher": "Bash", "hookCallbackIds": ["hook_0"]}]}
+            }
+        }))
+        .emit(testing::hook_callback_request(
+            "hook_req_1",
+            "hook_0",
+            json!({
+                "hook_event_name": "PreToolUse",
+                "session_id": "session-1",
+                "transcript_path": "/tmp/transcript.jsonl",
+                "cwd": "/tmp",
+                "tool_name": "Bash",
+                "tool_input": {"command": "ls"}
+            }),
+        ))
+        .expect(json!({
+            "type": "control_response",
+            "response": {
+                "request_id": "hook_req_1",
+                "response": {"continue": false, "stopReason": "blocked by test"}
+            }
+        }))
+        .build();
+
+    let mut hooks = HashMap::new();
+    hooks.insert(
+        HookEvent::PreToolUse,
+        vec![HookMatcher {
+            matcher: Some("Bash".to_string()),
+            hooks: vec![Box::new(StopHook) as Box<dyn HookCallback>],
+            timeout: None,
+        }],
+    );
+    let mut options = ClaudeAgentOptions::new();
+    options.hooks = Some(hooks);
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_interrupt_acknowledged() {
+    let (transport, handle) = MockCli::new()
+        .respond_to_control("interrupt", json!({}))
+        .build();
+
+    let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+    client.connect(None).await.unwrap();
+    client.interrupt().await.unwrap();
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_sdk_writes_are_recorded() {
+    let (transport, handle) = MockCli::new().auto_ack("set_model").build();
+
+    let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+    client.connect(None).await.unwrap();
+    client.set_model(Some("claude-opus-4")).await.unwrap();
+
+    let written = handle.written();
+    let subtypes: Vec<_> = written
+        .iter()
+        .map(|message| message["request"]["subtype"].clone())
+        .collect();
+    assert_eq!(subtypes, vec![json!("initialize"), json!("set_model")]);
+    assert_eq!(written[1]["request"]["model"], "claude-opus-4");
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stream_prompt_is_forwarded_alongside_control_traffic() {
+    let (transport, handle) = MockCli::new()
+        .expect(json!({"type": "user", "message": {"content": "first"}}))
+        .respond_to_control("interrupt", json!({}))
+        .expect(json!({"type": "user", "message": {"content": "second"}}))
+        .build();
+
+    let user = |text: &str| {
+        serde_json::to_value(InputMessage::user(text, "default".to_string())).unwrap()
+    };
+    let (tx, rx) = tokio::sync::mpsc::channel(4);
+    tx.send(user("first")).await.unwrap();
+
+    let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+    client.connect(Some(ClientPromptInput::Stream(rx))).await.unwrap();
+    while handle.written().len() < 2 {
+        tokio::task::yield_now().await;
+    }
+    // The prompt stream is still open while the interrupt goes through
+    client.interrupt().await.unwrap();
+    tx.send(user("second")).await.unwrap();
+    drop(tx);
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pending_request_fails_when_cli_exits() {
+    let (transport, handle) = MockCli::new()
+        .expect(json!({"type": "control_request", "request": {"subtype": "interrupt"}}))
+        .eof()
+        .build();
+
+    let mut client = ClaudeClient::with_transport(ClaudeAgentOptions::new(), transport);
+    client.connect(None).await.unwrap();
+    match client.interrupt().await {
+        Err(Error::Process(msg)) => assert!(msg.contains("closed its output"), "{}", msg),
+        other => panic!("expected process error, got {:?}", other),
+    }
+    assert!(client.process_exit().is_some());
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+fn burst_script() -> MockCli {
+    let mut cli = MockCli::new().expect(json!({"type": "user"}));
+    for n in 0..6 {
+        cli = cli.emit(testing::assistant_text(&format!("part {}", n)));
+    }
+    cli.emit(testing::result_success("session-1"))
+}
+
+// Wait until a lossless session with room for two messages has stopped
+// reading the rest of the burst
+async fn wait_for_stall(handle: &testing::MockCliHandle) {
+    let stalled = async {
+        while handle.unread() != 5 {
+            tokio::task::yield_now().await;
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(5), stalled)
+        .await
+        .expect("session did not stop reading at the full buffer");
+}
+
+#[tokio::test]
+async fn test_slow_stream_reports_lag_and_continues() {
+    let (transport, handle) = burst_script().build();
+    let options = ClaudeAgentOptions::new().with_event_buffer_capacity(2);
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    let mut response = client.receive_response().await.unwrap();
+    let mut observer = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+    handle.finish().await.unwrap();
+    // Once another stream reaches the result, everything has been published
+    // while `response` was not polled
+    while let Some(message) = observer.next().await {
+        if matches!(message, Ok(Message::Result(_))) {
+            break;
+        }
+    }
+
+    assert!(matches!(response.next().await, Some(Err(Error::Lagged(_)))));
+    let rest: Vec<_> = response.collect().await;
+    assert!(matches!(rest.last(), Some(Ok(Message::Result(_)))));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_lossless_stream_receives_every_message() {
+    let (transport, handle) = burst_script().build();
+    let options = ClaudeAgentOptions::new()
+        .with_event_buffer_capacity(2)
+        .with_lossless_events(true);
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    let mut response = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+    wait_for_stall(&handle).await;
+
+    let mut messages = Vec::new();
+    while let Some(message) = response.next().await {
+        messages.push(message.unwrap());
+    }
+    assert_eq!(messages.len(), 7);
+    assert!(matches!(messages[6], Message::Result(_)));
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stalled_lossless_stream_does_not_block_interrupt() {
+    let (transport, handle) = burst_script()
+        .respond_to_control("interrupt", json!({}))
+        .build();
+    let options = ClaudeAgentOptions::new()
+        .with_event_buffer_capacity(2)
+        .with_lossless_events(true);
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    let response = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+    wait_for_stall(&handle).await;
+    {
+        let interrupt = client.interrupt();
+        tokio::pin!(interrupt);
+        // The interrupt reaches the CLI while the stream holds back its output,
+        // so its answer is queued behind the unread messages
+        tokio::select! {
+            result = &mut interrupt => panic!("interrupt answered early: {:?}", result),
+            finished = handle.finish() => finished.unwrap(),
+        }
+
+        let messages: Vec<_> = response.collect().await;
+        assert_eq!(messages.len(), 7);
+        interrupt.await.unwrap();
+    }
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_stalled_lossless_stream_does_not_block_disconnect() {
+    let (transport, handle) = burst_script().build();
+    let options = ClaudeAgentOptions::new()
+        .with_event_buffer_capacity(2)
+        .with_lossless_events(true);
+    let mut client = ClaudeClient::with_transport(options, transport);
+    client.connect(None).await.unwrap();
+
+    let response = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+    wait_for_stall(&handle).await;
+    handle.finish().await.unwrap();
+
+    client.disconnect().await.unwrap();
+    // The session stopped, so the stream ends after what was buffered
+    let messages = tokio::time::timeout(std::time::Duration::from_secs(5), response.collect::<Vec<_>>())
+        .await
+        .expect("stream did not end after disconnect");
+    assert!(messages.len() < 7);
+}
+
+/// Never answers; reports when it is called and when its signal fires.
+struct WaitForHuman {
+    started_tx: Mutex<Option<oneshot::Sender<()>>>,
+    cancelled_tx: Mutex<Option<oneshot::Sender<CancellationSignal>>>,
+}
+
+impl WaitForHuman {
+    fn new() -> (Self, oneshot::Receiver<()>, oneshot::Receiver<CancellationSignal>) {
+        let (started_tx, started_rx) = oneshot::channel();
+        let (cancelled_tx, cancelled_rx) = oneshot::channel();
+        let callback = Self {
+            started_tx: Mutex::new(Some(started_tx)),
+            cancelled_tx: Mutex::new(Some(cancelled_tx)),
+        };
+        (callback, started_rx, cancelled_rx)
+    }
+}
+
+#[async_trait]
+impl CanUseTool for WaitForHuman {
+    async fn can_use(
+        &self,
+        _tool_name: &str,
+        _input: &serde_json::Value,
+        context: &ToolPermissionContext,
+    ) -> Result<PermissionResult> {
+        let signal = context.signal.clone().expect("signal");
+        let cancelled_tx = self.cancelled_tx.lock().unwrap().take();
+        // Like a permission dialog being dismissed from elsewhere
+        tokio::spawn(async move {
+            signal.cancelled().await;
+            if let Some(tx) = cancelled_tx {
+                let _ = tx.send(signal);
+            }
+        });
+        if let Some(tx) = self.started_tx.lock().unwrap().take() {
+            let _ = tx.send(());
+        }
+        std::future::pending().await
+    }
+}
+
+fn waiting_client(
+    transport: testing::MockTransport,
+) -> (ClaudeClient, oneshot::Receiver<()>, oneshot::Receiver<CancellationSignal>) {
+    let (callback, started_rx, cancelled_rx) = WaitForHuman::new();
+    let mut options = ClaudeAgentOptions::new();
+    options.can_use_tool = Some(Box::new(callback));
+    (ClaudeClient::with_transport(options, transport), started_rx, cancelled_rx)
+}
+
+#[tokio::test]
+async fn test_pending_permission_does_not_block_session_and_interrupt_cancels_it() {
+    let (transport, handle) = MockCli::new()
+        .expect(json!({"type": "user"}))
+        .emit(testing::can_use_tool_request("perm_1", "Bash", json!({"command": "ls"})))
+        .emit(testing::assistant_text("still streaming"))
+        .respond_to_control("interrupt", json!({}))
+        .expect(json!({
+            "type": "control_response",
+            "response": {"subtype": "error", "request_id": "perm_1"}
+        }))
+        .build();
+
+    let (mut client, started_rx, cancelled_rx) = waiting_client(transport);
+    client.connect(None).await.unwrap();
+    let mut messages = client.receive_messages_from_cc_stdout().await.unwrap();
+    client.send_to_cc("list files", None).await.unwrap();
+
+    // Messages keep flowing while the callback waits
+    started_rx.await.unwrap();
+    match messages.next().await {
+        Some(Ok(Message::Assistant(_))) => {}
+        other => panic!("expected assistant message, got {:?}", other.map(|m| m.is_ok())),
+    }
+    client.interrupt().await.unwrap();
+    cancelled_rx.await.unwrap();
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_disconnect_cancels_pending_permission() {
+    let (transport, handle) = MockCli::new()
+        .emit(testing::can_use_tool_request("perm_1", "Bash", json!({"command": "ls"})))
+        .build();
+
+    let (mut client, started_rx, cancelled_rx) = waiting_client(transport);
+    client.connect(None).await.unwrap();
+    started_rx.await.unwrap();
+    handle.finish().await.unwrap();
+
+    client.disconnect().await.unwrap();
+    cancelled_rx.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_cli_cancel_request_fires_signal_with_tool_use_and_session() {
+    let mut request = testing::can_use_tool_request("perm_1", "Bash", json!({"command": "ls"}));
+    request["request"]["tool_use_id"] = json!("toolu_01");
+    let (transport, handle) = MockCli::new()
+        .emit(testing::system_init("session-1"))
+        .emit(request)
+        .emit(testing::control_cancel_request("perm_1"))
+        .expect(json!({
+            "type": "control_response",
+            "response": {"subtype": "error", "request_id": "perm_1"}
+        }))
+        .build();
+
+    let (mut client, started_rx, cancelled_rx) = waiting_client(transport);
+    client.connect(None).await.unwrap();
+    started_rx.await.unwrap();
+
+    let signal = cancelled_rx.await.unwrap();
+    assert!(signal.is_cancelled());
+    assert_eq!(signal.tool_use_id(), Some("toolu_01"));
+    assert_eq!(signal.session_id(), Some("session-1"));
+
+    handle.finish().await.unwrap();
+    client.disconnect().await.unwrap();
+}
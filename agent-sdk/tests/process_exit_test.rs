@@ -0,0 +1,57 @@
+//! Tests for detecting the CLI process going away.
+//!
+//! A shell script stands in for `claude` so the tests can control exactly
+//! when and how it exits.
+
+#![cfg(unix)]
+
+mod common;
+
+use claude_agent_sdk::types::Error;
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+use common::CliScript;
+use futures::StreamExt;
+
+#[tokio::test]
+async fn test_exit_during_initialize_fails_connect_with_stderr() {
+    let cli = CliScript::new("init-exit", "echo 'boom: invalid API key' >&2\nexit 3\n");
+    let mut client = ClaudeClient::new(ClaudeAgentOptions {
+        cli_path: Some(cli.path()),
+        ..Default::default()
+    });
+
+    match client.connect(None).await {
+        Err(Error::Process(msg)) => {
+            assert!(msg.contains("exit status: 3"), "{}", msg);
+            assert!(msg.contains("boom: invalid API key"), "{}", msg);
+        }
+        other => panic!("expected process error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_crash_mid_turn_ends_response_stream() {
+    let cli = CliScript::initialized("mid-turn-exit", "read line\necho 'panic: out of memory' >&2\nexit 2\n");
+    let mut client = ClaudeClient::new(ClaudeAgentOptions {
+        cli_path: Some(cli.path()),
+        ..Default::default()
+    });
+    client.connect(None).await.unwrap();
+
+    let mut response = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+
+    match response.next().await {
+        Some(Err(Error::Process(msg))) => {
+            assert!(msg.contains("exit status: 2"), "{}", msg);
+            assert!(msg.contains("panic: out of memory"), "{}", msg);
+        }
+        other => panic!("expected process error, got {:?}", other.map(|r| r.is_ok())),
+    }
+    assert!(response.next().await.is_none());
+
+    let exit = client.process_exit().unwrap();
+    assert_eq!(exit.status.and_then(|status| status.code()), Some(2));
+    assert!(matches!(client.interrupt().await, Err(Error::Process(_))));
+    client.disconnect().await.unwrap();
+}
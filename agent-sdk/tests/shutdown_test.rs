@@ -0,0 +1,72 @@
+//! Tests for tearing down the CLI and the processes it spawned.
+//!
+//! The fake `claude` starts a background `sleep` (standing in for a Bash tool
+//! or MCP server) and records its PID, then answers `initialize`.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use claude_agent_sdk::internal::transport::ProcessHandle;
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+use common::{is_running, read_pid, CliScript};
+use std::time::Duration;
+
+// Keep the script alive for the whole test so its directory outlives the CLI
+async fn connected_client(name: &str) -> (ClaudeClient, u32, CliScript) {
+    let cli = CliScript::new(
+        name,
+        &format!(
+            "sleep 30 &\necho $! > \"$(dirname \"$0\")/tool.pid\"\n{}while read line; do :; done\nwait\n",
+            common::ANSWER_INITIALIZE
+        ),
+    );
+    let mut client = ClaudeClient::new(ClaudeAgentOptions {
+        cli_path: Some(cli.path()),
+        ..Default::default()
+    });
+    client.connect(None).await.unwrap();
+    let pid = read_pid(&cli.file("tool.pid"));
+    assert!(is_running(pid));
+    (client, pid, cli)
+}
+
+#[tokio::test]
+async fn test_disconnect_stops_spawned_processes() {
+    let (mut client, tool, _cli) = connected_client("disconnect").await;
+
+    client.shutdown(Duration::from_secs(2)).await.unwrap();
+    assert!(!is_running(tool));
+}
+
+#[tokio::test]
+async fn test_drop_tears_down_in_background() {
+    let (client, tool, _cli) = connected_client("drop").await;
+
+    drop(client);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while is_running(tool) && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert!(!is_running(tool));
+}
+
+#[tokio::test]
+async fn test_process_handle_shutdown_stops_process_group() {
+    use tokio::io::AsyncBufReadExt;
+
+    let mut child = tokio::process::Command::new("sh")
+        .args(["-c", "sleep 30 & echo $!; wait"])
+        .stdout(std::process::Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let mut lines = tokio::io::BufReader::new(stdout).lines();
+    let grandchild: u32 = lines.next_line().await.unwrap().unwrap().parse().unwrap();
+    assert!(is_running(grandchild));
+
+    let mut handle = ProcessHandle::new_group_leader(child);
+    handle.shutdown(Duration::from_secs(5)).await.unwrap();
+    assert!(!is_running(grandchild));
+}
@@ -0,0 +1,67 @@
+//! Tests for draining the CLI's stderr.
+
+#![cfg(unix)]
+
+mod common;
+
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+use common::CliScript;
+use std::sync::{Arc, Mutex};
+
+// Writes well over a pipe buffer's worth of stderr before answering
+// initialize, so the CLI would block if nobody drained it
+fn noisy_cli(name: &str) -> CliScript {
+    CliScript::new(
+        name,
+        &format!(
+            r#"pad=$(head -c 100 /dev/zero | tr '\0' '.')
+i=0
+while [ $i -lt 2000 ]; do
+  echo "debug $i $pad" >&2
+  i=$((i + 1))
+done
+{}while read line; do :; done
+"#,
+            common::ANSWER_INITIALIZE
+        ),
+    )
+}
+
+#[tokio::test]
+async fn test_stderr_reaches_callback_and_ring_buffer() {
+    let cli = noisy_cli("stderr-callback");
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let callback_seen = seen.clone();
+    let options = ClaudeAgentOptions {
+        cli_path: Some(cli.path()),
+        ..Default::default()
+    }
+    .with_stderr(move |line| callback_seen.lock().unwrap().push(line))
+    .with_stderr_buffer_lines(5);
+
+    // Nobody calls `stderr_receiver()`; connecting must not stall
+    let mut client = ClaudeClient::new(options);
+    client.connect(None).await.unwrap();
+
+    // The last lines may still be in flight when initialize completes
+    for _ in 0..100 {
+        if seen.lock().unwrap().len() == 2000 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen.len(), 2000);
+    assert!(seen[0].starts_with("debug 0 "));
+
+    let lines = client.stderr_lines();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[4].starts_with("debug 1999 "));
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn test_clone_keeps_stderr_callback() {
+    let options = ClaudeAgentOptions::new().with_stderr(|_| {});
+    assert!(options.clone().stderr.is_some());
+}
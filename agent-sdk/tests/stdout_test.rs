@@ -0,0 +1,44 @@
+//! Tests for reading the CLI's stdout under `max_buffer_size`.
+
+#![cfg(unix)]
+
+mod common;
+
+use claude_agent_sdk::types::{Error, Message};
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+use common::CliScript;
+use futures::StreamExt;
+
+// Replies to the first user message with an oversized assistant message, a
+// line of invalid UTF-8 and a result
+const SCRIPT: &str = r#"read line
+big=$(head -c 8192 /dev/zero | tr '\0' 'x')
+printf '{"type":"assistant","message":{"model":"m","content":[{"type":"text","text":"%s"}]}}\n' "$big"
+printf '\377\376\n'
+printf '{"type":"result","subtype":"success","duration_ms":1,"duration_api_ms":1,"is_error":false,"num_turns":1,"session_id":"s"}\n'
+while read line; do :; done
+"#;
+
+#[tokio::test]
+async fn test_oversized_line_is_reported_and_stream_continues() {
+    let cli = CliScript::initialized("oversized-line", SCRIPT);
+    let mut client = ClaudeClient::new(
+        ClaudeAgentOptions {
+            cli_path: Some(cli.path()),
+            ..Default::default()
+        }
+        .with_max_buffer_size(4096),
+    );
+    client.connect(None).await.unwrap();
+
+    let mut response = client.receive_response().await.unwrap();
+    client.send_to_cc("Hello", None).await.unwrap();
+
+    match response.next().await {
+        Some(Err(Error::MessageParse(msg))) => assert!(msg.contains("4096"), "{}", msg),
+        other => panic!("expected overflow error, got {:?}", other.map(|r| r.is_ok())),
+    }
+    assert!(matches!(response.next().await, Some(Ok(Message::Result(_)))));
+    assert!(response.next().await.is_none());
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,157 @@
+//! Integration tests for running the client over a custom transport.
+//!
+//! A fake CLI on the other end of an in-memory duplex pipe answers control
+//! requests, so these tests exercise the full session without spawning `claude`.
+
+use claude_agent_sdk::types::Error;
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient, StreamTransport};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, ReadHalf, WriteHalf};
+
+struct FakeCli {
+    lines: tokio::io::Lines<BufReader<ReadHalf<DuplexStream>>>,
+    writer: WriteHalf<DuplexStream>,
+}
+
+impl FakeCli {
+    async fn next_message(&mut self) -> Value {
+        let line = self.lines.next_line().await.unwrap().expect("stdin closed");
+        serde_json::from_str(&line).unwrap()
+    }
+
+    async fn send(&mut self, message: Value) {
+        let line = format!("{}\n", message);
+        self.writer.write_all(line.as_bytes()).await.unwrap();
+    }
+
+    async fn reply_success(&mut self, request: &Value, response: Value) {
+        self.send(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": request["request_id"],
+                "response": response
+            }
+        }))
+        .await;
+    }
+}
+
+fn client_with_fake_cli(options: ClaudeAgentOptions) -> (ClaudeClient, FakeCli) {
+    let (sdk_side, cli_side) = tokio::io::duplex(64 * 1024);
+    let (reader, writer) = tokio::io::split(sdk_side);
+    let (cli_reader, cli_writer) = tokio::io::split(cli_side);
+
+    let client = ClaudeClient::with_transport(options, StreamTransport::new(reader, writer));
+    let cli = FakeCli {
+        lines: BufReader::new(cli_reader).lines(),
+        writer: cli_writer,
+    };
+    (client, cli)
+}
+
+#[tokio::test]
+async fn test_connect_and_set_model_over_custom_transport() {
+    let (mut client, mut cli) = client_with_fake_cli(ClaudeAgentOptions::new());
+
+    let cli_task = tokio::spawn(async move {
+        let init = cli.next_message().await;
+        assert_eq!(init["type"], "control_request");
+        assert_eq!(init["request"]["subtype"], "initialize");
+        cli.reply_success(&init, json!({"commands": []})).await;
+
+        let set_model = cli.next_message().await;
+        assert_eq!(set_model["request"]["subtype"], "set_model");
+        assert_eq!(set_model["request"]["model"], "claude-sonnet-4");
+        cli.reply_success(&set_model, json!({})).await;
+        cli
+    });
+
+    client.connect(None).await.unwrap();
+    client.set_model(Some("claude-sonnet-4")).await.unwrap();
+    assert!(client.stderr_receiver().is_none());
+    assert!(client.process_handle().is_none());
+
+    cli_task.await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_control_error_response_is_surfaced() {
+    let (mut client, mut cli) = client_with_fake_cli(ClaudeAgentOptions::new());
+
+    let cli_task = tokio::spawn(async move {
+        let init = cli.next_message().await;
+        cli.reply_success(&init, json!({})).await;
+
+        let request = cli.next_message().await;
+        cli.send(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "error",
+                "request_id": request["request_id"],
+                "error": "Invalid permission mode"
+            }
+        }))
+        .await;
+        cli
+    });
+
+    client.connect(None).await.unwrap();
+    match client.set_permission_mode("nonsense").await {
+        Err(Error::ControlProtocol(msg)) => assert_eq!(msg, "Invalid permission mode"),
+        other => panic!("expected control protocol error, got {:?}", other),
+    }
+
+    cli_task.await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_control_request_times_out() {
+    let options = ClaudeAgentOptions::new()
+        .with_control_request_timeout(std::time::Duration::from_millis(100));
+    let (mut client, mut cli) = client_with_fake_cli(options);
+
+    let cli_task = tokio::spawn(async move {
+        let init = cli.next_message().await;
+        cli.reply_success(&init, json!({})).await;
+
+        // Read the interrupt but never answer it
+        let request = cli.next_message().await;
+        assert_eq!(request["request"]["subtype"], "interrupt");
+        cli
+    });
+
+    client.connect(None).await.unwrap();
+    let result = client.interrupt().await;
+    assert!(matches!(result, Err(Error::Timeout(_))), "got {:?}", result);
+
+    let _cli = cli_task.await.unwrap();
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_string_prompt_is_sent_as_user_message() {
+    let (mut client, mut cli) = client_with_fake_cli(ClaudeAgentOptions::new());
+
+    let cli_task = tokio::spawn(async move {
+        let init = cli.next_message().await;
+        cli.reply_success(&init, json!({})).await;
+
+        let user = cli.next_message().await;
+        assert_eq!(user["type"], "user");
+        assert_eq!(user["message"]["content"], "Hello!");
+        cli
+    });
+
+    client
+        .connect(Some(claude_agent_sdk::client::ClientPromptInput::String(
+            "Hello!".to_string(),
+        )))
+        .await
+        .unwrap();
+
+    cli_task.await.unwrap();
+    client.disconnect().await.unwrap();
+}
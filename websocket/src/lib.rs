@@ -17,13 +17,13 @@ pub use server::create_router;
 mod tests {
     use std::time::Duration;
 
-    use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient, InputMessage, client::ClientPromptInput};
+    use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient, InputMessage};
     use futures::StreamExt as _;
     use tracing::info;
     use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-    use super::*;
 
     #[tokio::test]
+    #[ignore = "requires a live claude CLI and blocks indefinitely"]
     async fn test_it_compiles() {
         
         tracing_subscriber::registry()
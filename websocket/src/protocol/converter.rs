@@ -35,7 +35,8 @@ pub fn sdk_to_protocol(sdk_msg: &Message, session_id: &str) -> Vec<ServerMessage
                             delta: Delta::Text { text: text.clone() },
                         });
                     }
-                    ContentBlock::ToolUse { id, name, input } => {
+                    ContentBlock::ToolUse { id, name, input }
+                    | ContentBlock::ServerToolUse { id, name, input } => {
                         // Send as separate tool_use message only
                         result.push(ServerMessage::ToolUse {
                             id: Uuid::new_v4().to_string(),
@@ -54,8 +55,20 @@ pub fn sdk_to_protocol(sdk_msg: &Message, session_id: &str) -> Vec<ServerMessage
                             },
                         });
                     }
+                    ContentBlock::WebSearchToolResult { tool_use_id, content } => {
+                        // Server tools run inside the API, so their results arrive
+                        // in the assistant message rather than a user message
+                        result.push(ServerMessage::ToolResult {
+                            id: Uuid::new_v4().to_string(),
+                            session_id: session_id.to_string(),
+                            request_id: tool_use_id.clone(),
+                            tool_use_id: tool_use_id.clone(),
+                            content: serde_json::to_string(content).unwrap_or_default(),
+                            is_error: content["type"] == "web_search_tool_result_error",
+                        });
+                    }
                     other => {
-                        tracing::warn!("Unhandled ContentBlock variant: {:?}", other);
+                        tracing::debug!("Skipping {} block", other.block_type());
                     }
                 }
             }
@@ -77,10 +90,10 @@ pub fn sdk_to_protocol(sdk_msg: &Message, session_id: &str) -> Vec<ServerMessage
             vec![ServerMessage::Result {
                 id: Uuid::new_v4().to_string(),
                 session_id: session_id.to_string(),
-                subtype: match result.subtype.as_str() {
-                    "success" => ResultSubtype::Success,
-                    "error" => ResultSubtype::Error,
-                    "interrupted" => ResultSubtype::Interrupted,
+                subtype: match &result.subtype {
+                    claude_agent_sdk::ResultSubtype::Success => ResultSubtype::Success,
+                    subtype if subtype.is_error() => ResultSubtype::Error,
+                    subtype if subtype.as_str() == "interrupted" => ResultSubtype::Interrupted,
                     unknown => {
                         tracing::warn!("Unknown result subtype '{}', defaulting to Error", unknown);
                         ResultSubtype::Error
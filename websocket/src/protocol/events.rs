@@ -68,6 +68,7 @@ pub struct ControlRequestMessage {
 /// Unified event type emitted by the agent runtime
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum AgentEvent {
     /// Session initialized with session ID and system information
     /// Also used as response to UserSessionInit - success/error fields indicate initialization result
@@ -462,19 +463,6 @@ pub struct TokenUsage {
     pub total_tokens: i64,
 }
 
-/// SDK usage data (from Result message)
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct SdkUsage {
-    #[serde(default)]
-    pub input_tokens: i64,
-    #[serde(default)]
-    pub output_tokens: i64,
-    #[serde(default)]
-    pub cache_read_input_tokens: i64,
-    #[serde(default)]
-    pub cache_creation_input_tokens: i64,
-}
-
 /// File operation type
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -3,7 +3,7 @@
 //! Converts agent-sdk message types to the unified AgentEvent system.
 
 use crate::protocol::event_converter::*;
-use crate::protocol::events::{AgentEvent, SdkUsage, TokenUsage};
+use crate::protocol::events::{AgentEvent, TokenUsage};
 use claude_agent_sdk::{ContentBlock, ContentBlockContent, Message, MessageContent};
 
 /// Convert SDK message to unified AgentEvent(s) with parent tool use context.
@@ -31,7 +31,8 @@ pub fn sdk_to_events_with_parent(
                     ContentBlock::Text { text } => {
                         events.push(create_assistant_message(session_id, text.clone(), false));
                     }
-                    ContentBlock::ToolUse { id, name, input } => {
+                    ContentBlock::ToolUse { id, name, input }
+                    | ContentBlock::ServerToolUse { id, name, input } => {
                         events.push(create_tool_started_with_parent(
                             session_id,
                             name.clone(),
@@ -43,17 +44,23 @@ pub fn sdk_to_events_with_parent(
                     ContentBlock::Thinking { thinking, .. } => {
                         events.push(create_assistant_reasoning(session_id, thinking.clone()));
                     }
+                    ContentBlock::WebSearchToolResult { tool_use_id, content } => {
+                        events.push(web_search_completed(
+                            session_id,
+                            tool_use_id,
+                            content,
+                            effective_parent.map(|s| s.to_string()),
+                        ));
+                    }
                     other => {
-                        tracing::warn!("Unhandled ContentBlock variant: {:?}", other);
+                        tracing::debug!("Skipping {} block", other.block_type());
                     }
                 }
             }
 
             // Mark last text message as final if exists
-            if let Some(last_event) = events.last_mut() {
-                if let AgentEvent::AssistantMessage { is_final, .. } = last_event {
-                    *is_final = true;
-                }
+            if let Some(AgentEvent::AssistantMessage { is_final, .. }) = events.last_mut() {
+                *is_final = true;
             }
 
             events
@@ -63,6 +70,30 @@ pub fn sdk_to_events_with_parent(
     }
 }
 
+/// A `web_search_tool_result` block as a completed tool.
+fn web_search_completed(
+    session_id: &str,
+    tool_use_id: &str,
+    content: &serde_json::Value,
+    parent_tool_use_id: Option<String>,
+) -> AgentEvent {
+    let content_str = serde_json::to_string(content).unwrap_or_default();
+    let success = content["type"] != "web_search_tool_result_error";
+    let (result, error) = if success {
+        (Some(content_str), None)
+    } else {
+        (None, Some(content_str))
+    };
+    create_tool_completed_with_parent(
+        session_id,
+        tool_use_id.to_string(),
+        success,
+        result,
+        error,
+        parent_tool_use_id,
+    )
+}
+
 /// Convert SDK message to unified AgentEvent(s).
 ///
 /// A single SDK message may map to multiple events.
@@ -85,7 +116,8 @@ pub fn sdk_to_events(sdk_msg: &Message, session_id: &str) -> Vec<AgentEvent> {
                             false, // Not final yet
                         ));
                     }
-                    ContentBlock::ToolUse { id, name, input } => {
+                    ContentBlock::ToolUse { id, name, input }
+                    | ContentBlock::ServerToolUse { id, name, input } => {
                         // Tool use started - pass parent_tool_use_id for SubAgent identification
                         events.push(create_tool_started_with_parent(
                             session_id,
@@ -99,41 +131,40 @@ pub fn sdk_to_events(sdk_msg: &Message, session_id: &str) -> Vec<AgentEvent> {
                         // Stream thinking as reasoning
                         events.push(create_assistant_reasoning(session_id, thinking.clone()));
                     }
+                    ContentBlock::WebSearchToolResult { tool_use_id, content } => {
+                        // Server tool results arrive in the assistant message
+                        events.push(web_search_completed(
+                            session_id,
+                            tool_use_id,
+                            content,
+                            parent_tool_use_id.clone(),
+                        ));
+                    }
                     other => {
-                        tracing::warn!("Unhandled ContentBlock variant: {:?}", other);
+                        tracing::debug!("Skipping {} block", other.block_type());
                     }
                 }
             }
 
             // Mark last text message as final if exists
-            if let Some(last_event) = events.last_mut() {
-                if let AgentEvent::AssistantMessage { is_final, .. } = last_event {
-                    *is_final = true;
-                }
+            if let Some(AgentEvent::AssistantMessage { is_final, .. }) = events.last_mut() {
+                *is_final = true;
             }
 
             events
         }
 
         Message::Result(result) => {
-            // Extract token usage from result.usage field using serde
-            let usage = if let Some(ref usage_value) = result.usage {
-                // Deserialize SDK usage data
-                match serde_json::from_value::<SdkUsage>(usage_value.clone()) {
-                    Ok(sdk_usage) => TokenUsage {
-                        input_tokens: sdk_usage.input_tokens,
-                        output_tokens: sdk_usage.output_tokens,
-                        cached_tokens: sdk_usage.cache_read_input_tokens,
-                        total_tokens: sdk_usage.input_tokens + sdk_usage.output_tokens,
-                    },
-                    Err(e) => {
-                        tracing::warn!("Failed to parse usage data: {}", e);
-                        TokenUsage::default()
-                    }
-                }
-            } else {
-                TokenUsage::default()
-            };
+            let usage = result
+                .usage
+                .as_ref()
+                .map(|usage| TokenUsage {
+                    input_tokens: usage.input_tokens as i64,
+                    output_tokens: usage.output_tokens as i64,
+                    cached_tokens: usage.cache_read_input_tokens as i64,
+                    total_tokens: (usage.input_tokens + usage.output_tokens) as i64,
+                })
+                .unwrap_or_default();
 
             if result.is_error {
                 // Combine errors array and result field for error message
@@ -265,6 +296,7 @@ pub fn sdk_to_events(sdk_msg: &Message, session_id: &str) -> Vec<AgentEvent> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use claude_agent_sdk::{ResultSubtype, Usage};
     use serde_json::json;
 
     #[test]
@@ -337,10 +369,8 @@ mod tests {
 
     #[test]
     fn test_sdk_result_success_to_events() {
-        use serde_json::json;
-
         let sdk_msg = Message::Result(claude_agent_sdk::ResultMessage {
-            subtype: "success".to_string(),
+            subtype: ResultSubtype::Success,
             duration_ms: 1500,
             duration_api_ms: 1200,
             num_turns: 3,
@@ -348,11 +378,13 @@ mod tests {
             session_id: "session-123".to_string(),
             result: None,
             total_cost_usd: Some(0.05),
-            usage: Some(json!({
-                "input_tokens": 1773,
-                "output_tokens": 478,
-                "cache_read_input_tokens": 32255
-            })),
+            usage: Some(Usage {
+                input_tokens: 1773,
+                output_tokens: 478,
+                cache_read_input_tokens: 32255,
+                ..Default::default()
+            }),
+            model_usage: Default::default(),
             structured_output: None,
             errors: vec![],
         });
@@ -374,7 +406,7 @@ mod tests {
     #[test]
     fn test_sdk_result_error_to_events() {
         let sdk_msg = Message::Result(claude_agent_sdk::ResultMessage {
-            subtype: "error".to_string(),
+            subtype: ResultSubtype::Unknown("error".to_string()),
             duration_ms: 500,
             duration_api_ms: 400,
             num_turns: 1,
@@ -383,6 +415,7 @@ mod tests {
             result: Some("API error".to_string()),
             total_cost_usd: None,
             usage: None,
+            model_usage: Default::default(),
             structured_output: None,
             errors: vec![],
         });
@@ -401,7 +434,7 @@ mod tests {
     #[test]
     fn test_sdk_result_error_with_errors_array() {
         let sdk_msg = Message::Result(claude_agent_sdk::ResultMessage {
-            subtype: "error_during_execution".to_string(),
+            subtype: ResultSubtype::ErrorDuringExecution,
             duration_ms: 0,
             duration_api_ms: 0,
             num_turns: 0,
@@ -410,6 +443,7 @@ mod tests {
             result: None,
             total_cost_usd: Some(0.0),
             usage: None,
+            model_usage: Default::default(),
             structured_output: None,
             errors: vec!["No conversation found with session ID: abc123".to_string()],
         });
@@ -454,4 +488,47 @@ mod tests {
         assert!(matches!(events[1], AgentEvent::AssistantMessage { .. }));
         assert!(matches!(events[2], AgentEvent::ToolStarted { .. }));
     }
+
+    #[test]
+    fn test_sdk_server_tool_blocks_to_events() {
+        let sdk_msg = Message::Assistant(claude_agent_sdk::AssistantMessage {
+            content: vec![
+                ContentBlock::RedactedThinking {
+                    data: "EmwKAhgB".to_string(),
+                },
+                ContentBlock::ServerToolUse {
+                    id: "srvtoolu_1".to_string(),
+                    name: "web_search".to_string(),
+                    input: json!({"query": "rust"}),
+                },
+                ContentBlock::WebSearchToolResult {
+                    tool_use_id: "srvtoolu_1".to_string(),
+                    content: json!({"type": "web_search_tool_result_error", "error_code": "max_uses_exceeded"}),
+                },
+                ContentBlock::Unknown(json!({"type": "container_upload", "file_id": "file_1"})),
+            ],
+            model: "claude-sonnet-4".to_string(),
+            parent_tool_use_id: None,
+            error: None,
+        });
+
+        let events = sdk_to_events(&sdk_msg, "session-123");
+
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            AgentEvent::ToolStarted { tool_name, tool_id, .. } => {
+                assert_eq!(tool_name, "web_search");
+                assert_eq!(tool_id, "srvtoolu_1");
+            }
+            _ => panic!("Expected ToolStarted event"),
+        }
+        match &events[1] {
+            AgentEvent::ToolCompleted { tool_id, success, error, .. } => {
+                assert_eq!(tool_id, "srvtoolu_1");
+                assert!(!success);
+                assert!(error.as_deref().unwrap().contains("max_uses_exceeded"));
+            }
+            _ => panic!("Expected ToolCompleted event"),
+        }
+    }
 }
@@ -16,7 +16,7 @@ use axum::{
     routing::get,
     Router,
 };
-use claude_agent_sdk::types::ProtocolMessage;
+use claude_agent_sdk::types::{ProtocolMessage, SystemEvent, SystemInit};
 use futures::{pin_mut, SinkExt, StreamExt, stream::SplitStream};
 use rust_embed::RustEmbed;
 use serde::Deserialize;
@@ -302,11 +302,11 @@ async fn handle_socket(socket: WebSocket, _state: AppState, session_id: String)
             claude_code_version: session_init_data.claude_code_version.clone(),
             error: None,
         };
-        if let Ok(json) = serde_json::to_string(&response) {
-            if let Err(e) = ws_sender.send(WsMessage::Text(json)).await {
-                error!("Failed to send workspace_init_output for session {}: {}", session_id, e);
-                return;
-            }
+        if let Ok(json) = serde_json::to_string(&response)
+            && let Err(e) = ws_sender.send(WsMessage::Text(json)).await
+        {
+            error!("Failed to send workspace_init_output for session {}: {}", session_id, e);
+            return;
         }
     } else {
         // Send session_init format (legacy)
@@ -316,11 +316,11 @@ async fn handle_socket(socket: WebSocket, _state: AppState, session_id: String)
             error: None,
             data: session_init_data,
         };
-        if let Ok(json) = serde_json::to_string(&init_response) {
-            if let Err(e) = ws_sender.send(WsMessage::Text(json)).await {
-                error!("Failed to send init response for session {}: {}", session_id, e);
-                return;
-            }
+        if let Ok(json) = serde_json::to_string(&init_response)
+            && let Err(e) = ws_sender.send(WsMessage::Text(json)).await
+        {
+            error!("Failed to send init response for session {}: {}", session_id, e);
+            return;
         }
     }
 
@@ -664,10 +664,10 @@ async fn send_error_and_close(
 
 /// 发送 ControlRequestMessage 到 WebSocket
 async fn send_control_request(ws_sender: &Sender<WsMessage>, msg: ControlRequestMessage) {
-    if let Ok(json) = serde_json::to_string(&msg) {
-        if let Err(e) = ws_sender.send(WsMessage::Text(json)).await {
-            error!("Failed to send control request: {}", e);
-        }
+    if let Ok(json) = serde_json::to_string(&msg)
+        && let Err(e) = ws_sender.send(WsMessage::Text(json)).await
+    {
+        error!("Failed to send control request: {}", e);
     }
 }
 
@@ -681,10 +681,10 @@ async fn send_message(ws_sender: &Sender<WsMessage>, session_id: &str, msg: &Pro
         agent_type: "claude".to_string(),
         data: serde_json::to_value(msg).unwrap_or(serde_json::Value::Null),
     };
-    if let Ok(json) = serde_json::to_string(&sidecar_msg) {
-        if let Err(e) = ws_sender.send(WsMessage::Text(json)).await {
-            error!("Failed to send message: {}", e);
-        }
+    if let Ok(json) = serde_json::to_string(&sidecar_msg)
+        && let Err(e) = ws_sender.send(WsMessage::Text(json)).await
+    {
+        error!("Failed to send message: {}", e);
     }
 }
 
@@ -714,6 +714,7 @@ enum QueryStreamResult {
 
 /// Process a query stream, handling messages, permissions, and cancellation.
 /// This is the unified handler for both UserMessage and Query message types.
+#[allow(clippy::too_many_arguments)]
 async fn process_query_stream(
     session: &Session,
     message: String,
@@ -850,6 +851,13 @@ async fn wait_for_session_init(
 
     let client_guard = session.client().lock().await;
 
+    // Subscribe before sending anything: interrupt() now waits for the CLI's
+    // acknowledgement, by which time the init message has already gone out
+    let mut stream = match client_guard.receive_protocol_messages().await {
+        Ok(s) => s,
+        Err(e) => return Err(format!("Failed to subscribe to protocol messages: {}", e)),
+    };
+
     // Send empty query and interrupt to get session init data
     info!("Sending empty query to get session init data for session {}", session.session_id());
 
@@ -864,12 +872,6 @@ async fn wait_for_session_init(
     let _ = client_guard.interrupt().await;
     info!("Sent interrupt after empty query for session {}", session.session_id());
 
-    // Subscribe to protocol messages
-    let mut stream = match client_guard.receive_protocol_messages().await {
-        Ok(s) => s,
-        Err(e) => return Err(format!("Failed to subscribe to protocol messages: {}", e)),
-    };
-
     // Drop the lock before waiting for messages
     drop(client_guard);
 
@@ -890,53 +892,37 @@ async fn wait_for_session_init(
                 info!("Received message from stdout: {:#?}", msg);
                 match &msg {
                     ProtocolMessage::System(system) if system.subtype == "init" => {
-                        let extra = &system.extra;
-
-                        let actual_session_id = extra
-                            .get("session_id")
-                            .and_then(|v| v.as_str())
-                            .unwrap_or(session.session_id())
-                            .to_string();
-
-                        info!("Session initialized, actual session_id: {}", actual_session_id);
-
-                        // Extract session init data
-                        let get_string_array = |key: &str| -> Vec<String> {
-                            extra.get(key)
-                                .and_then(|v| v.as_array())
-                                .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
-                                .unwrap_or_default()
-                        };
-
-                        let get_string = |key: &str| -> Option<String> {
-                            extra.get(key).and_then(|v| v.as_str()).map(String::from)
+                        let init = match system.event() {
+                            SystemEvent::Init(init) => *init,
+                            // Carry on with the session ID we already have
+                            _ => {
+                                warn!("Could not decode init message for session {}", session.session_id());
+                                SystemInit::default()
+                            }
                         };
-
-                        let plugins: Vec<PluginInfo> = extra.get("plugins")
-                            .and_then(|v| v.as_array())
-                            .map(|arr| {
-                                arr.iter().filter_map(|p| {
-                                    let name = p.get("name")?.as_str()?.to_string();
-                                    let path = p.get("path")?.as_str()?.to_string();
-                                    Some(PluginInfo { name, path })
-                                }).collect()
-                            })
-                            .unwrap_or_default();
+                        let non_empty = |s: String| (!s.is_empty()).then_some(s);
+                        let actual_session_id = non_empty(init.session_id)
+                            .unwrap_or_else(|| session.session_id().to_string());
+                        info!("Session initialized, actual session_id: {}", actual_session_id);
 
                         let init_data = SessionInitData {
-                            cwd: get_string("cwd"),
-                            model: get_string("model"),
-                            tools: get_string_array("tools"),
-                            mcp_servers: get_string_array("mcp_servers"),
-                            permission_mode: get_string("permissionMode"),
-                            slash_commands: get_string_array("slash_commands"),
-                            api_key_source: get_string("apiKeySource"),
-                            claude_code_version: get_string("claude_code_version"),
-                            output_style: get_string("output_style"),
-                            agents: get_string_array("agents"),
-                            skills: get_string_array("skills"),
-                            plugins,
-                            uuid: get_string("uuid"),
+                            cwd: non_empty(init.cwd),
+                            model: non_empty(init.model),
+                            tools: init.tools,
+                            mcp_servers: init.mcp_servers.into_iter().map(|server| server.name).collect(),
+                            permission_mode: non_empty(init.permission_mode),
+                            slash_commands: init.slash_commands,
+                            api_key_source: non_empty(init.api_key_source),
+                            claude_code_version: init.claude_code_version,
+                            output_style: init.output_style,
+                            agents: init.agents,
+                            skills: init.skills,
+                            plugins: init
+                                .plugins
+                                .into_iter()
+                                .map(|plugin| PluginInfo { name: plugin.name, path: plugin.path })
+                                .collect(),
+                            uuid: init.uuid,
                         };
 
                         return Ok((actual_session_id, init_data));
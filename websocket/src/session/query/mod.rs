@@ -33,7 +33,7 @@ pub type PermissionHandler = Arc<
 >;
 
 /// Query 选项
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct QueryOptions {
     /// 权限模式
     pub permission_mode: PermissionMode,
@@ -47,18 +47,6 @@ pub struct QueryOptions {
     pub disallowed_tools: Option<Vec<String>>,
 }
 
-impl Default for QueryOptions {
-    fn default() -> Self {
-        Self {
-            permission_mode: PermissionMode::default(),
-            permission_handler: None,
-            max_turns: None,
-            env: None,
-            disallowed_tools: None,
-        }
-    }
-}
-
 impl std::fmt::Debug for QueryOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QueryOptions")
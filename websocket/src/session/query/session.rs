@@ -6,9 +6,9 @@ use claude_agent_sdk::types::{
     PermissionResultDeny, PermissionRuleValue, PermissionUpdate, PermissionUpdateDestination,
     ProtocolMessage, ToolPermissionContext,
 };
-use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient};
+use claude_agent_sdk::{ClaudeAgentOptions, ClaudeClient, Transport};
 use futures::stream::{Stream, StreamExt};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -26,13 +26,13 @@ impl CanUseTool for PermissionHandlerAdapter {
         &self,
         tool_name: &str,
         input: &serde_json::Value,
-        _context: &ToolPermissionContext,
+        context: &ToolPermissionContext,
     ) -> claude_agent_sdk::types::error::Result<PermissionResult> {
         info!("🔐 PermissionHandlerAdapter::can_use called for tool: {}", tool_name);
 
         let request = PermissionRequest {
             tool_name: tool_name.to_string(),
-            tool_use_id: None, // ToolPermissionContext 没有 tool_use_id 字段
+            tool_use_id: context.tool_use_id.clone(),
             input: input.clone(),
         };
 
@@ -101,6 +101,29 @@ impl Session {
         })
     }
 
+    /// 使用自定义 Transport 创建会话（例如测试中的 MockCli）
+    pub async fn with_transport(
+        session_id: String,
+        cwd: PathBuf,
+        config: SessionConfig,
+        options: &QueryOptions,
+        transport: impl Transport + 'static,
+    ) -> Result<Self, QueryError> {
+        info!("Creating session {} over custom transport", session_id);
+
+        let agent_options = Self::build_agent_options(&cwd, &config, options);
+
+        let mut client = ClaudeClient::with_transport(agent_options, transport);
+        client.connect(None).await?;
+
+        Ok(Self {
+            session_id,
+            client: Arc::new(Mutex::new(client)),
+            config,
+            cwd,
+        })
+    }
+
     /// 恢复现有会话
     pub async fn resume(
         session_id: String,
@@ -156,12 +179,12 @@ impl Session {
 
     /// 构建 ClaudeAgentOptions
     fn build_agent_options(
-        cwd: &PathBuf,
+        cwd: &Path,
         config: &SessionConfig,
         options: &QueryOptions,
     ) -> ClaudeAgentOptions {
         let mut agent_options = ClaudeAgentOptions::new();
-        agent_options.cwd = Some(cwd.clone());
+        agent_options.cwd = Some(cwd.to_path_buf());
 
         // 转换权限模式
         use claude_agent_sdk::PermissionMode as SdkMode;
@@ -233,15 +256,7 @@ impl Session {
 
             let client_guard = client.lock().await;
 
-            // 发送用户消息
-            let input_msg = InputMessage::user(message, session_id.clone());
-            if let Err(e) = client_guard.send_input_message(input_msg).await {
-                error!("[{}] Failed to send input message: {:?}", session_id, e);
-                yield Err(QueryError::from(e));
-                return;
-            }
-
-            // 订阅协议消息
+            // 先订阅协议消息，避免错过发送后立即到达的响应
             let mut agent_stream = match client_guard.receive_protocol_messages().await {
                 Ok(stream) => stream,
                 Err(e) => {
@@ -251,6 +266,14 @@ impl Session {
                 }
             };
 
+            // 发送用户消息
+            let input_msg = InputMessage::user(message, session_id.clone());
+            if let Err(e) = client_guard.send_input_message(input_msg).await {
+                error!("[{}] Failed to send input message: {:?}", session_id, e);
+                yield Err(QueryError::from(e));
+                return;
+            }
+
             // 释放锁，让其他操作可以进行
             drop(client_guard);
 
@@ -1,7 +1,14 @@
 //! WebSocket protocol integration tests.
 
+use claude_agent_sdk::testing::{self, MockCli};
+use claude_agent_sdk::types::ProtocolMessage;
+use futures::StreamExt;
+use serde_json::json;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
 use websocket::protocol::events::ClientMessage;
 use websocket::protocol::types::*;
+use websocket::session::{PermissionHandler, PermissionRequest, PermissionResponse, QueryOptions, Session};
 
 #[tokio::test]
 async fn test_session_start_flow() {
@@ -18,7 +25,63 @@ async fn test_user_message_flow() {
 #[tokio::test]
 async fn test_permission_request_flow() {
     // Test: tool_use → permission_request → permission_response → tool_result
-    // TODO: Implement test
+    let (transport, handle) = MockCli::new()
+        .expect(json!({"type": "user", "message": {"content": "List files"}}))
+        .emit(testing::system_init("session-123"))
+        .emit(testing::assistant_tool_use("toolu_1", "Bash", json!({"command": "ls"})))
+        .emit(testing::can_use_tool_request("perm_1", "Bash", json!({"command": "ls"})))
+        .expect(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": "perm_1",
+                "response": {"behavior": "allow", "updatedInput": {"command": "ls"}}
+            }
+        }))
+        .emit(testing::assistant_text("Done"))
+        .emit(testing::result_success("session-123"))
+        .build();
+
+    let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    let handler: PermissionHandler = Arc::new(move |request: PermissionRequest| {
+        seen.lock().unwrap().push(request.tool_name.clone());
+        Box::pin(async { PermissionResponse::Allow })
+    });
+    let options = QueryOptions {
+        permission_handler: Some(handler),
+        ..Default::default()
+    };
+
+    let session = Session::with_transport(
+        "session-123".to_string(),
+        std::env::temp_dir(),
+        SessionConfig {
+            permission_mode: PermissionMode::Default,
+            max_turns: None,
+            max_thinking_tokens: None,
+            dangerously_skip_permissions: None,
+            metadata: std::collections::HashMap::new(),
+        },
+        &options,
+        transport,
+    )
+    .await
+    .unwrap();
+
+    let messages: Vec<_> = session
+        .query("List files".to_string(), options.clone(), CancellationToken::new())
+        .collect()
+        .await;
+    assert!(messages.iter().all(Result::is_ok));
+    assert!(matches!(
+        messages.last(),
+        Some(Ok(ProtocolMessage::Result(result))) if !result.is_error
+    ));
+    assert_eq!(*requests.lock().unwrap(), vec!["Bash".to_string()]);
+
+    handle.finish().await.unwrap();
+    session.client().lock().await.disconnect().await.unwrap();
 }
 
 #[tokio::test]